@@ -1,9 +1,11 @@
 mod combinators;
 mod float;
 mod http_bench;
+mod search;
 
 fn main() {
     combinators::bench();
     float::bench();
     http_bench::bench();
+    search::bench();
 }
@@ -1,4 +1,5 @@
 use benchmark_simple::*;
+use chomp1::ascii::HTTP_TOKEN;
 use chomp1::prelude::*;
 use chomp1::{__parse_internal, __parse_internal_or, parse, parser};
 
@@ -30,29 +31,7 @@ struct Header<B> {
 }
 
 fn is_token(c: u8) -> bool {
-    match c {
-        128..=255 => false,
-        0..=31 => false,
-        b'(' => false,
-        b')' => false,
-        b'<' => false,
-        b'>' => false,
-        b'@' => false,
-        b',' => false,
-        b';' => false,
-        b':' => false,
-        b'\\' => false,
-        b'"' => false,
-        b'/' => false,
-        b'[' => false,
-        b']' => false,
-        b'?' => false,
-        b'=' => false,
-        b'{' => false,
-        b'}' => false,
-        b' ' => false,
-        _ => true,
-    }
+    HTTP_TOKEN.contains(c)
 }
 
 fn is_horizontal_space(c: u8) -> bool {
@@ -0,0 +1,83 @@
+use std::iter;
+
+use benchmark_simple::*;
+
+use chomp1::prelude::*;
+use chomp1::search::{self, ByteSet};
+
+macro_rules! function_name {
+    () => {{
+        fn f() {}
+        fn type_name_of<T>(_: T) -> &'static str {
+            std::any::type_name::<T>()
+        }
+        let name = type_name_of(f);
+        match &name[..name.len() - 3].rfind(':') {
+            Some(pos) => &name[pos + 1..name.len() - 3],
+            None => &name[..name.len() - 3],
+        }
+    }};
+}
+
+pub fn bench() {
+    take_till_closure_10k();
+    take_till_memchr_10k();
+    take_till_memchr2_10k();
+    take_while_closure_10k();
+    take_while_byteset_10k();
+}
+
+fn line() -> Vec<u8> {
+    iter::repeat(b'a')
+        .take(10240)
+        .chain(iter::once(b'\n'))
+        .collect()
+}
+
+fn take_till_closure_10k() {
+    let data = line();
+
+    let res = Bench::new().run(&Options::default(), || {
+        parse_only(|i| take_till(i, |c| c == b'\n'), &data)
+    });
+    println!("{}: {}", function_name!(), res);
+}
+
+fn take_till_memchr_10k() {
+    let data = line();
+
+    let res = Bench::new().run(&Options::default(), || {
+        parse_only(|i| search::take_till_bytes(i, b'\n'), &data)
+    });
+    println!("{}: {}", function_name!(), res);
+}
+
+fn take_till_memchr2_10k() {
+    let data = line();
+
+    let res = Bench::new().run(&Options::default(), || {
+        parse_only(|i| search::take_till_bytes(i, (b'\r', b'\n')), &data)
+    });
+    println!("{}: {}", function_name!(), res);
+}
+
+fn take_while_closure_10k() {
+    let data = line();
+
+    let res = Bench::new().run(&Options::default(), || {
+        parse_only(|i| take_while(i, |c| c.is_ascii_alphanumeric()), &data)
+    });
+    println!("{}: {}", function_name!(), res);
+}
+
+fn take_while_byteset_10k() {
+    const ALNUM: ByteSet =
+        ByteSet::from_bytes(b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz");
+
+    let data = line();
+
+    let res = Bench::new().run(&Options::default(), || {
+        parse_only(|i| search::take_while_bytes(i, ALNUM), &data)
+    });
+    println!("{}: {}", function_name!(), res);
+}
@@ -14,6 +14,7 @@ extern crate chomp1;
 use std::env;
 use std::fs::File;
 
+use chomp1::ascii::HTTP_TOKEN;
 use chomp1::buffer::{Source, Stream, StreamError};
 use chomp1::prelude::*;
 
@@ -31,29 +32,7 @@ struct Header<B> {
 }
 
 fn is_token(c: u8) -> bool {
-    match c {
-        128..=255 => false,
-        0..=31 => false,
-        b'(' => false,
-        b')' => false,
-        b'<' => false,
-        b'>' => false,
-        b'@' => false,
-        b',' => false,
-        b';' => false,
-        b':' => false,
-        b'\\' => false,
-        b'"' => false,
-        b'/' => false,
-        b'[' => false,
-        b']' => false,
-        b'?' => false,
-        b'=' => false,
-        b'{' => false,
-        b'}' => false,
-        b' ' => false,
-        _ => true,
-    }
+    HTTP_TOKEN.contains(c)
 }
 
 fn is_horizontal_space(c: u8) -> bool {
@@ -148,7 +127,7 @@ fn main() {
     loop {
         match i.parse(request) {
             Ok(_) => n += 1,
-            Err(StreamError::Retry) => {} // Needed to refill buffer when necessary
+            Err(StreamError::Retry(_)) => {} // Needed to refill buffer when necessary
             Err(StreamError::EndOfInput) => break,
             Err(e) => {
                 panic!("{}: {:?}", n, e);
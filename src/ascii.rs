@@ -7,18 +7,57 @@ use conv::{NoError, ValueFrom};
 
 use crate::combinators::{matched_by, option, or};
 use crate::parsers::{satisfy, skip_while, skip_while1, take_while1, token, Error, SimpleResult};
+use crate::search::ByteSet;
 use crate::types::{Buffer, Input};
 
+/// Lowercase ASCII letters.
+pub const LOWERCASE: ByteSet = ByteSet::from_range(b'a', b'z');
+
+/// Uppercase ASCII letters.
+pub const UPPERCASE: ByteSet = ByteSet::from_range(b'A', b'Z');
+
+/// ASCII letters.
+pub const ALPHA: ByteSet = LOWERCASE.union(UPPERCASE);
+
+/// ASCII digits.
+pub const DIGIT: ByteSet = ByteSet::from_range(b'0', b'9');
+
+/// ASCII letters and digits.
+pub const ALPHANUMERIC: ByteSet = ALPHA.union(DIGIT);
+
+/// Hexadecimal digits, in both upper- and lowercase.
+pub const HEX_DIGIT: ByteSet = DIGIT
+    .union(ByteSet::from_range(b'a', b'f'))
+    .union(ByteSet::from_range(b'A', b'F'));
+
+/// ASCII whitespace, see `is_whitespace`.
+pub const WHITESPACE: ByteSet = ByteSet::from_range(9, 13).union(ByteSet::from_bytes(b" "));
+
+/// Space (' ') and horizontal tab ('\t').
+pub const HORIZONTAL_SPACE: ByteSet = ByteSet::from_bytes(b" \t");
+
+/// Newline ('\n') and carriage return ('\r').
+pub const END_OF_LINE: ByteSet = ByteSet::from_bytes(b"\r\n");
+
+/// Characters allowed in an HTTP token, `tchar` in RFC 7230.
+pub const HTTP_TOKEN: ByteSet = ALPHANUMERIC.union(ByteSet::from_bytes(b"!#$%&'*+-.^_`|~"));
+
+/// Unreserved URI characters, `unreserved` in RFC 3986.
+pub const URI_UNRESERVED: ByteSet = ALPHANUMERIC.union(ByteSet::from_bytes(b"-._~"));
+
+/// The standard base64 alphabet from RFC 4648, excluding the `=` padding.
+pub const BASE64: ByteSet = ALPHANUMERIC.union(ByteSet::from_bytes(b"+/"));
+
 /// Lowercase ASCII predicate.
 #[inline]
 pub fn is_lowercase(c: u8) -> bool {
-    c.is_ascii_lowercase()
+    LOWERCASE.contains(c)
 }
 
 /// Uppercase ASCII character predicate.
 #[inline]
 pub fn is_uppercase(c: u8) -> bool {
-    c.is_ascii_uppercase()
+    UPPERCASE.contains(c)
 }
 
 /// ASCII whitespace predicate.
@@ -33,37 +72,43 @@ pub fn is_uppercase(c: u8) -> bool {
 /// * Space
 #[inline]
 pub fn is_whitespace(c: u8) -> bool {
-    (9..=13).contains(&c) || c == b' '
+    WHITESPACE.contains(c)
 }
 
 /// A predicate which matches either space (' ') or horizontal tab ('\t').
 #[inline]
 pub fn is_horizontal_space(c: u8) -> bool {
-    c == b'\t' || c == b' '
+    HORIZONTAL_SPACE.contains(c)
 }
 
 /// A predicate matching eithr a newline ('\n') or a carriage return ('\r').
 #[inline]
 pub fn is_end_of_line(c: u8) -> bool {
-    c == b'\n' || c == b'\r'
+    END_OF_LINE.contains(c)
 }
 
 /// ASCII digit predicate.
 #[inline]
 pub fn is_digit(c: u8) -> bool {
-    c.is_ascii_digit()
+    DIGIT.contains(c)
 }
 
 /// ASCII alphabetic predicate.
 #[inline]
 pub fn is_alpha(c: u8) -> bool {
-    is_lowercase(c) || is_uppercase(c)
+    ALPHA.contains(c)
 }
 
 /// ASCII alphanumeric predicate.
 #[inline]
 pub fn is_alphanumeric(c: u8) -> bool {
-    is_alpha(c) || is_digit(c)
+    ALPHANUMERIC.contains(c)
+}
+
+/// Hexadecimal digit predicate.
+#[inline]
+pub fn is_hex_digit(c: u8) -> bool {
+    HEX_DIGIT.contains(c)
 }
 
 /// Skips over whitespace.
@@ -415,12 +460,189 @@ pub fn string_ci<I: Input<Token = u8>>(mut i: I, s: &'static [u8]) -> SimpleResu
     }
 }
 
+/// A set of keywords with associated values, stored as a trie for use with
+/// `one_of_strings`.
+///
+/// The trie is built once and can then be shared by any number of parsers.
+/// If the same keyword is given more than once the last value is used.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct Keywords<T> {
+    nodes: Vec<KeywordNode>,
+    values: Vec<T>,
+    case_insensitive: bool,
+}
+
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default)]
+struct KeywordNode {
+    children: Vec<(u8, usize)>,
+    value: Option<usize>,
+}
+
+#[cfg(feature = "std")]
+impl<T: Clone> Keywords<T> {
+    /// Builds a trie matching the given keywords exactly.
+    pub fn new(keywords: &[(&[u8], T)]) -> Self {
+        Self::build(keywords, false)
+    }
+
+    /// Builds a trie matching the given keywords in a case-insensitive manner.
+    /// Only respects ASCII characters for the case-insensitive comparison.
+    pub fn case_insensitive(keywords: &[(&[u8], T)]) -> Self {
+        Self::build(keywords, true)
+    }
+
+    fn build(keywords: &[(&[u8], T)], case_insensitive: bool) -> Self {
+        let mut k = Keywords {
+            nodes: vec![KeywordNode::default()],
+            values: Vec::with_capacity(keywords.len()),
+            case_insensitive,
+        };
+
+        for (word, value) in keywords {
+            let mut node = 0;
+
+            for &c in word.iter() {
+                node = match k.child(node, c) {
+                    Some(n) => n,
+                    None => {
+                        k.nodes.push(KeywordNode::default());
+
+                        let n = k.nodes.len() - 1;
+
+                        k.nodes[node].children.push((c, n));
+
+                        n
+                    }
+                };
+            }
+
+            k.nodes[node].value = Some(k.values.len());
+            k.values.push(value.clone());
+        }
+
+        k
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Keywords<T> {
+    #[inline]
+    fn child(&self, node: usize, c: u8) -> Option<usize> {
+        let ci = self.case_insensitive;
+
+        self.nodes[node]
+            .children
+            .iter()
+            .find(|&&(b, _)| if ci { compare_ci(b, c) } else { b == c })
+            .map(|&(_, n)| n)
+    }
+}
+
+/// Matches the longest keyword in `keywords` and returns its associated value.
+///
+/// The input is scanned in a single pass instead of trying every keyword in
+/// turn. If no keyword matches an error is returned without consuming any
+/// input. If the end of the input is reached while a longer keyword could still
+/// match the parser is considered incomplete.
+///
+/// ```
+/// use chomp1::ascii::{one_of_strings, Keywords};
+/// use chomp1::prelude::parse_only;
+///
+/// #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// enum Log {
+///     Error,
+///     Warning,
+///     Info,
+///     Debug,
+/// }
+///
+/// let levels = Keywords::new(&[
+///     (b"ERROR", Log::Error),
+///     (b"WARN", Log::Warning),
+///     (b"WARNING", Log::Warning),
+///     (b"INFO", Log::Info),
+///     (b"DEBUG", Log::Debug),
+/// ]);
+///
+/// assert_eq!(parse_only(|i| one_of_strings(i, &levels), b"INFO"), Ok(Log::Info));
+/// assert_eq!(parse_only(|i| one_of_strings(i, &levels), b"WARNING"), Ok(Log::Warning));
+///
+/// let levels = Keywords::case_insensitive(&[(b"error", Log::Error), (b"info", Log::Info)]);
+///
+/// assert_eq!(parse_only(|i| one_of_strings(i, &levels), b"Error"), Ok(Log::Error));
+/// ```
+#[cfg(feature = "std")]
+pub fn one_of_strings<I: Input<Token = u8>, T: Clone>(
+    mut i: I,
+    keywords: &Keywords<T>,
+) -> SimpleResult<I, T> {
+    use crate::primitives::Primitives;
+
+    let m = i.mark();
+    let mut node = 0;
+    let mut len = 0;
+    let mut best = keywords.nodes[0].value.map(|v| (0, v));
+
+    i.consume_while(|c| match keywords.child(node, c) {
+        Some(n) => {
+            node = n;
+            len += 1;
+
+            if let Some(v) = keywords.nodes[n].value {
+                best = Some((len, v));
+            }
+
+            true
+        }
+        None => false,
+    });
+
+    let mut i = i.restore(m);
+
+    match best {
+        Some((n, v)) => {
+            i.consume(n);
+
+            i.ret(keywords.values[v].clone())
+        }
+        None => i.err(Error::unexpected()),
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::{compare_ci, float, match_float, string_ci, to_decimal};
+    use super::*;
     use crate::parsers::Error;
     use crate::primitives::IntoInner;
 
+    #[test]
+    fn byte_sets() {
+        for c in 0..=255u8 {
+            assert_eq!(is_lowercase(c), c.is_ascii_lowercase());
+            assert_eq!(is_uppercase(c), c.is_ascii_uppercase());
+            assert_eq!(is_alpha(c), c.is_ascii_alphabetic());
+            assert_eq!(is_digit(c), c.is_ascii_digit());
+            assert_eq!(is_alphanumeric(c), c.is_ascii_alphanumeric());
+            assert_eq!(is_hex_digit(c), c.is_ascii_hexdigit());
+            assert_eq!(is_whitespace(c), (9..=13).contains(&c) || c == b' ');
+        }
+
+        assert!(HTTP_TOKEN.contains(b'!'));
+        assert!(HTTP_TOKEN.contains(b'~'));
+        assert!(!HTTP_TOKEN.contains(b':'));
+        assert!(!HTTP_TOKEN.contains(b' '));
+        assert!(!HTTP_TOKEN.contains(127));
+        assert!(URI_UNRESERVED.contains(b'~'));
+        assert!(!URI_UNRESERVED.contains(b'/'));
+        assert!(BASE64.contains(b'+'));
+        assert!(!BASE64.contains(b'='));
+        assert_eq!((0..=255u8).filter(|&c| BASE64.contains(c)).count(), 64);
+        assert_eq!((0..=255u8).filter(|&c| HTTP_TOKEN.contains(c)).count(), 77);
+    }
+
     macro_rules! test_to_decimal {
         ( $($n:ty),+ ) => { $(
             assert_eq!(to_decimal::<$n, _>(&b""[..]), 0);
@@ -565,6 +787,86 @@ mod test {
         }
     }
 
+    #[test]
+    fn one_of_strings_test() {
+        let k = Keywords::new(&[
+            (b"in", 1),
+            (b"int", 2),
+            (b"integer", 3),
+            (b"if", 4),
+            (b"if", 5),
+        ]);
+
+        assert_eq!(
+            one_of_strings(&b"int x"[..], &k).into_inner(),
+            (&b" x"[..], Ok(2))
+        );
+        assert_eq!(
+            one_of_strings(&b"inte"[..], &k).into_inner(),
+            (&b"e"[..], Ok(2))
+        );
+        assert_eq!(
+            one_of_strings(&b"integers"[..], &k).into_inner(),
+            (&b"s"[..], Ok(3))
+        );
+        assert_eq!(
+            one_of_strings(&b"if"[..], &k).into_inner(),
+            (&b""[..], Ok(5))
+        );
+        assert_eq!(
+            one_of_strings(&b"i"[..], &k).into_inner(),
+            (&b"i"[..], Err(Error::unexpected()))
+        );
+        assert_eq!(
+            one_of_strings(&b"IN"[..], &k).into_inner(),
+            (&b"IN"[..], Err(Error::unexpected()))
+        );
+
+        let k = Keywords::case_insensitive(&[(b"Get", 1), (b"GETS", 2), (b"post", 3)]);
+
+        assert_eq!(
+            one_of_strings(&b"gEt"[..], &k).into_inner(),
+            (&b""[..], Ok(1))
+        );
+        assert_eq!(
+            one_of_strings(&b"gets"[..], &k).into_inner(),
+            (&b""[..], Ok(2))
+        );
+        assert_eq!(
+            one_of_strings(&b"POST"[..], &k).into_inner(),
+            (&b""[..], Ok(3))
+        );
+
+        let k = Keywords::new(&[(b"", 0), (b"a", 1)]);
+
+        assert_eq!(
+            one_of_strings(&b"b"[..], &k).into_inner(),
+            (&b"b"[..], Ok(0))
+        );
+        assert_eq!(
+            one_of_strings(&b"ab"[..], &k).into_inner(),
+            (&b"b"[..], Ok(1))
+        );
+    }
+
+    #[test]
+    fn one_of_strings_incomplete() {
+        use crate::buffer::InputBuf;
+
+        let k = Keywords::new(&[(b"GET", 1), (b"GETS", 2)]);
+
+        let (i, r) = one_of_strings(InputBuf::new(b"GET"), &k).into_inner();
+
+        assert_eq!(r, Ok(1));
+        assert!(i.is_incomplete());
+
+        let (i, r) = one_of_strings(InputBuf::new(b"GET "), &k).into_inner();
+
+        assert_eq!(r, Ok(1));
+        assert!(!i.is_incomplete());
+        assert_eq!(i.len(), 1);
+    }
+
     #[test]
     fn string_ci_test() {
         assert_eq!(
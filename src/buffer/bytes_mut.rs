@@ -0,0 +1,471 @@
+use std::mem;
+
+use bytes::{Buf, Bytes, BytesMut};
+
+use crate::buffer::{merge_needed, Needed, Stream, StreamError};
+use crate::primitives::{Guard, IntoInner};
+use crate::search::Needle;
+use crate::types::{Input, ParseResult};
+
+/// `Bytes` input which might be followed by more data, the `Bytes` analogue of
+/// `InputBuf`.
+#[must_use]
+#[derive(Debug, Eq, PartialEq, Hash)]
+pub struct PartialBytes {
+    /// If this is set a parser has tried to read past the end of this buffer,
+    /// possibly knowing how many more items it needed.
+    incomplete: Option<Option<Needed>>,
+    /// Current buffer
+    bytes: Bytes,
+}
+
+impl PartialBytes {
+    /// Creates a new input buffer with incomplete set to false.
+    #[inline]
+    pub fn new(bytes: Bytes) -> Self {
+        PartialBytes {
+            incomplete: None,
+            bytes,
+        }
+    }
+
+    /// Returns true if parsers want to obtain more data.
+    ///
+    /// The result of the parsing is only accurate if this is false after
+    /// completed parsing.
+    #[inline]
+    pub fn is_incomplete(&self) -> bool {
+        self.incomplete.is_some()
+    }
+
+    /// Returns the number of additional bytes the parsers needed, see
+    /// `InputBuf::needed`.
+    #[inline]
+    pub fn needed(&self) -> Option<Needed> {
+        self.incomplete.and_then(|n| n)
+    }
+
+    /// Marks the buffer as incomplete.
+    #[inline]
+    fn set_incomplete(&mut self, n: Option<Needed>) {
+        self.incomplete = Some(merge_needed(self.incomplete, n));
+    }
+
+    /// Returns the length of the contained buffer, may be an incomplete buffer.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns true if the contained buffer is empty, may return true even when
+    /// incomplete.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+impl IntoInner for PartialBytes {
+    type Inner = Bytes;
+
+    #[inline]
+    fn into_inner(self) -> Self::Inner {
+        self.bytes
+    }
+}
+
+impl Input for PartialBytes {
+    type Buffer = Bytes;
+    type Marker = Bytes;
+    type Token = u8;
+
+    #[inline]
+    fn _peek(&mut self, _g: Guard) -> Option<Self::Token> {
+        let c = self.bytes.first().cloned();
+
+        if c.is_none() {
+            self.set_incomplete(Some(Needed(1)));
+        }
+
+        c
+    }
+
+    #[inline]
+    fn _pop(&mut self, g: Guard) -> Option<Self::Token> {
+        self.bytes._pop(g).or_else(|| {
+            self.set_incomplete(Some(Needed(1)));
+
+            None
+        })
+    }
+
+    #[inline]
+    fn _consume(&mut self, g: Guard, n: usize) -> Option<Self::Buffer> {
+        self.bytes._consume(g, n).or_else(|| {
+            let missing = n - self.bytes.len();
+
+            self.set_incomplete(Some(Needed(missing)));
+
+            None
+        })
+    }
+
+    #[inline]
+    fn _consume_while<F>(&mut self, g: Guard, mut f: F) -> Self::Buffer
+    where
+        F: FnMut(Self::Token) -> bool,
+    {
+        match self.bytes.iter().position(|c| !f(*c)) {
+            Some(n) => self.bytes.split_to(n),
+            None => self._consume_remaining(g),
+        }
+    }
+
+    #[inline]
+    fn _consume_till<N>(&mut self, g: Guard, n: &N) -> Self::Buffer
+    where
+        N: Needle<Self::Token>,
+    {
+        match n.find(&self.bytes) {
+            Some(n) => self.bytes.split_to(n),
+            None => self._consume_remaining(g),
+        }
+    }
+
+    #[inline]
+    fn _consume_prefix(&mut self, _g: Guard, s: &[Self::Token]) -> Result<Self::Buffer, usize> {
+        if self.bytes.starts_with(s) {
+            Ok(self.bytes.split_to(s.len()))
+        } else {
+            let n = self
+                .bytes
+                .iter()
+                .zip(s)
+                .take_while(|&(a, b)| a == b)
+                .count();
+
+            // Ran out of data before finding a mismatch, the next item might
+            // already mismatch and let another parser make progress
+            if n == self.bytes.len() {
+                self.set_incomplete(Some(Needed(1)));
+            }
+
+            self.bytes.advance(n);
+
+            Err(n)
+        }
+    }
+
+    #[inline]
+    fn _consume_from(&mut self, g: Guard, m: Self::Marker) -> Self::Buffer {
+        self.bytes._consume_from(g, m)
+    }
+
+    #[inline]
+    fn _consume_remaining(&mut self, g: Guard) -> Self::Buffer {
+        self.set_incomplete(None);
+
+        self.bytes._consume_remaining(g)
+    }
+
+    #[inline]
+    fn _mark(&self, _g: Guard) -> Self::Marker {
+        // Incomplete state is separate from the parsed state, no matter how we hit
+        // incomplete we want to keep it.
+        self.bytes.clone()
+    }
+
+    #[inline]
+    fn _restore(mut self, _g: Guard, m: Self::Marker) -> Self {
+        self.bytes = m;
+
+        self
+    }
+}
+
+/// Stream parsing frames out of a `BytesMut` accumulator.
+///
+/// Data is appended to the accumulator through `buffer_mut`, for example by
+/// reading from a socket into it. The parsers run on `PartialBytes` and the
+/// `Bytes` buffers they return share storage with the accumulator, so they
+/// can be kept around after more data has been read.
+///
+/// Data which a parser did not consume is copied when more data is appended,
+/// unless it is no longer referenced by any buffer returned from a parser.
+///
+/// A parser which succeeded after reading up to the end of the data, like a
+/// number which could continue in the next read, returns `Incomplete` until
+/// `set_end_of_input` marks that no more data will be appended.
+///
+/// ```
+/// use bytes::Bytes;
+/// use chomp1::buffer::{BytesMutStream, Stream, StreamError};
+/// use chomp1::prelude::{take_while, token};
+///
+/// let mut s = BytesMutStream::new();
+/// let line = |s: &mut BytesMutStream| {
+///     s.parse(|i| take_while(i, |c| c != b'\n').bind(|i, l| token(i, b'\n').map(|_| l)))
+/// };
+///
+/// s.buffer_mut().extend_from_slice(b"foo\nba");
+///
+/// assert_eq!(line(&mut s), Ok(Bytes::from_static(b"foo")));
+/// assert_eq!(line(&mut s), Err(StreamError::Incomplete(None)));
+///
+/// s.buffer_mut().extend_from_slice(b"r\n");
+///
+/// assert_eq!(line(&mut s), Ok(Bytes::from_static(b"bar")));
+/// assert_eq!(line(&mut s), Err(StreamError::EndOfInput));
+/// ```
+#[derive(Debug, Default)]
+pub struct BytesMutStream {
+    /// Data which has been handed to a parser but not consumed, may be shared
+    /// with buffers returned from earlier parsers
+    frozen: Bytes,
+    /// Data appended since the last parse
+    buf: BytesMut,
+    /// No more data will be appended to the accumulator
+    end_of_input: bool,
+}
+
+impl BytesMutStream {
+    /// Creates a new stream with an empty accumulator.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new stream parsing the data in `buf`, data appended to `buf`
+    /// is parsed after it.
+    #[inline]
+    pub fn from_buffer(buf: BytesMut) -> Self {
+        BytesMutStream {
+            frozen: Bytes::new(),
+            buf,
+            end_of_input: false,
+        }
+    }
+
+    /// Borrows the accumulator mutably, data appended to it is parsed after all
+    /// the data which has not yet been parsed.
+    ///
+    /// The accumulator only contains the data appended since the last call to
+    /// `parse`.
+    #[inline]
+    pub fn buffer_mut(&mut self) -> &mut BytesMut {
+        &mut self.buf
+    }
+
+    /// The number of bytes which have not yet been parsed.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.frozen.len() + self.buf.len()
+    }
+
+    /// Returns true if all data has been parsed.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Marks whether the accumulator holds the end of the data, `true` makes
+    /// parsers which succeeded at the end of the data complete instead of
+    /// returning `Incomplete`.
+    #[inline]
+    pub fn set_end_of_input(&mut self, value: bool) {
+        self.end_of_input = value;
+    }
+
+    /// Moves the data appended to the accumulator after the unparsed data.
+    #[inline]
+    fn freeze(&mut self) {
+        if self.buf.is_empty() {
+            return;
+        }
+
+        if self.frozen.is_empty() {
+            self.frozen = self.buf.split().freeze();
+        } else {
+            // Only copies if the data is shared
+            let mut b = BytesMut::from(mem::take(&mut self.frozen));
+
+            b.unsplit(self.buf.split());
+
+            self.frozen = b.freeze();
+        }
+    }
+}
+
+impl<'a, 'i> Stream<'a, 'i> for BytesMutStream {
+    type Input = PartialBytes;
+
+    #[inline]
+    fn parse<F, T, E>(
+        &'a mut self,
+        f: F,
+    ) -> Result<T, StreamError<<Self::Input as Input>::Buffer, E>>
+    where
+        F: FnOnce(Self::Input) -> ParseResult<Self::Input, T, E>,
+        T: 'i,
+        E: 'i,
+    {
+        use crate::primitives::Primitives;
+
+        self.freeze();
+
+        if self.frozen.is_empty() {
+            return Err(StreamError::EndOfInput);
+        }
+
+        match f(PartialBytes::new(self.frozen.clone())).into_inner() {
+            (remainder, Ok(data)) => {
+                if remainder.is_incomplete() && !self.end_of_input {
+                    // The parser might have accepted more data
+                    return Err(StreamError::Incomplete(remainder.needed()));
+                }
+
+                self.frozen = remainder.into_inner();
+
+                Ok(data)
+            }
+            (mut remainder, Err(err)) => {
+                if remainder.is_incomplete() {
+                    Err(StreamError::Incomplete(remainder.needed()))
+                } else {
+                    self.frozen = remainder.bytes.clone();
+
+                    Err(StreamError::ParseError(remainder.consume_remaining(), err))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::{Bytes, BytesMut};
+
+    use super::{BytesMutStream, Needed, PartialBytes};
+    use crate::ascii::decimal;
+    use crate::buffer::{Stream, StreamError};
+    use crate::parsers::{take, token, Error};
+    use crate::primitives::{IntoInner, Primitives};
+    use crate::types::{ParseResult, U8Input};
+
+    /// Netstring, `<length>:<data>,`.
+    fn netstring<I: U8Input>(i: I) -> ParseResult<I, I::Buffer, Error<u8>> {
+        decimal(i)
+            .bind(|i, n| token(i, b':').then(|i| take(i, n)))
+            .bind(|i, s| token(i, b',').map(|_| s))
+    }
+
+    #[test]
+    fn partial_bytes() {
+        let mut b = PartialBytes::new(Bytes::from_static(b"ab"));
+
+        assert_eq!(b.consume(2), Some(Bytes::from_static(b"ab")));
+        assert!(!b.is_incomplete());
+        assert_eq!(b.peek(), None);
+        assert!(b.is_incomplete());
+
+        let mut b = PartialBytes::new(Bytes::from_static(b"ab"));
+
+        assert_eq!(b.consume_prefix(b"abcd"), Err(2));
+        assert!(b.is_incomplete());
+        assert_eq!(b.needed(), Some(Needed(1)));
+
+        let mut b = PartialBytes::new(Bytes::from_static(b"abc"));
+
+        assert_eq!(b.consume_prefix(b"ac"), Err(1));
+        assert!(!b.is_incomplete());
+        assert_eq!(b.consume_till(&b'c'), Bytes::from_static(b"b"));
+        assert!(!b.is_incomplete());
+        assert_eq!(b.consume_while(|c| c == b'c'), Bytes::from_static(b"c"));
+        assert!(b.is_incomplete());
+        assert_eq!(b.into_inner(), Bytes::new());
+    }
+
+    #[test]
+    fn frames() {
+        let mut s = BytesMutStream::from_buffer(BytesMut::from(&b"3:abc,2:de,4:f"[..]));
+
+        let abc = s.parse(netstring).unwrap();
+        let de = s.parse(netstring).unwrap();
+
+        assert_eq!(
+            s.parse(netstring),
+            Err(StreamError::Incomplete(Some(Needed(3))))
+        );
+        assert_eq!(s.len(), 3);
+
+        s.buffer_mut().extend_from_slice(b"ghi,5:");
+
+        assert_eq!(s.parse(netstring), Ok(Bytes::from_static(b"fghi")));
+        assert_eq!(
+            s.parse(netstring),
+            Err(StreamError::Incomplete(Some(Needed(5))))
+        );
+
+        s.buffer_mut().extend_from_slice(b"jklmnx");
+
+        assert_eq!(
+            s.parse(netstring),
+            Err(StreamError::ParseError(
+                Bytes::from_static(b"x"),
+                Error::expected(b',')
+            ))
+        );
+        assert_eq!(s.len(), 1);
+
+        // Frames share storage and outlive the stream
+        assert_eq!(abc.as_ptr().wrapping_add(6), de.as_ptr());
+
+        drop(s);
+
+        assert_eq!(abc, Bytes::from_static(b"abc"));
+        assert_eq!(de, Bytes::from_static(b"de"));
+    }
+
+    #[test]
+    fn split_value() {
+        let mut s = BytesMutStream::new();
+
+        s.buffer_mut().extend_from_slice(b"12");
+
+        assert_eq!(
+            s.parse(decimal::<_, u32>),
+            Err(StreamError::Incomplete(None))
+        );
+        assert_eq!(s.len(), 2);
+
+        s.buffer_mut().extend_from_slice(b"34;");
+
+        assert_eq!(s.parse(decimal::<_, u32>), Ok(1234));
+        assert_eq!(s.parse(|i| token(i, b';')), Ok(b';'));
+
+        s.buffer_mut().extend_from_slice(b"56");
+
+        assert_eq!(
+            s.parse(decimal::<_, u32>),
+            Err(StreamError::Incomplete(None))
+        );
+
+        s.set_end_of_input(true);
+
+        assert_eq!(s.parse(decimal::<_, u32>), Ok(56));
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn end_of_input() {
+        let mut s = BytesMutStream::new();
+
+        assert!(s.is_empty());
+        assert_eq!(s.parse(netstring), Err(StreamError::EndOfInput));
+
+        s.buffer_mut().extend_from_slice(b"1:a,");
+
+        assert_eq!(s.parse(netstring), Ok(Bytes::from_static(b"a")));
+        assert_eq!(s.parse(netstring), Err(StreamError::EndOfInput));
+    }
+}
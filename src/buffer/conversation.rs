@@ -0,0 +1,516 @@
+//! Request/response exchanges over a `Read`+`Write` stream.
+
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::buffer::{
+    Buffer, FixedSizeBuffer, InputBuf, Needed, RWDataSource, Source, Stream, StreamError,
+};
+use crate::types::ParseResult;
+
+/// Error returned when receiving a reply in a `Conversation`.
+#[derive(Debug)]
+pub enum ConversationError<E> {
+    /// The parser failed on the reply, the data it did not consume is left in
+    /// the buffer.
+    Parse(E),
+    /// The stream was closed before the reply was complete, possibly knowing
+    /// how many more bytes the parser needed.
+    Incomplete(Option<Needed>),
+    /// The stream was closed before any part of the reply was received.
+    Closed,
+    /// Reading or writing the stream failed, this includes timeouts.
+    Io(io::Error),
+}
+
+impl<E> ConversationError<E> {
+    /// Returns true if this is an `Io` error caused by the read timeout of the
+    /// stream expiring.
+    ///
+    /// The part of the reply received so far is kept in the buffer, calling
+    /// `Conversation::receive` again keeps waiting for the rest.
+    #[inline]
+    pub fn is_timeout(&self) -> bool {
+        match *self {
+            ConversationError::Io(ref e) => {
+                matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                )
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<E> From<io::Error> for ConversationError<E> {
+    #[inline]
+    fn from(e: io::Error) -> Self {
+        ConversationError::Io(e)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for ConversationError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ConversationError::Parse(ref e) => write!(f, "invalid reply: {}", e),
+            ConversationError::Incomplete(Some(Needed(n))) => {
+                write!(f, "stream closed with {} bytes of the reply missing", n)
+            }
+            ConversationError::Incomplete(None) => write!(f, "stream closed during the reply"),
+            ConversationError::Closed => write!(f, "stream closed"),
+            ConversationError::Io(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> error::Error for ConversationError<E> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ConversationError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Sends requests and parses the replies on a `Read`+`Write` stream, like a
+/// network connection speaking a line-based or framed protocol.
+///
+/// Replies are parsed from a `Source` buffer, so data the peer sent ahead of
+/// time, for example the replies to pipelined requests, is kept for the next
+/// call to `receive`. `Retry` is handled by reading more data and running the
+/// parser again.
+///
+/// Timeouts are provided by the stream, for example through
+/// `TcpStream::set_read_timeout`. A read timing out is returned as an `Io`
+/// error for which `ConversationError::is_timeout` is true.
+///
+/// ```
+/// use std::io::{BufRead, BufReader, Write};
+/// use std::thread;
+///
+/// use chomp1::buffer::{duplex, Conversation};
+/// use chomp1::prelude::{take_while, token, Buffer, SimpleResult, U8Input};
+///
+/// fn line<I: U8Input>(i: I) -> SimpleResult<I, Vec<u8>> {
+///     take_while(i, |c| c != b'\n').bind(|i, l| token(i, b'\n').map(|_| l.into_vec()))
+/// }
+///
+/// let (client, server) = duplex();
+///
+/// // Replies to every line with the line in uppercase
+/// let server = thread::spawn(move || {
+///     let mut r = BufReader::new(server);
+///     let mut l = String::new();
+///
+///     while r.read_line(&mut l).unwrap() > 0 {
+///         r.get_mut().write_all(l.to_uppercase().as_bytes()).unwrap();
+///         l.clear();
+///     }
+/// });
+///
+/// let mut c = Conversation::new(client);
+///
+/// assert_eq!(c.request(b"hello\n", |i| line(i)).unwrap(), b"HELLO");
+/// assert_eq!(c.request(b"world\n", |i| line(i)).unwrap(), b"WORLD");
+///
+/// drop(c);
+/// server.join().unwrap();
+/// ```
+///
+/// Note: Parsers are called with inputs of any lifetime, so the parsed values
+/// cannot borrow from the buffer, and generic parser functions need to be
+/// wrapped in a closure.
+#[derive(Debug)]
+pub struct Conversation<RW: io::Read + io::Write, B: Buffer<u8> = FixedSizeBuffer<u8>> {
+    source: Source<RWDataSource<RW>, B>,
+}
+
+impl<RW: io::Read + io::Write> Conversation<RW, FixedSizeBuffer<u8>> {
+    /// Creates a new `Conversation` on a stream with the default
+    /// `FixedSizeBuffer` settings.
+    #[inline]
+    pub fn new(stream: RW) -> Self {
+        Self::from_source(Source::new_rw(stream))
+    }
+}
+
+impl<RW: io::Read + io::Write, B: Buffer<u8>> Conversation<RW, B> {
+    /// Creates a new `Conversation` from a `Source`, keeping any data already
+    /// in its buffer.
+    ///
+    /// Enables automatic fill on the source.
+    #[inline]
+    pub fn from_source(mut source: Source<RWDataSource<RW>, B>) -> Self {
+        source.set_autofill(true);
+
+        Conversation { source }
+    }
+
+    /// Writes the whole `request` to the stream and flushes it.
+    #[inline]
+    pub fn send(&mut self, request: &[u8]) -> io::Result<()> {
+        use std::io::Write;
+
+        self.source.write_all(request)?;
+        self.source.flush()
+    }
+
+    /// Parses the next reply using `parser`, reading from the stream until the
+    /// parser completes.
+    ///
+    /// Blocks until enough data has arrived, the stream is closed or reading
+    /// from it fails.
+    pub fn receive<F, T, E>(&mut self, mut parser: F) -> Result<T, ConversationError<E>>
+    where
+        F: for<'i> FnMut(InputBuf<'i, u8>) -> ParseResult<InputBuf<'i, u8>, T, E>,
+    {
+        loop {
+            match self.source.parse(&mut parser) {
+                Ok(t) => return Ok(t),
+                Err(StreamError::Retry(_)) => {}
+                Err(StreamError::ParseError(_, e)) => return Err(ConversationError::Parse(e)),
+                Err(StreamError::Incomplete(n)) => return Err(ConversationError::Incomplete(n)),
+                Err(StreamError::EndOfInput) => return Err(ConversationError::Closed),
+                Err(StreamError::IoError(e)) => return Err(ConversationError::Io(e)),
+            }
+        }
+    }
+
+    /// Sends `request` and parses the reply using `parser`.
+    #[inline]
+    pub fn request<F, T, E>(&mut self, request: &[u8], parser: F) -> Result<T, ConversationError<E>>
+    where
+        F: for<'i> FnMut(InputBuf<'i, u8>) -> ParseResult<InputBuf<'i, u8>, T, E>,
+    {
+        self.send(request)?;
+
+        self.receive(parser)
+    }
+
+    /// Borrows the underlying stream.
+    #[inline]
+    pub fn get_ref(&self) -> &RW {
+        self.source.get_ref().get_ref()
+    }
+
+    /// Mutably borrows the underlying stream, for example to change its
+    /// timeouts.
+    ///
+    /// Reading from the stream directly will skip data.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut RW {
+        self.source.get_mut().get_mut()
+    }
+
+    /// Borrows the `Source` the replies are parsed from.
+    #[inline]
+    pub fn source(&self) -> &Source<RWDataSource<RW>, B> {
+        &self.source
+    }
+
+    /// Mutably borrows the `Source` the replies are parsed from, for example to
+    /// `require` the size of a frame.
+    #[inline]
+    pub fn source_mut(&mut self) -> &mut Source<RWDataSource<RW>, B> {
+        &mut self.source
+    }
+
+    /// Consumes self to reveal the `Source`, including any data received but
+    /// not yet parsed.
+    #[inline]
+    pub fn into_source(self) -> Source<RWDataSource<RW>, B> {
+        self.source
+    }
+}
+
+impl<RW: io::Read + io::Write, B: Buffer<u8>> io::Write for Conversation<RW, B> {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.source.write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.source.flush()
+    }
+}
+
+/// Data sent in one direction of a duplex pipe.
+#[derive(Debug, Default)]
+struct Pipe {
+    data: VecDeque<u8>,
+    /// Set when either end is dropped.
+    closed: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    pipe: Mutex<Pipe>,
+    ready: Condvar,
+}
+
+impl Shared {
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, Pipe> {
+        self.pipe.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[inline]
+    fn close(&self) {
+        self.lock().closed = true;
+        self.ready.notify_all();
+    }
+}
+
+/// One end of an in-memory duplex pipe created by `duplex`.
+///
+/// Data written to one end can be read from the other. Reads block until data
+/// is available, returning end of input once the other end has been dropped
+/// and its data has been read. Writes never block and fail with
+/// `io::ErrorKind::BrokenPipe` once the other end has been dropped.
+#[derive(Debug)]
+pub struct DuplexStream {
+    read: Arc<Shared>,
+    write: Arc<Shared>,
+    timeout: Option<Duration>,
+}
+
+/// Creates a connected pair of in-memory streams, useful for testing protocol
+/// implementations without a network connection.
+pub fn duplex() -> (DuplexStream, DuplexStream) {
+    let a = Arc::new(Shared::default());
+    let b = Arc::new(Shared::default());
+
+    (
+        DuplexStream {
+            read: a.clone(),
+            write: b.clone(),
+            timeout: None,
+        },
+        DuplexStream {
+            read: b,
+            write: a,
+            timeout: None,
+        },
+    )
+}
+
+impl DuplexStream {
+    /// Sets the time a read waits for data before failing with
+    /// `io::ErrorKind::TimedOut`, `None` waits forever.
+    #[inline]
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the read timeout of this end.
+    #[inline]
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+impl io::Read for DuplexStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let deadline = self.timeout.map(|t| Instant::now() + t);
+        let mut pipe = self.read.lock();
+
+        loop {
+            if !pipe.data.is_empty() || pipe.closed || buf.is_empty() {
+                return pipe.data.read(buf);
+            }
+
+            pipe = match deadline {
+                None => self
+                    .read
+                    .ready
+                    .wait(pipe)
+                    .unwrap_or_else(PoisonError::into_inner),
+                Some(d) => {
+                    let now = Instant::now();
+
+                    if now >= d {
+                        return Err(io::Error::new(io::ErrorKind::TimedOut, "read timed out"));
+                    }
+
+                    self.read
+                        .ready
+                        .wait_timeout(pipe, d - now)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+            };
+        }
+    }
+}
+
+impl io::Write for DuplexStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut pipe = self.write.lock();
+
+        if pipe.closed {
+            return Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "other end of the pipe was dropped",
+            ));
+        }
+
+        pipe.data.extend(buf);
+
+        self.write.ready.notify_all();
+
+        Ok(buf.len())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for DuplexStream {
+    fn drop(&mut self) {
+        self.read.close();
+        self.write.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::{duplex, Conversation, ConversationError};
+    use crate::buffer::{FixedSizeBuffer, Needed, Source};
+    use crate::parsers::{take, take_while, token, Error, SimpleResult};
+    use crate::types::{Buffer, U8Input};
+
+    fn line<I: U8Input>(i: I) -> SimpleResult<I, Vec<u8>> {
+        take_while(i, |c| c != b'\n').bind(|i, l| token(i, b'\n').map(|_| l.into_vec()))
+    }
+
+    #[test]
+    fn pipe() {
+        let (mut a, mut b) = duplex();
+        let mut buf = [0; 4];
+
+        a.write_all(b"abc").unwrap();
+        b.write_all(b"d").unwrap();
+
+        assert_eq!(b.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], b"abc");
+        assert_eq!(a.read(&mut buf).unwrap(), 1);
+        assert_eq!(&buf[..1], b"d");
+
+        a.set_read_timeout(Some(Duration::from_millis(1)));
+
+        assert_eq!(a.read_timeout(), Some(Duration::from_millis(1)));
+        assert_eq!(
+            a.read(&mut buf).unwrap_err().kind(),
+            std::io::ErrorKind::TimedOut
+        );
+
+        b.write_all(b"e").unwrap();
+        drop(b);
+
+        assert_eq!(a.read(&mut buf).unwrap(), 1);
+        assert_eq!(a.read(&mut buf).unwrap(), 0);
+        assert_eq!(
+            a.write(b"f").unwrap_err().kind(),
+            std::io::ErrorKind::BrokenPipe
+        );
+    }
+
+    #[test]
+    fn request() {
+        let (client, mut server) = duplex();
+
+        let t = thread::spawn(move || {
+            let mut req = [0; 4];
+
+            server.read_exact(&mut req).unwrap();
+            assert_eq!(&req, b"one\n");
+
+            // Split reply, followed by a pipelined one
+            server.write_all(b"fi").unwrap();
+            thread::sleep(Duration::from_millis(10));
+            server.write_all(b"rst\nsecond\n").unwrap();
+        });
+
+        let mut c = Conversation::new(client);
+
+        assert_eq!(c.request(b"one\n", |i| line(i)).unwrap(), b"first");
+        assert_eq!(c.source().buffer(), b"second\n");
+        assert_eq!(c.receive(|i| line(i)).unwrap(), b"second");
+
+        t.join().unwrap();
+
+        match c.receive(|i| line(i)) {
+            Err(ConversationError::Closed) => {}
+            r => panic!("expected Closed, got {:?}", r),
+        }
+    }
+
+    #[test]
+    fn timeout() {
+        let (mut client, mut server) = duplex();
+
+        client.set_read_timeout(Some(Duration::from_millis(5)));
+
+        let mut c = Conversation::new(client);
+
+        let e = c.request(b"ping\n", |i| line(i)).unwrap_err();
+
+        assert!(e.is_timeout());
+
+        // The partial reply is kept across timeouts
+        server.write_all(b"po").unwrap();
+
+        assert!(c.receive(|i| line(i)).unwrap_err().is_timeout());
+
+        server.write_all(b"ng\n").unwrap();
+
+        assert_eq!(c.receive(|i| line(i)).unwrap(), b"pong");
+
+        c.get_mut().set_read_timeout(None);
+
+        assert_eq!(c.get_ref().read_timeout(), None);
+
+        let mut req = [0; 5];
+
+        server.read_exact(&mut req).unwrap();
+        assert_eq!(&req, b"ping\n");
+    }
+
+    #[test]
+    fn errors() {
+        let (client, mut server) = duplex();
+        let source = Source::from_read_write(client, FixedSizeBuffer::with_size(16));
+        let mut c = Conversation::from_source(source);
+
+        server.write_all(b"ab\nxyz").unwrap();
+
+        match c.receive(|i| token(i, b'a').bind(|i, _| token(i, b'a'))) {
+            Err(ConversationError::Parse(e)) => assert_eq!(e, Error::expected(b'a')),
+            r => panic!("expected Parse, got {:?}", r),
+        }
+
+        // The failing parser consumed the first token
+        assert_eq!(c.receive(|i| line(i)).unwrap(), b"b");
+
+        drop(server);
+
+        match c.receive(|i| take(i, 5).map(|b| b.to_vec())) {
+            Err(ConversationError::Incomplete(n)) => assert_eq!(n, Some(Needed(2))),
+            r => panic!("expected Incomplete, got {:?}", r),
+        }
+
+        assert_eq!(c.into_source().buffer(), b"xyz");
+    }
+}
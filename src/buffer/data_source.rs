@@ -64,6 +64,21 @@ impl<RW: io::Read + io::Write> RWDataSource<RW> {
         RWDataSource(inner)
     }
 
+    /// Borrows the underlying stream.
+    #[inline]
+    pub fn get_ref(&self) -> &RW {
+        &self.0
+    }
+
+    /// Mutably borrows the underlying stream, for example to change its
+    /// timeouts.
+    ///
+    /// Reading from the stream directly will skip data.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut RW {
+        &mut self.0
+    }
+
     /// Consumes self to reveal the underlying stream.
     #[inline]
     pub fn into_inner(self) -> RW {
@@ -27,43 +27,73 @@
 //! # }
 //! ```
 
+#[cfg(feature = "bytes")]
+mod bytes_mut;
+mod conversation;
 mod slice;
 mod stateful;
 
 pub mod data_source;
 
 use std::cell::Cell;
+use std::cmp;
 use std::io;
 use std::ops;
 use std::ptr;
 
+#[cfg(feature = "bytes")]
+pub use self::bytes_mut::{BytesMutStream, PartialBytes};
+pub use self::conversation::{duplex, Conversation, ConversationError, DuplexStream};
 pub use self::data_source::{DataSource, RWDataSource};
 pub use self::slice::SliceStream;
 pub use self::stateful::Source;
 use crate::primitives::Guard;
+use crate::search::Needle;
 use crate::types::Buffer as InputBuffer;
 use crate::types::{Input, ParseResult};
 
 const DEFAULT_BUFFER_SIZE: usize = 6 * 1024;
 
+/// Lower bound on the number of additional items a parser needs before it can
+/// make progress.
+///
+/// Only reported by parsers which know the size of the data they are waiting
+/// for, like `take`, since a parser which ran into the end of the input while
+/// scanning could finish on the next item. Matching a prefix like `string` only
+/// needs one more item, since it could mismatch and let another parser finish.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Needed(pub usize);
+
+/// Combines the hint from another incomplete read with the `current` one, the
+/// parser can make progress as soon as any of the reads can.
+#[inline]
+pub(crate) fn merge_needed(current: Option<Option<Needed>>, n: Option<Needed>) -> Option<Needed> {
+    match current {
+        None => n,
+        Some(Some(m)) => n.map(|n| cmp::min(m, n)),
+        Some(None) => None,
+    }
+}
+
 /// Error type for parsing using the `Stream` trait.
 #[derive(Debug)]
 pub enum StreamError<B: InputBuffer, E> {
     /// An error occurred in the parser, the given slice indicates the part
     /// which failed.
     ParseError(B, E),
-    /// Parser failed to complete with the available data.
-    Incomplete,
+    /// Parser failed to complete with the available data, possibly with the
+    /// number of items it was missing.
+    Incomplete(Option<Needed>),
     /// An IO-error occurred while attempting to fill the buffer.
     IoError(io::Error),
     /// The last parser completed successfully and there is no more input to
     /// parse.
     EndOfInput,
     /// The last parser failed with an incomplete state, fill the buffer and try
-    /// again.
+    /// again, possibly with the number of items it was missing.
     ///
     /// Filling the buffer is automatic by default.
-    Retry,
+    Retry(Option<Needed>),
 }
 
 impl<B: InputBuffer, E: PartialEq<E>> PartialEq for StreamError<B, E> {
@@ -73,9 +103,9 @@ impl<B: InputBuffer, E: PartialEq<E>> PartialEq for StreamError<B, E> {
             (StreamError::ParseError(b1, e1), StreamError::ParseError(b2, e2)) => {
                 b1 == b2 && e1 == e2
             }
-            (&StreamError::Incomplete, &StreamError::Incomplete)
-            | (&StreamError::EndOfInput, &StreamError::EndOfInput)
-            | (&StreamError::Retry, &StreamError::Retry) => true,
+            (StreamError::Incomplete(n1), StreamError::Incomplete(n2))
+            | (StreamError::Retry(n1), StreamError::Retry(n2)) => n1 == n2,
+            (&StreamError::EndOfInput, &StreamError::EndOfInput) => true,
             _ => false,
         }
     }
@@ -108,9 +138,9 @@ pub trait Stream<'a, 'i> {
 #[must_use]
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct InputBuf<'a, I>(
-    /// If this is set to true a parser has tried to read past the end of this
-    /// buffer.
-    bool,
+    /// If this is set a parser has tried to read past the end of this buffer,
+    /// possibly knowing how many more items it needed.
+    Option<Option<Needed>>,
     /// Current buffer slice
     &'a [I],
 );
@@ -119,7 +149,7 @@ impl<'a, I: 'a> InputBuf<'a, I> {
     /// Creates a new input buffer with incomplete set to false.
     #[inline]
     pub fn new(buf: &'a [I]) -> Self {
-        InputBuf(false, buf)
+        InputBuf(None, buf)
     }
 
     /// Returns true if parsers want to obtain more data.
@@ -128,7 +158,31 @@ impl<'a, I: 'a> InputBuf<'a, I> {
     /// completed parsing.
     #[inline]
     pub fn is_incomplete(&self) -> bool {
-        self.0
+        self.0.is_some()
+    }
+
+    /// Returns the number of additional items the parsers needed, if the buffer
+    /// is incomplete and all the parsers which ran out of data knew how much
+    /// they were missing.
+    ///
+    /// ```
+    /// use chomp1::buffer::{InputBuf, Needed};
+    /// use chomp1::parsers::take;
+    /// use chomp1::primitives::IntoInner;
+    ///
+    /// let (i, _) = take(InputBuf::new(b"abc"), 5).into_inner();
+    ///
+    /// assert_eq!(i.needed(), Some(Needed(2)));
+    /// ```
+    #[inline]
+    pub fn needed(&self) -> Option<Needed> {
+        self.0.and_then(|n| n)
+    }
+
+    /// Marks the buffer as incomplete.
+    #[inline]
+    fn incomplete(&mut self, n: Option<Needed>) {
+        self.0 = Some(merge_needed(self.0, n));
     }
 
     /// Returns the length of the contained buffer, may be an incomplete buffer.
@@ -155,7 +209,7 @@ impl<'a, I: Copy + PartialEq> Input for InputBuf<'a, I> {
         if let Some(c) = self.1.first() {
             Some(*c)
         } else {
-            self.0 = true;
+            self.incomplete(Some(Needed(1)));
 
             None
         }
@@ -173,7 +227,7 @@ impl<'a, I: Copy + PartialEq> Input for InputBuf<'a, I> {
     #[inline]
     fn _consume(&mut self, _g: Guard, n: usize) -> Option<Self::Buffer> {
         if n > self.1.len() {
-            self.0 = true;
+            self.incomplete(Some(Needed(n - self.1.len())));
 
             None
         } else {
@@ -201,6 +255,45 @@ impl<'a, I: Copy + PartialEq> Input for InputBuf<'a, I> {
         }
     }
 
+    #[inline]
+    fn _consume_till<N>(&mut self, g: Guard, n: &N) -> Self::Buffer
+    where
+        N: Needle<Self::Token>,
+    {
+        if let Some(n) = n.find(self.1) {
+            let b = &self.1[..n];
+
+            self.1 = &self.1[n..];
+
+            b
+        } else {
+            self._consume_remaining(g)
+        }
+    }
+
+    #[inline]
+    fn _consume_prefix(&mut self, _g: Guard, s: &[Self::Token]) -> Result<Self::Buffer, usize> {
+        if self.1.starts_with(s) {
+            let b = &self.1[..s.len()];
+
+            self.1 = &self.1[s.len()..];
+
+            Ok(b)
+        } else {
+            let n = self.1.iter().zip(s).take_while(|&(a, b)| a == b).count();
+
+            // Ran out of data before finding a mismatch, the next item might
+            // already mismatch and let another parser make progress
+            if n == self.1.len() {
+                self.incomplete(Some(Needed(1)));
+            }
+
+            self.1 = &self.1[n..];
+
+            Err(n)
+        }
+    }
+
     #[inline]
     fn _consume_from(&mut self, _g: Guard, m: Self::Marker) -> Self::Buffer {
         &m[..m.len() - self.1.len()]
@@ -208,7 +301,7 @@ impl<'a, I: Copy + PartialEq> Input for InputBuf<'a, I> {
 
     #[inline]
     fn _consume_remaining(&mut self, _g: Guard) -> Self::Buffer {
-        self.0 = true;
+        self.incomplete(None);
 
         let b = self.1;
 
@@ -518,7 +611,7 @@ impl<I: Copy + PartialEq> Buffer<I> for GrowingBuffer<I> {
 
 #[cfg(test)]
 mod test {
-    use super::InputBuf;
+    use super::{InputBuf, Needed};
     use crate::primitives::{IntoInner, Primitives};
     use crate::types::test::run_primitives_test;
     use crate::types::{Input, ParseResult};
@@ -547,6 +640,36 @@ mod test {
         assert_eq!(r2.into_inner(), (InputBuf::new(b"in2"), Err(23i32)));
     }
 
+    #[test]
+    fn test_input_buf_needed() {
+        let mut b = InputBuf::new(b"ab");
+
+        assert_eq!(b.needed(), None);
+        assert_eq!(b.consume(5), None);
+        assert_eq!(b.needed(), Some(Needed(3)));
+        assert_eq!(b.consume(4), None);
+        // The smallest amount is enough for one of the parsers to progress
+        assert_eq!(b.needed(), Some(Needed(2)));
+        assert_eq!(b.consume(2), Some(&b"ab"[..]));
+        assert_eq!(b.peek(), None);
+        assert_eq!(b.needed(), Some(Needed(1)));
+
+        let mut b = InputBuf::new(b"ab");
+
+        // The next item could already mismatch
+        assert_eq!(b.consume_prefix(b"abcd"), Err(2));
+        assert_eq!(b.needed(), Some(Needed(1)));
+
+        let mut b = InputBuf::new(b"ab");
+
+        assert_eq!(b.consume(3), None);
+        assert_eq!(b.consume_remaining(), &b"ab"[..]);
+        assert!(b.is_incomplete());
+        assert_eq!(b.needed(), None);
+        assert_eq!(b.consume(3), None);
+        assert_eq!(b.needed(), None);
+    }
+
     #[test]
     fn test_input_buf() {
         run_primitives_test(InputBuf::new(b"abc"), |x| x);
@@ -87,7 +87,7 @@ impl<'a, 'i, I: 'i + Copy + PartialEq> Stream<'a, 'i> for SliceStream<'i, I> {
             }
             (mut remainder, Err(err)) => {
                 if remainder.is_incomplete() {
-                    Err(StreamError::Incomplete)
+                    Err(StreamError::Incomplete(remainder.needed()))
                 } else {
                     // TODO: Do something neater with the remainder
                     // TODO: Detail this behaviour, maybe make it configurable
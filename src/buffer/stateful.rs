@@ -1,8 +1,9 @@
+use std::cmp;
 use std::io;
 
 use crate::buffer::data_source::{IteratorDataSource, ReadDataSource};
 use crate::buffer::{
-    Buffer, DataSource, FixedSizeBuffer, InputBuf, RWDataSource, Stream, StreamError,
+    Buffer, DataSource, FixedSizeBuffer, InputBuf, Needed, RWDataSource, Stream, StreamError,
 };
 use crate::primitives::IntoInner;
 use crate::types::{Input, ParseResult};
@@ -18,6 +19,8 @@ bitflags! {
         const END_OF_INPUT   = 2;
         /// `parse()` should attempt to read more data whenever the `INCOMPLETE` flag is set.
         const AUTOMATIC_FILL = 4;
+        /// A `Retry` requests at least twice the amount of data the parser saw.
+        const RETRY_DOUBLING = 8;
     }
 }
 
@@ -30,7 +33,7 @@ pub struct Source<S: DataSource, B: Buffer<S::Item>> {
     /// Temporary source
     buffer: B,
     /// The requested amount of bytes to be available for reading from the
-    /// buffer
+    /// buffer before the next parser runs after a `Retry`
     request: usize,
     /// Input state, if end has been reached
     state: ParserState,
@@ -118,11 +121,15 @@ impl<S: DataSource, B: Buffer<S::Item>> Source<S, B> {
     }
 
     /// Attempts to fill the buffer to satisfy the last call to `parse()`.
+    ///
+    /// Reads at least one more item, or until the amount set by `require` or
+    /// `Needed` by the last parser is available.
     #[inline]
     pub fn fill(&mut self) -> io::Result<usize> {
-        let req = self.buffer.len() + 1;
+        let req = cmp::max(self.buffer.len() + 1, self.request);
 
         self.fill_requested(req).map(|n| {
+            self.request = 0;
             self.state.remove(ParserState::INCOMPLETE);
 
             if n > 0 {
@@ -162,6 +169,20 @@ impl<S: DataSource, B: Buffer<S::Item>> Source<S, B> {
         &self.buffer
     }
 
+    /// Borrows the data source.
+    #[inline]
+    pub fn get_ref(&self) -> &S {
+        &self.source
+    }
+
+    /// Mutably borrows the data source.
+    ///
+    /// Reading from the data source directly will skip the data it returns.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.source
+    }
+
     /// Resets the buffer state, keeping the current buffer contents and cursor
     /// position.
     ///
@@ -172,6 +193,41 @@ impl<S: DataSource, B: Buffer<S::Item>> Source<S, B> {
         self.state = ParserState::empty();
     }
 
+    /// Makes the next `fill()` read until at least `items` items are buffered,
+    /// or until the end of the input.
+    ///
+    /// Parsers are plain functions which cannot be suspended, so after a
+    /// `Retry` the parser runs again from the start of the buffer. Parsing a
+    /// large frame which arrives in small pieces therefore takes time
+    /// quadratic in its size. If the size of the frame is known, for example
+    /// after parsing a length prefix, requiring the whole frame avoids running
+    /// the parser on every piece. The requirement is cleared by the next fill.
+    ///
+    /// Note: Blocks in `fill()` until the data arrives, and a `FixedSizeBuffer`
+    /// stops filling once it is full.
+    #[inline]
+    pub fn require(&mut self, items: usize) {
+        self.request = items;
+    }
+
+    /// Changes the retry doubling setting, `true` will make every `Retry`
+    /// require at least twice the data the failed parser saw, as if calling
+    /// `require`.
+    ///
+    /// This bounds the total work of reparsing a frame of unknown size to a
+    /// constant factor of its size, at the cost of blocking until the doubled
+    /// amount of data is available or the input ends. Only enable it if the
+    /// data source keeps sending, a peer waiting for a reply to a short message
+    /// would never send the extra data. Disabled by default.
+    #[inline]
+    pub fn set_retry_doubling(&mut self, value: bool) {
+        if value {
+            self.state.insert(ParserState::RETRY_DOUBLING)
+        } else {
+            self.state.remove(ParserState::RETRY_DOUBLING)
+        }
+    }
+
     /// Changes the setting automatic fill feature, `true` will make the buffer
     /// automatically call `fill()` on the next call to `parse()` after a
     /// `Retry` was encountered.
@@ -186,6 +242,23 @@ impl<S: DataSource, B: Buffer<S::Item>> Source<S, B> {
     }
 }
 
+/// Marks the last parse as incomplete after it saw `len` items, requesting the
+/// items it `needed` or more.
+///
+/// Takes the fields separately since the buffer is still borrowed by the parser.
+#[inline]
+fn retry(state: &mut ParserState, request: &mut usize, len: usize, needed: Option<Needed>) {
+    state.insert(ParserState::INCOMPLETE);
+
+    if let Some(Needed(n)) = needed {
+        *request = cmp::max(*request, len.saturating_add(n));
+    }
+
+    if state.contains(ParserState::RETRY_DOUBLING) {
+        *request = cmp::max(*request, len.saturating_mul(2));
+    }
+}
+
 impl<S: DataSource<Item = u8>, B: Buffer<u8>> io::Read for Source<S, B> {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
@@ -262,9 +335,16 @@ where
             (remainder, Ok(data)) => {
                 if remainder.is_incomplete() && !self.state.contains(ParserState::END_OF_INPUT) {
                     // We can't accept this since we might have hit a premature end
-                    self.state.insert(ParserState::INCOMPLETE);
+                    let needed = remainder.needed();
+
+                    retry(
+                        &mut self.state,
+                        &mut self.request,
+                        self.buffer.len(),
+                        needed,
+                    );
 
-                    Err(StreamError::Retry)
+                    Err(StreamError::Retry(needed))
                 } else {
                     // TODO: Do something neater with the remainder
                     self.buffer.consume(self.buffer.len() - remainder.len());
@@ -277,11 +357,18 @@ where
                     remainder.is_incomplete(),
                     self.state.contains(ParserState::END_OF_INPUT),
                 ) {
-                    (true, true) => Err(StreamError::Incomplete),
+                    (true, true) => Err(StreamError::Incomplete(remainder.needed())),
                     (true, false) => {
-                        self.state.insert(ParserState::INCOMPLETE);
+                        let needed = remainder.needed();
+
+                        retry(
+                            &mut self.state,
+                            &mut self.request,
+                            self.buffer.len(),
+                            needed,
+                        );
 
-                        Err(StreamError::Retry)
+                        Err(StreamError::Retry(needed))
                     }
                     _ => {
                         // TODO: Do something neater with the remainder
@@ -302,9 +389,10 @@ mod test {
 
     use super::*;
     use crate::buffer::data_source::ReadDataSource;
-    use crate::buffer::{FixedSizeBuffer, Stream, StreamError};
-    use crate::parsers::{any, take, take_while, Error};
-    use crate::types::Input;
+    use crate::buffer::{FixedSizeBuffer, GrowingBuffer, Stream, StreamError};
+    use crate::combinators::or;
+    use crate::parsers::{any, string, take, take_while, token, Error, SimpleResult};
+    use crate::types::{Input, U8Input};
 
     fn buf(
         source: &[u8],
@@ -364,7 +452,7 @@ mod test {
                 n += 1;
                 any(i).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(1))))
         );
         assert_eq!(n, 2);
         assert_eq!(m, 1);
@@ -382,7 +470,7 @@ mod test {
                 n += 1;
                 any(i).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(1))))
         );
         assert_eq!(n, 4);
         assert_eq!(m, 2);
@@ -400,7 +488,7 @@ mod test {
                 n += 1;
                 any(i).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(1))))
         );
         assert_eq!(n, 6);
         assert_eq!(m, 3);
@@ -418,7 +506,7 @@ mod test {
                 n += 1;
                 any(i).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(1))))
         );
         assert_eq!(n, 8);
         assert_eq!(m, 4);
@@ -471,7 +559,7 @@ mod test {
                 n += 1;
                 any(i).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(1))))
         );
         assert_eq!(n, 3);
         assert_eq!(m, 2);
@@ -498,7 +586,7 @@ mod test {
                 n += 1;
                 any(i).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(1))))
         );
         assert_eq!(n, 6);
         assert_eq!(m, 4);
@@ -542,7 +630,7 @@ mod test {
                 n += 1;
                 take(i, 2).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(1))))
         );
         assert_eq!(n, 2);
         assert_eq!(m, 1);
@@ -560,7 +648,7 @@ mod test {
                 n += 1;
                 take(i, 2).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(2))))
         );
         assert_eq!(n, 4);
         assert_eq!(m, 2);
@@ -604,7 +692,7 @@ mod test {
                 n += 1;
                 take(i, 2).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(2))))
         );
         assert_eq!(n, 2);
         assert_eq!(m, 1);
@@ -613,7 +701,7 @@ mod test {
                 n += 1;
                 take(i, 2).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(1))))
         );
         assert_eq!(n, 3);
         assert_eq!(m, 1);
@@ -622,7 +710,7 @@ mod test {
                 n += 1;
                 take(i, 2).inspect(|_| m += 1)
             }),
-            Err(StreamError::Incomplete)
+            Err(StreamError::Incomplete(Some(Needed(1))))
         );
         assert_eq!(n, 4);
         assert_eq!(m, 1);
@@ -631,7 +719,7 @@ mod test {
                 n += 1;
                 take(i, 2).inspect(|_| m += 1)
             }),
-            Err(StreamError::Incomplete)
+            Err(StreamError::Incomplete(Some(Needed(1))))
         );
         assert_eq!(n, 5);
         assert_eq!(m, 1);
@@ -667,7 +755,7 @@ mod test {
                 })
                 .inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(None))
         );
         assert_eq!(n, 2);
         assert_eq!(m, 2);
@@ -695,7 +783,7 @@ mod test {
                 })
                 .inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(None))
         );
         assert_eq!(n, 4);
         assert_eq!(m, 4);
@@ -742,7 +830,7 @@ mod test {
                 n += 1;
                 take(i, 2).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(2))))
         );
         assert_eq!(n, 1);
         assert_eq!(m, 0);
@@ -763,7 +851,7 @@ mod test {
                 n += 1;
                 take(i, 2).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(2))))
         );
         assert_eq!(n, 3);
         assert_eq!(m, 1);
@@ -784,7 +872,7 @@ mod test {
                 n += 1;
                 take(i, 2).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(2))))
         );
         assert_eq!(n, 5);
         assert_eq!(m, 2);
@@ -826,7 +914,7 @@ mod test {
                 n += 1;
                 any(i).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(1))))
         );
         assert_eq!(n, 2);
         assert_eq!(m, 1);
@@ -847,7 +935,7 @@ mod test {
                 n += 1;
                 any(i).inspect(|_| m += 1)
             }),
-            Err(StreamError::Retry)
+            Err(StreamError::Retry(Some(Needed(1))))
         );
         assert_eq!(n, 4);
         assert_eq!(m, 2);
@@ -864,4 +952,150 @@ mod test {
         assert_eq!(n, 4);
         assert_eq!(m, 2);
     }
+
+    /// Reader returning at most `n` bytes at a time, like a socket receiving
+    /// small segments.
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl<'a> io::Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.1.min(buf.len()).min(self.0.len());
+
+            buf[..n].copy_from_slice(&self.0[..n]);
+
+            self.0 = &self.0[n..];
+
+            Ok(n)
+        }
+    }
+
+    /// Reader returning one segment per read, like a peer which sends a message
+    /// and then waits for the reply.
+    struct Segments<'a>(&'a [&'a [u8]]);
+
+    impl<'a> io::Read for Segments<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let (s, rest) = self.0.split_first().expect("blocked waiting for more data");
+
+            buf[..s.len()].copy_from_slice(s);
+
+            self.0 = rest;
+
+            Ok(s.len())
+        }
+    }
+
+    /// Parses a 1000 byte line arriving 10 bytes at a time, returning the number
+    /// of times the parser ran.
+    fn frame_runs(
+        setup: impl FnOnce(&mut Source<ReadDataSource<Trickle>, GrowingBuffer<u8>>),
+    ) -> usize {
+        let mut data = [b'a'; 1000];
+
+        data[999] = b'\n';
+
+        let mut b = Source::from_read(Trickle(&data, 10), GrowingBuffer::new());
+        let mut n = 0;
+
+        setup(&mut b);
+
+        loop {
+            // The parser does not know the size of the line
+            match b.parse(|i| {
+                n += 1;
+
+                take_while(i, |c| c != b'\n').bind(|i, l| token(i, b'\n').map(|_| l))
+            }) {
+                Ok(l) => {
+                    assert_eq!(l, &data[..999]);
+
+                    return n;
+                }
+                Err(StreamError::Retry(_)) => {}
+                Err(e) => panic!("unexpected error: {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn require() {
+        // At least once for every segment
+        assert!(frame_runs(|_| {}) >= 100);
+        assert_eq!(frame_runs(|b| b.require(1000)), 1);
+        // Only applies to the next fill
+        assert_eq!(frame_runs(|b| b.require(500)), 51);
+
+        let mut b = buf(&b"abc"[..], 2);
+
+        // Stops when full
+        b.require(3);
+
+        assert_eq!(b.fill().unwrap(), 2);
+        assert_eq!(b.buffer(), b"ab");
+    }
+
+    #[test]
+    fn retry_doubling() {
+        // 10, 20, 40, ..., 640, 1000 bytes
+        assert_eq!(frame_runs(|b| b.set_retry_doubling(true)), 8);
+
+        // The input ends before the doubled amount is available
+        let mut b = Source::from_read(Trickle(b"abcde", 1), GrowingBuffer::new());
+        let mut lens = Vec::new();
+
+        b.set_retry_doubling(true);
+
+        let r = loop {
+            match b.parse(|i| take_while(i, |c| c != b'\n').then(|i| token(i, b'\n'))) {
+                Err(StreamError::Retry(None)) => lens.push(b.len()),
+                r => break r,
+            }
+        };
+
+        assert_eq!(r, Err(StreamError::Incomplete(None)));
+        assert_eq!(lens, [1, 2, 4, 5]);
+    }
+
+    #[test]
+    fn needed() {
+        let data = [b'a'; 1000];
+        let mut b = Source::from_read(Trickle(&data, 10), FixedSizeBuffer::with_size(1000));
+
+        assert_eq!(
+            b.parse(|i| take(i, 1000)),
+            Err(StreamError::Retry(Some(Needed(990))))
+        );
+        // Fills exactly the missing amount
+        assert_eq!(b.fill().unwrap(), 990);
+        assert_eq!(b.parse(|i| take(i, 1000)).map(|f| f.len()), Ok(1000));
+
+        let mut b = buf(b"abc", 10);
+
+        assert_eq!(
+            b.parse(|i| take(i, 5)),
+            Err(StreamError::Retry(Some(Needed(2))))
+        );
+        assert_eq!(
+            b.parse(|i| take(i, 5)),
+            Err(StreamError::Incomplete(Some(Needed(2))))
+        );
+        // Unknown when a parser ran into the end while scanning
+        assert_eq!(
+            b.parse(|i| take(i, 1)
+                .then(|i| take_while(i, |_| true))
+                .then(|i| take(i, 1))),
+            Err(StreamError::Incomplete(None))
+        );
+
+        // A prefix which ran out of data could mismatch on the next item
+        let mut b = Source::from_read(Segments(&[b"a", b"x"]), FixedSizeBuffer::with_size(10));
+
+        fn p<I: U8Input>(i: I) -> SimpleResult<I, u8> {
+            or(i, |i| string(i, b"abcd").map(|_| b'b'), |i| token(i, b'a'))
+        }
+
+        assert_eq!(b.parse(p), Err(StreamError::Retry(Some(Needed(1)))));
+        assert_eq!(b.parse(p), Ok(b'a'));
+        assert_eq!(b.buffer(), b"x");
+    }
 }
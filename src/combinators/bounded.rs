@@ -1,7 +1,9 @@
 //! Bounded versions of combinators.
 //!
-//! This module provides bounded versions of `many`, `many_till` and
-//! `skip_many`.
+//! This module provides bounded versions of `many`, `many_till`, `skip_many`,
+//! `skip_many_till`, `sep_by`, `sep_end_by` and `end_by`, as well as
+//! `fold_many` and `fold_sep_by` which fold the parsed values instead of
+//! collecting them.
 //!
 //! The core range types are used to describe a half-open range of successive
 //! applications of a parser. `usize` is used to specify an exact number of
@@ -837,9 +839,250 @@ where
     BoundedRange::parse_many(r, i, parser)
 }
 
+/// Applies the parser `p` multiple times, separated and optionally ended by the
+/// parser `sep`, and returns a value populated with the values yielded by `p`.
+/// A trailing separator is only consumed if at least one item was parsed. If
+/// the number of items yielded by `p` does not fall into the range `r` and the
+/// separator or parser registers error or incomplete failure is propagated.
+///
+/// # Panics
+///
+/// Will panic if the end of the range is smaller than the start of the range.
+///
+/// # Notes
+///
+/// * Will allocate depending on the `FromIterator` implementation.
+/// * Will never yield more items than the upper bound of the range.
+#[inline]
+pub fn sep_end_by<I: Input, T, E, R, F, U, N, P, V>(
+    i: I,
+    r: R,
+    mut p: P,
+    mut sep: F,
+) -> ParseResult<I, T, E>
+where
+    T: FromIterator<U>,
+    E: From<N>,
+    R: BoundedRange,
+    P: FnMut(I) -> ParseResult<I, U, E>,
+    F: FnMut(I) -> ParseResult<I, V, N>,
+{
+    // If we have parsed at least one item
+    let mut item = false;
+
+    let (i, r) = {
+        // Add sep in front of p if we have read at least one item
+        let parser = |i| {
+            (if item { sep(i).map(|_| ()) } else { i.ret(()) })
+                .then(&mut p)
+                .inspect(|_| item = true)
+        };
+
+        BoundedRange::parse_many(r, i, parser).into_inner()
+    };
+
+    match r {
+        Ok(t) if item => {
+            let m = i.mark();
+
+            match sep(i).into_inner() {
+                (b, Ok(_)) => b.ret(t),
+                (b, Err(_)) => b.restore(m).ret(t),
+            }
+        }
+        Ok(t) => i.ret(t),
+        Err(e) => i.err(e),
+    }
+}
+
+/// Applies the parser `p` multiple times, each application followed by the
+/// parser `sep`, and returns a value populated with the values yielded by `p`.
+/// If the number of items does not fall into the range `r` and the separator or
+/// parser registers error or incomplete failure is propagated.
+///
+/// # Panics
+///
+/// Will panic if the end of the range is smaller than the start of the range.
+///
+/// # Notes
+///
+/// * Will allocate depending on the `FromIterator` implementation.
+/// * Will never yield more items than the upper bound of the range.
+#[inline]
+pub fn end_by<I: Input, T, E, R, F, U, N, P, V>(
+    i: I,
+    r: R,
+    mut p: P,
+    mut sep: F,
+) -> ParseResult<I, T, E>
+where
+    T: FromIterator<U>,
+    E: From<N>,
+    R: BoundedRange,
+    P: FnMut(I) -> ParseResult<I, U, E>,
+    F: FnMut(I) -> ParseResult<I, V, N>,
+{
+    BoundedRange::parse_many(r, i, |i| {
+        p(i).bind(|i, t| sep(i).map(|_| t).map_err(From::from))
+    })
+}
+
+/// Applies the parser `P` multiple times until the parser `F` succeeds,
+/// throwing away any value produced by `P`. Consumes the matched part of `F`.
+/// If `F` does not succeed within the given range `R` this combinator will
+/// propagate any failure from `P`.
+///
+/// # Panics
+///
+/// Will panic if the end of the range is smaller than the start of the range.
+///
+/// # Notes
+///
+/// * Will never yield more items than the upper bound of the range.
+#[inline]
+pub fn skip_many_till<I: Input, T, E, R, F, N, P, V>(
+    i: I,
+    r: R,
+    mut p: P,
+    end: F,
+) -> ParseResult<I, (), E>
+where
+    R: BoundedRange,
+    E: From<N>,
+    P: FnMut(I) -> ParseResult<I, T, E>,
+    F: FnMut(I) -> ParseResult<I, V, N>,
+{
+    // () implements FromIterator<()>, so nothing is allocated
+    BoundedRange::many_till(r, i, |i| p(i).map(|_| ()), end)
+}
+
+/// Applies the parser `p` multiple times until it fails or the maximum value of
+/// the range has been reached, combining the successful values with the
+/// accumulator through `f`, starting from `init`.
+///
+/// Propagates errors if the minimum number of iterations has not been met
+///
+/// # Panics
+///
+/// Will panic if the end of the range is smaller than the start of the range.
+///
+/// # Notes
+///
+/// * Does not allocate, unlike `many` with a collection.
+/// * Will never fold more items than the upper bound of the range.
+///
+/// ```
+/// use chomp1::ascii::decimal;
+/// use chomp1::combinators::bounded::fold_many;
+/// use chomp1::prelude::{parse_only, token};
+///
+/// let number = |i| decimal(i).bind(|i, n: u32| token(i, b' ').map(|_| n));
+/// let r = parse_only(|i| fold_many(i, 1.., 0, number, |a, n| a + n), b"1 2 3 4");
+///
+/// assert_eq!(r, Ok(6));
+/// ```
+#[inline]
+pub fn fold_many<I: Input, A, E, F, G, U, R>(
+    i: I,
+    r: R,
+    init: A,
+    mut p: F,
+    mut f: G,
+) -> ParseResult<I, A, E>
+where
+    R: BoundedRange,
+    F: FnMut(I) -> ParseResult<I, U, E>,
+    G: FnMut(A, U) -> A,
+{
+    let mut acc = Some(init);
+
+    // () implements FromIterator<()>, so the items are folded as they are produced
+    let r: ParseResult<I, (), E> =
+        BoundedRange::parse_many(r, i, |i| p(i).map(|u| acc = acc.take().map(|a| f(a, u))));
+
+    r.map(|_| acc.expect("fold_many accumulator was taken"))
+}
+
+/// Applies the parser `p` multiple times, separated by the parser `sep`,
+/// combining the values yielded by `p` with the accumulator through `f`,
+/// starting from `init`. If the number of items yielded by `p` does not fall
+/// into the range `r` and the separator or parser registers error or
+/// incomplete failure is propagated.
+///
+/// # Panics
+///
+/// Will panic if the end of the range is smaller than the start of the range.
+///
+/// # Notes
+///
+/// * Does not allocate, unlike `sep_by` with a collection.
+/// * Will never fold more items than the upper bound of the range.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use chomp1::combinators::bounded::fold_sep_by;
+/// use chomp1::prelude::{parse_only, take_while1, token};
+///
+/// let r = parse_only(
+///     |i| {
+///         fold_sep_by(
+///             i,
+///             ..,
+///             HashMap::new(),
+///             |i| {
+///                 take_while1(i, |c| c != b'=').bind(|i, k| {
+///                     token(i, b'=')
+///                         .then(|i| take_while1(i, |c| c != b';'))
+///                         .map(|v| (k, v))
+///                 })
+///             },
+///             |i| token(i, b';'),
+///             |mut m, (k, v)| {
+///                 m.insert(k, v);
+///                 m
+///             },
+///         )
+///     },
+///     b"a=1;b=2",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(r.get(&b"b"[..]), Some(&&b"2"[..]));
+/// ```
+#[inline]
+pub fn fold_sep_by<I: Input, A, E, R, F, G, U, N, P, V>(
+    i: I,
+    r: R,
+    init: A,
+    mut p: P,
+    sep: F,
+    mut f: G,
+) -> ParseResult<I, A, E>
+where
+    E: From<N>,
+    R: BoundedRange,
+    P: FnMut(I) -> ParseResult<I, U, E>,
+    F: FnMut(I) -> ParseResult<I, V, N>,
+    G: FnMut(A, U) -> A,
+{
+    let mut acc = Some(init);
+
+    let r: ParseResult<I, (), E> = sep_by(
+        i,
+        r,
+        |i| p(i).map(|u| acc = acc.take().map(|a| f(a, u))),
+        sep,
+    );
+
+    r.map(|_| acc.expect("fold_sep_by accumulator was taken"))
+}
+
 #[cfg(test)]
 mod test {
-    use super::{many, many_till, skip_many};
+    use super::{
+        end_by, fold_many, fold_sep_by, many, many_till, sep_end_by, skip_many, skip_many_till,
+    };
     use crate::parsers::{any, string, token, Error};
     use crate::primitives::IntoInner;
     use crate::types::ParseResult;
@@ -1574,6 +1817,96 @@ mod test {
         assert_eq!(r.into_inner(), (&b"b"[..], Err(Error::expected(b'a'))));
     }
 
+    #[test]
+    fn sep_end_by_range() {
+        let r: ParseResult<_, Vec<_>, _> =
+            sep_end_by(&b""[..], .., |i| token(i, b'a'), |i| token(i, b','));
+        assert_eq!(r.into_inner(), (&b""[..], Ok(vec![])));
+        let r: ParseResult<_, Vec<_>, _> =
+            sep_end_by(&b","[..], .., |i| token(i, b'a'), |i| token(i, b','));
+        assert_eq!(r.into_inner(), (&b","[..], Ok(vec![])));
+        let r: ParseResult<_, Vec<_>, _> =
+            sep_end_by(&b"a,a"[..], .., |i| token(i, b'a'), |i| token(i, b','));
+        assert_eq!(r.into_inner(), (&b""[..], Ok(vec![b'a', b'a'])));
+        let r: ParseResult<_, Vec<_>, _> =
+            sep_end_by(&b"a,a,]"[..], .., |i| token(i, b'a'), |i| token(i, b','));
+        assert_eq!(r.into_inner(), (&b"]"[..], Ok(vec![b'a', b'a'])));
+        let r: ParseResult<_, Vec<_>, _> =
+            sep_end_by(&b"a,,"[..], .., |i| token(i, b'a'), |i| token(i, b','));
+        assert_eq!(r.into_inner(), (&b","[..], Ok(vec![b'a'])));
+        let r: ParseResult<_, Vec<_>, _> =
+            sep_end_by(&b"a,a,a,"[..], ..3, |i| token(i, b'a'), |i| token(i, b','));
+        assert_eq!(r.into_inner(), (&b"a,"[..], Ok(vec![b'a', b'a'])));
+        let r: ParseResult<_, Vec<_>, _> =
+            sep_end_by(&b"a,]"[..], 2.., |i| token(i, b'a'), |i| token(i, b','));
+        assert_eq!(r.into_inner(), (&b"]"[..], Err(Error::expected(b'a'))));
+    }
+
+    #[test]
+    fn end_by_range() {
+        let r: ParseResult<_, Vec<_>, _> =
+            end_by(&b""[..], .., |i| token(i, b'a'), |i| token(i, b';'));
+        assert_eq!(r.into_inner(), (&b""[..], Ok(vec![])));
+        let r: ParseResult<_, Vec<_>, _> =
+            end_by(&b"a;a;a"[..], .., |i| token(i, b'a'), |i| token(i, b';'));
+        assert_eq!(r.into_inner(), (&b"a"[..], Ok(vec![b'a', b'a'])));
+        let r: ParseResult<_, Vec<_>, _> =
+            end_by(&b"a;a;a;"[..], 1..3, |i| token(i, b'a'), |i| token(i, b';'));
+        assert_eq!(r.into_inner(), (&b"a;"[..], Ok(vec![b'a', b'a'])));
+        let r: ParseResult<_, Vec<_>, _> =
+            end_by(&b"a;a"[..], 2, |i| token(i, b'a'), |i| token(i, b';'));
+        assert_eq!(r.into_inner(), (&b""[..], Err(Error::expected(b';'))));
+    }
+
+    #[test]
+    fn skip_many_till_range() {
+        let r = skip_many_till(&b"aab"[..], .., |i| token(i, b'a'), |i| token(i, b'b'));
+        assert_eq!(r.into_inner(), (&b""[..], Ok(())));
+        let r = skip_many_till(&b"b"[..], 1.., |i| token(i, b'a'), |i| token(i, b'b'));
+        assert_eq!(r.into_inner(), (&b"b"[..], Err(Error::expected(b'a'))));
+        let r = skip_many_till(&b"aaab"[..], ..3, |i| token(i, b'a'), |i| token(i, b'b'));
+        assert_eq!(r.into_inner(), (&b"ab"[..], Err(Error::expected(b'b'))));
+    }
+
+    #[test]
+    fn fold_many_range() {
+        let add = |a: u32, c: u8| a + u32::from(c - b'0');
+
+        let r = fold_many(&b""[..], .., 0, any, add);
+        assert_eq!(r.into_inner(), (&b""[..], Ok(0)));
+        let r = fold_many(&b"123"[..], .., 0, any, add);
+        assert_eq!(r.into_inner(), (&b""[..], Ok(6)));
+        let r = fold_many(&b"123"[..], ..3, 0, any, add);
+        assert_eq!(r.into_inner(), (&b"3"[..], Ok(3)));
+        let r = fold_many(&b"1234"[..], 1..3, 10, any, add);
+        assert_eq!(r.into_inner(), (&b"34"[..], Ok(13)));
+        let r = fold_many(&b"12"[..], 2, 0, any, add);
+        assert_eq!(r.into_inner(), (&b""[..], Ok(3)));
+        let r = fold_many(&b"12"[..], 3.., 0, any, add);
+        assert_eq!(r.into_inner(), (&b""[..], Err(Error::unexpected())));
+        let r = fold_many(&b"12"[..], 1..5, 0, |i| token(i, b'1'), add);
+        assert_eq!(r.into_inner(), (&b"2"[..], Ok(1)));
+    }
+
+    #[test]
+    fn fold_sep_by_range() {
+        let push = |mut v: Vec<u8>, c| {
+            v.push(c);
+            v
+        };
+
+        let r = fold_sep_by(&b""[..], .., vec![], any, |i| token(i, b','), push);
+        assert_eq!(r.into_inner(), (&b""[..], Ok(vec![])));
+        let r = fold_sep_by(&b"a,b,c"[..], .., vec![], any, |i| token(i, b','), push);
+        assert_eq!(r.into_inner(), (&b""[..], Ok(vec![b'a', b'b', b'c'])));
+        let r = fold_sep_by(&b"a,b,"[..], .., vec![], any, |i| token(i, b','), push);
+        assert_eq!(r.into_inner(), (&b","[..], Ok(vec![b'a', b'b'])));
+        let r = fold_sep_by(&b"a,b,c"[..], ..3, vec![], any, |i| token(i, b','), push);
+        assert_eq!(r.into_inner(), (&b",c"[..], Ok(vec![b'a', b'b'])));
+        let r = fold_sep_by(&b"a"[..], 2.., vec![], any, |i| token(i, b','), push);
+        assert_eq!(r.into_inner(), (&b""[..], Err(Error::expected(b','))));
+    }
+
     #[test]
     #[should_panic]
     fn panic_many_range_lt() {
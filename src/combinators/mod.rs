@@ -4,13 +4,24 @@
 mod macros;
 
 pub mod bounded;
+#[cfg(feature = "std")]
+pub mod permutation;
 
+use std::cell::Cell;
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+#[cfg(feature = "std")]
+use std::error;
+use std::fmt;
 use std::iter::FromIterator;
 
 use either::Either;
 
+#[cfg(feature = "std")]
+pub use self::permutation::permutation;
+use crate::parsers::Error;
 use crate::primitives::{IntoInner, Primitives};
-use crate::types::{Input, ParseResult};
+use crate::types::{Buffer, Input, ParseResult};
 
 /// Applies the parser `p` exactly `num` times collecting all items into `T:
 /// FromIterator`.
@@ -44,6 +55,41 @@ where
     bounded::many(i, num, p)
 }
 
+/// Applies the parser `p` at least `min` and at most `max` times collecting all
+/// items into `T: FromIterator`.
+///
+/// Propagates the error from `p` if it matched fewer than `min` times.
+///
+/// # Panics
+///
+/// Will panic if `max` is smaller than `min`.
+///
+/// ```
+/// use chomp1::combinators::count_min_max;
+/// use chomp1::prelude::{parse_only, token, Error};
+///
+/// let p = |i| count_min_max(i, 2, 3, |i| token(i, b'a'));
+///
+/// assert_eq!(parse_only(p, b"a"), Err((&b""[..], Error::expected(b'a'))));
+/// assert_eq!(parse_only(p, b"aab"), Ok(vec![b'a', b'a']));
+/// assert_eq!(parse_only(p, b"aaaa"), Ok(vec![b'a', b'a', b'a']));
+/// ```
+#[inline]
+pub fn count_min_max<I: Input, T, E, F, U>(
+    i: I,
+    min: usize,
+    max: usize,
+    p: F,
+) -> ParseResult<I, T, E>
+where
+    F: FnMut(I) -> ParseResult<I, U, E>,
+    T: FromIterator<U>,
+{
+    assert!(min <= max);
+
+    bounded::many(i, min..max.saturating_add(1), p)
+}
+
 /// Tries the parser `f`, on success it yields the parsed value, on failure
 /// `default` will be yielded instead.
 ///
@@ -313,6 +359,89 @@ where
     bounded::sep_by(i, 1.., p, sep)
 }
 
+/// Applies the parser `R` zero or more times, separated and optionally ended by
+/// the parser `F`. All matches from `R` will be collected into the type `T:
+/// FromIterator`.
+///
+/// A trailing separator is consumed if at least one item has been read. If the
+/// separator or parser registers error or incomplete this parser stops and
+/// yields the collected value.
+///
+/// ```
+/// use chomp1::ascii::decimal;
+/// use chomp1::combinators::sep_end_by;
+/// use chomp1::prelude::{parse_only, token};
+///
+/// let r: Result<Vec<u8>, _> =
+///     parse_only(|i| sep_end_by(i, decimal, |i| token(i, b',')), b"1,2,3,");
+///
+/// assert_eq!(r, Ok(vec![1, 2, 3]));
+/// ```
+#[inline]
+pub fn sep_end_by<I: Input, T, E, R, F, U, N, V>(i: I, p: R, sep: F) -> ParseResult<I, T, E>
+where
+    T: FromIterator<U>,
+    E: From<N>,
+    R: FnMut(I) -> ParseResult<I, U, E>,
+    F: FnMut(I) -> ParseResult<I, V, N>,
+{
+    bounded::sep_end_by(i, .., p, sep)
+}
+
+/// Applies the parser `R` one or more times, separated and optionally ended by
+/// the parser `F`. All matches from `R` will be collected into the type `T:
+/// FromIterator`.
+///
+/// A trailing separator is consumed if present. Propagates the error from `R`
+/// if no item could be read.
+///
+/// ```
+/// use chomp1::ascii::decimal;
+/// use chomp1::combinators::sep_end_by1;
+/// use chomp1::prelude::{parse_only, token};
+///
+/// let r: Result<Vec<u8>, _> =
+///     parse_only(|i| sep_end_by1(i, decimal, |i| token(i, b',')), b"1,2,3");
+///
+/// assert_eq!(r, Ok(vec![1, 2, 3]));
+/// ```
+#[inline]
+pub fn sep_end_by1<I: Input, T, E, R, F, U, N, V>(i: I, p: R, sep: F) -> ParseResult<I, T, E>
+where
+    T: FromIterator<U>,
+    E: From<N>,
+    R: FnMut(I) -> ParseResult<I, U, E>,
+    F: FnMut(I) -> ParseResult<I, V, N>,
+{
+    bounded::sep_end_by(i, 1.., p, sep)
+}
+
+/// Applies the parser `R` zero or more times, each match followed by the parser
+/// `F`. All matches from `R` will be collected into the type `T: FromIterator`.
+///
+/// If the parser or the separator registers error or incomplete this parser
+/// stops before the failed item and yields the collected value.
+///
+/// ```
+/// use chomp1::ascii::decimal;
+/// use chomp1::combinators::end_by;
+/// use chomp1::prelude::{parse_only, token};
+///
+/// let r: Result<Vec<u8>, _> = parse_only(|i| end_by(i, decimal, |i| token(i, b';')), b"1;2;3");
+///
+/// assert_eq!(r, Ok(vec![1, 2]));
+/// ```
+#[inline]
+pub fn end_by<I: Input, T, E, R, F, U, N, V>(i: I, p: R, sep: F) -> ParseResult<I, T, E>
+where
+    T: FromIterator<U>,
+    E: From<N>,
+    R: FnMut(I) -> ParseResult<I, U, E>,
+    F: FnMut(I) -> ParseResult<I, V, N>,
+{
+    bounded::end_by(i, .., p, sep)
+}
+
 /// Applies the parser `R` multiple times until the parser `F` succeeds and
 /// returns a `T: FromIterator` populated by the values yielded by `R`. Consumes
 /// the matched part of `F`.
@@ -340,6 +469,33 @@ where
     bounded::many_till(i, .., p, end)
 }
 
+/// Applies the parser `R` one or more times until the parser `F` succeeds and
+/// returns a `T: FromIterator` populated by the values yielded by `R`. Consumes
+/// the matched part of `F`.
+///
+/// `F` is only attempted once `R` has matched at least once. Errors from `R`
+/// are propagated.
+///
+/// ```
+/// use chomp1::combinators::many1_till;
+/// use chomp1::prelude::{parse_only, token, Error};
+///
+/// let p = |i| many1_till(i, |i| token(i, b'a'), |i| token(i, b';'));
+///
+/// assert_eq!(parse_only(p, b"aa;"), Ok(vec![b'a', b'a']));
+/// assert_eq!(parse_only(p, b";"), Err((&b";"[..], Error::expected(b'a'))));
+/// ```
+#[inline]
+pub fn many1_till<I: Input, T, E, R, F, U, N, V>(i: I, p: R, end: F) -> ParseResult<I, T, E>
+where
+    T: FromIterator<U>,
+    E: From<N>,
+    R: FnMut(I) -> ParseResult<I, U, E>,
+    F: FnMut(I) -> ParseResult<I, V, N>,
+{
+    bounded::many_till(i, 1.., p, end)
+}
+
 /// Runs the given parser until it fails, discarding matched input.
 ///
 /// Incomplete state will be propagated.
@@ -397,6 +553,29 @@ where
     bounded::skip_many(i, 1.., f)
 }
 
+/// Applies the parser `R` until the parser `F` succeeds, discarding the values
+/// yielded by `R`. Consumes the matched part of `F`.
+///
+/// Errors from `R` are propagated. This does not allocate, unlike `many_till`.
+///
+/// ```
+/// use chomp1::combinators::skip_many_till;
+/// use chomp1::prelude::{any, parse_only, string, take_remainder};
+///
+/// let p = |i| skip_many_till(i, any, |i| string(i, b"*/")).then(take_remainder);
+///
+/// assert_eq!(parse_only(p, b" comment */ rest"), Ok(&b" rest"[..]));
+/// ```
+#[inline]
+pub fn skip_many_till<I: Input, T, E, R, F, N, V>(i: I, p: R, end: F) -> ParseResult<I, (), E>
+where
+    E: From<N>,
+    R: FnMut(I) -> ParseResult<I, T, E>,
+    F: FnMut(I) -> ParseResult<I, V, N>,
+{
+    bounded::skip_many_till(i, .., p, end)
+}
+
 /// Returns the result of the given parser as well as the slice which matched
 /// it.
 ///
@@ -452,6 +631,379 @@ where
     }
 }
 
+/// Applies the parser `p` between the parsers `open` and `close`, returning the
+/// value yielded by `p`.
+///
+/// ```
+/// use chomp1::ascii::decimal;
+/// use chomp1::combinators::between;
+/// use chomp1::prelude::{parse_only, token};
+///
+/// let r: Result<u32, _> = parse_only(
+///     |i| between(i, |i| token(i, b'('), |i| token(i, b')'), decimal),
+///     b"(42)",
+/// );
+///
+/// assert_eq!(r, Ok(42));
+/// ```
+#[inline]
+pub fn between<I: Input, T, E, F, G, P, U, V>(i: I, open: F, close: G, p: P) -> ParseResult<I, T, E>
+where
+    F: FnOnce(I) -> ParseResult<I, U, E>,
+    G: FnOnce(I) -> ParseResult<I, V, E>,
+    P: FnOnce(I) -> ParseResult<I, T, E>,
+{
+    open(i).then(p).bind(|i, t| close(i).map(|_| t))
+}
+
+/// Succeeds without consuming any input if the parser `f` fails, fails with
+/// `Error::unexpected` if `f` succeeds.
+///
+/// Typically used to make sure a keyword is not the prefix of a longer
+/// identifier.
+///
+/// ```
+/// use chomp1::ascii::is_alphanumeric;
+/// use chomp1::combinators::not_followed_by;
+/// use chomp1::prelude::{parse_only, satisfy, string, Error};
+///
+/// let p = |i| string(i, b"let").bind(|i, k| not_followed_by(i, |i| satisfy(i, is_alphanumeric)).map(|_| k));
+///
+/// assert_eq!(parse_only(p, b"let x"), Ok(&b"let"[..]));
+/// assert_eq!(parse_only(p, b"letter"), Err((&b"ter"[..], Error::unexpected())));
+/// ```
+#[inline]
+pub fn not_followed_by<I: Input, T, E, F>(i: I, f: F) -> ParseResult<I, (), Error<I::Token>>
+where
+    F: FnOnce(I) -> ParseResult<I, T, E>,
+{
+    let m = i.mark();
+
+    match f(i).into_inner() {
+        (b, Ok(_)) => b.restore(m).err(Error::unexpected()),
+        (b, Err(_)) => b.restore(m).ret(()),
+    }
+}
+
+/// Error produced by `depth_limited` when the nesting limit is exceeded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct DepthLimitExceeded;
+
+impl fmt::Display for DepthLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "nesting depth limit exceeded")
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for DepthLimitExceeded {}
+
+/// Nesting counter shared by the recursive parsers of a grammar, see
+/// `depth_limited`.
+#[derive(Debug)]
+pub struct Depth {
+    current: Cell<usize>,
+    max: usize,
+}
+
+impl Depth {
+    /// Creates a counter allowing at most `max` nested `depth_limited` calls.
+    pub fn new(max: usize) -> Self {
+        Depth {
+            current: Cell::new(0),
+            max,
+        }
+    }
+
+    /// Number of `depth_limited` calls currently active.
+    pub fn current(&self) -> usize {
+        self.current.get()
+    }
+
+    /// The maximum nesting depth.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+}
+
+/// Runs the parser `f` one nesting level deeper, failing with
+/// `DepthLimitExceeded` instead of running `f` if `depth` already is at its
+/// maximum.
+///
+/// Wrap the recursive step of a grammar in this combinator to turn stack
+/// overflows on deeply nested input into parse errors. The same `Depth` has to
+/// be used by all the levels, usually by passing it along as a parameter.
+///
+/// Note that backtracking combinators like `or` and `option` will discard the
+/// error like any other, so the recursion should be decided without them.
+///
+/// ```
+/// use chomp1::combinators::{depth_limited, Depth, DepthLimitExceeded};
+/// use chomp1::parse_only;
+/// use chomp1::parsers;
+/// use chomp1::prelude::{peek, token, Input, ParseResult, U8Input};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Error {
+///     Syntax,
+///     TooDeep,
+/// }
+///
+/// impl From<parsers::Error<u8>> for Error {
+///     fn from(_: parsers::Error<u8>) -> Self {
+///         Error::Syntax
+///     }
+/// }
+///
+/// impl From<DepthLimitExceeded> for Error {
+///     fn from(_: DepthLimitExceeded) -> Self {
+///         Error::TooDeep
+///     }
+/// }
+///
+/// // Counts the levels of nested brackets
+/// fn nested<I: U8Input>(i: I, d: &Depth) -> ParseResult<I, usize, Error> {
+///     token(i, b'[').map_err(Error::from).then(|i| {
+///         peek(i)
+///             .map_err(Error::from)
+///             .bind(|i, c| match c {
+///                 Some(b'[') => depth_limited(i, d, |i| nested(i, d)),
+///                 _ => i.ret(0),
+///             })
+///             .bind(|i, n| token(i, b']').map_err(Error::from).map(|_| n + 1))
+///     })
+/// }
+///
+/// // The outermost level is not wrapped in `depth_limited`
+/// let d = Depth::new(2);
+///
+/// assert_eq!(parse_only(|i| nested(i, &d), b"[[[]]]"), Ok(3));
+/// assert_eq!(
+///     parse_only(|i| nested(i, &d), b"[[[[]]]]").map_err(|(_, e)| e),
+///     Err(Error::TooDeep)
+/// );
+/// assert_eq!(d.current(), 0);
+/// ```
+#[inline]
+pub fn depth_limited<I: Input, T, E, F>(i: I, depth: &Depth, f: F) -> ParseResult<I, T, E>
+where
+    F: FnOnce(I) -> ParseResult<I, T, E>,
+    E: From<DepthLimitExceeded>,
+{
+    let n = depth.current.get();
+
+    if n >= depth.max {
+        return i.err(DepthLimitExceeded.into());
+    }
+
+    depth.current.set(n + 1);
+
+    let r = f(i);
+
+    depth.current.set(n);
+
+    r
+}
+
+/// Memo of the left-recursive invocations of a grammar rule, see `left_rec`.
+///
+/// Each active invocation keeps the position it started at and the longest
+/// result found so far (the seed).
+#[cfg(feature = "std")]
+pub struct LeftRec<I: Input, T> {
+    frames: RefCell<Vec<Frame<I::Marker, T>>>,
+}
+
+/// Active invocation of a `left_rec` rule.
+#[cfg(feature = "std")]
+struct Frame<M, T> {
+    /// Position the invocation started at.
+    start: M,
+    /// Longest match so far and the number of tokens it consumed.
+    seed: Option<(T, usize)>,
+}
+
+#[cfg(feature = "std")]
+impl<I: Input, T> LeftRec<I, T> {
+    /// Creates an empty memo.
+    pub fn new() -> Self {
+        LeftRec {
+            frames: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Number of invocations currently growing their seed.
+    pub fn active(&self) -> usize {
+        self.frames.borrow().len()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<I: Input, T> Default for LeftRec<I, T> {
+    fn default() -> Self {
+        LeftRec::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<I: Input, T> fmt::Debug for LeftRec<I, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LeftRec")
+            .field("active", &self.active())
+            .finish()
+    }
+}
+
+/// Runs the possibly left-recursive rule `f`, growing its result until it no
+/// longer consumes more input.
+///
+/// A rule like `expr = expr "+" term | term` can be transcribed directly by
+/// calling the rule itself in the body of `f`, as long as every invocation goes
+/// through `left_rec` with the same `LeftRec`. When the rule calls itself again
+/// at the position it started at, the recursive call yields the longest result
+/// found so far at that position, or fails with `Error::unexpected` on the
+/// first attempt. `f` is then run again until the match stops growing, which
+/// makes binary operators left-associative. Indirect left recursion is
+/// supported as long as one rule of the cycle is wrapped.
+///
+/// Note: Requires the `std` feature, `T` is cloned for every recursive call.
+///
+/// ```
+/// use chomp1::ascii::decimal;
+/// use chomp1::combinators::{left_rec, or, LeftRec};
+/// use chomp1::prelude::{parse_only, token, SimpleResult, U8Input};
+///
+/// // expr = expr "-" num | num
+/// fn expr<I: U8Input>(i: I, rule: &LeftRec<I, i32>) -> SimpleResult<I, i32>
+/// where
+///     I::Marker: Clone,
+/// {
+///     left_rec(i, rule, |i| {
+///         or(
+///             i,
+///             |i| expr(i, rule).bind(|i, l| token(i, b'-').then(decimal).map(|r: i32| l - r)),
+///             decimal,
+///         )
+///     })
+/// }
+///
+/// let rule = LeftRec::new();
+///
+/// assert_eq!(parse_only(|i| expr(i, &rule), b"10-2-3"), Ok(5));
+/// ```
+#[cfg(feature = "std")]
+pub fn left_rec<I: Input, T, E, F>(mut i: I, rule: &LeftRec<I, T>, mut f: F) -> ParseResult<I, T, E>
+where
+    I::Marker: Clone,
+    T: Clone,
+    E: From<Error<I::Token>>,
+    F: FnMut(I) -> ParseResult<I, T, E>,
+{
+    // Called again at the position of the innermost active invocation
+    let seed = match rule.frames.borrow().last() {
+        Some(f) if i.consume_from(f.start.clone()).is_empty() => Some(f.seed.clone()),
+        _ => None,
+    };
+
+    match seed {
+        Some(Some((t, n))) => {
+            return match i.consume(n) {
+                Some(_) => i.ret(t),
+                None => i.err(Error::unexpected().into()),
+            };
+        }
+        Some(None) => return i.err(Error::unexpected().into()),
+        None => {}
+    }
+
+    let start = i.mark();
+
+    rule.frames.borrow_mut().push(Frame {
+        start: start.clone(),
+        seed: None,
+    });
+
+    let mut i = loop {
+        let (mut b, r) = f(i).into_inner();
+        let n = b.consume_from(start.clone()).len();
+        let mut frames = rule.frames.borrow_mut();
+        let seed = &mut frames.last_mut().expect("LeftRec frame was popped").seed;
+
+        match (r, seed.as_ref().map(|s| s.1)) {
+            // First match or the match grew, try to grow it further
+            (Ok(t), None) => *seed = Some((t, n)),
+            (Ok(t), Some(m)) if n > m => *seed = Some((t, n)),
+            // Failed without ever matching
+            (Err(e), None) => {
+                frames.pop();
+
+                return b.err(e);
+            }
+            // Could not grow any further
+            (_, Some(_)) => break b.restore(start),
+        }
+
+        i = b.restore(start.clone());
+    };
+
+    let frame = rule
+        .frames
+        .borrow_mut()
+        .pop()
+        .expect("LeftRec frame was popped");
+    let (t, n) = frame.seed.expect("LeftRec seed was empty");
+
+    match i.consume(n) {
+        Some(_) => i.ret(t),
+        None => i.err(Error::unexpected().into()),
+    }
+}
+
+/// Runs the parser `f` under the name `name`, reporting when it is entered and
+/// whether it succeeded.
+///
+/// Events are only recorded when the `trace` feature is enabled and the input
+/// is wrapped in `trace::Traced`, otherwise this is equivalent to `f(i)`.
+///
+/// ```
+/// use chomp1::combinators::trace;
+/// use chomp1::prelude::{parse_only, token};
+///
+/// assert_eq!(parse_only(|i| trace(i, "a", |i| token(i, b'a')), b"abc"), Ok(b'a'));
+/// ```
+#[inline]
+pub fn trace<I: Input, T, E, F>(i: I, name: &'static str, f: F) -> ParseResult<I, T, E>
+where
+    F: FnOnce(I) -> ParseResult<I, T, E>,
+{
+    #[cfg(feature = "trace")]
+    {
+        use crate::trace::EventKind;
+
+        i.trace(name, EventKind::Enter);
+
+        let (b, r) = f(i).into_inner();
+
+        b.trace(
+            name,
+            if r.is_ok() {
+                EventKind::Success
+            } else {
+                EventKind::Failure
+            },
+        );
+
+        b.from_result(r)
+    }
+
+    #[cfg(not(feature = "trace"))]
+    {
+        let _ = name;
+
+        f(i)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -832,6 +1384,215 @@ mod test {
         );
     }
 
+    #[test]
+    fn between_test() {
+        let p = |i| between(i, |i| token(i, b'['), |i| token(i, b']'), any);
+
+        assert_eq!(p(&b"[a]b"[..]).into_inner(), (&b"b"[..], Ok(b'a')));
+        assert_eq!(
+            p(&b"a]"[..]).into_inner(),
+            (&b"a]"[..], Err(Error::expected(b'[')))
+        );
+        assert_eq!(
+            p(&b"[ab"[..]).into_inner(),
+            (&b"b"[..], Err(Error::expected(b']')))
+        );
+    }
+
+    #[test]
+    fn not_followed_by_test() {
+        assert_eq!(
+            not_followed_by(&b"ab"[..], |i| token(i, b'b')).into_inner(),
+            (&b"ab"[..], Ok(()))
+        );
+        assert_eq!(
+            not_followed_by(&b"ab"[..], |i| token(i, b'a')).into_inner(),
+            (&b"ab"[..], Err(Error::unexpected()))
+        );
+        assert_eq!(
+            not_followed_by(&b""[..], any).into_inner(),
+            (&b""[..], Ok(()))
+        );
+    }
+
+    #[test]
+    fn count_min_max_test() {
+        let r: ParseResult<_, Vec<_>, _> = count_min_max(&b"aaab"[..], 0, 2, |i| token(i, b'a'));
+        assert_eq!(r.into_inner(), (&b"ab"[..], Ok(vec![b'a', b'a'])));
+        let r: ParseResult<_, Vec<_>, _> = count_min_max(&b"ab"[..], 1, 1, |i| token(i, b'a'));
+        assert_eq!(r.into_inner(), (&b"b"[..], Ok(vec![b'a'])));
+        let r: ParseResult<_, Vec<_>, _> = count_min_max(&b"ab"[..], 2, 3, |i| token(i, b'a'));
+        assert_eq!(r.into_inner(), (&b"b"[..], Err(Error::expected(b'a'))));
+        let r: ParseResult<_, Vec<_>, _> =
+            count_min_max(&b"aa"[..], 1, usize::MAX, |i| token(i, b'a'));
+        assert_eq!(r.into_inner(), (&b""[..], Ok(vec![b'a', b'a'])));
+    }
+
+    #[test]
+    fn sep_end_by_test() {
+        let r: ParseResult<_, Vec<_>, _> = sep_end_by(&b"a;a;"[..], any, |i| token(i, b';'));
+        assert_eq!(r.into_inner(), (&b""[..], Ok(vec![b'a', b'a'])));
+        let r: ParseResult<_, Vec<_>, _> =
+            sep_end_by(&b";"[..], |i| token(i, b'a'), |i| token(i, b';'));
+        assert_eq!(r.into_inner(), (&b";"[..], Ok(vec![])));
+        let r: ParseResult<_, Vec<_>, _> =
+            sep_end_by1(&b";"[..], |i| token(i, b'a'), |i| token(i, b';'));
+        assert_eq!(r.into_inner(), (&b";"[..], Err(Error::expected(b'a'))));
+        let r: ParseResult<_, Vec<_>, _> =
+            sep_end_by1(&b"a;a"[..], |i| token(i, b'a'), |i| token(i, b';'));
+        assert_eq!(r.into_inner(), (&b""[..], Ok(vec![b'a', b'a'])));
+        let r: ParseResult<_, Vec<_>, _> = end_by(&b"a;a"[..], any, |i| token(i, b';'));
+        assert_eq!(r.into_inner(), (&b"a"[..], Ok(vec![b'a'])));
+    }
+
+    #[test]
+    fn many1_till_test() {
+        let r: ParseResult<_, Vec<_>, _> = many1_till(&b"ab;"[..], any, |i| token(i, b';'));
+        assert_eq!(r.into_inner(), (&b""[..], Ok(vec![b'a', b'b'])));
+        // The end parser is only tried after the first item
+        let r: ParseResult<_, Vec<_>, _> = many1_till(&b";;"[..], any, |i| token(i, b';'));
+        assert_eq!(r.into_inner(), (&b""[..], Ok(vec![b';'])));
+        assert_eq!(
+            skip_many_till(&b"abc"[..], any, |i| token(i, b'b')).into_inner(),
+            (&b"c"[..], Ok(()))
+        );
+        assert_eq!(
+            skip_many_till(&b"ac"[..], any, |i| token(i, b'b')).into_inner(),
+            (&b""[..], Err(Error::unexpected()))
+        );
+    }
+
+    #[test]
+    fn left_rec_test() {
+        use crate::ascii::decimal;
+        use crate::buffer::InputBuf;
+
+        type R<I> = ParseResult<I, i32, Error<u8>>;
+
+        // expr = expr "-" term | term
+        // term = "(" expr ")" | decimal
+        fn expr<I: Input<Token = u8>>(i: I, rule: &LeftRec<I, i32>) -> R<I>
+        where
+            I::Marker: Clone,
+        {
+            left_rec(i, rule, |i| {
+                or(
+                    i,
+                    |i| {
+                        expr(i, rule)
+                            .bind(|i, l| token(i, b'-').then(|i| term(i, rule)).map(|r| l - r))
+                    },
+                    |i| term(i, rule),
+                )
+            })
+        }
+
+        fn term<I: Input<Token = u8>>(i: I, rule: &LeftRec<I, i32>) -> R<I>
+        where
+            I::Marker: Clone,
+        {
+            or(
+                i,
+                |i| between(i, |i| token(i, b'('), |i| token(i, b')'), |i| expr(i, rule)),
+                decimal,
+            )
+        }
+
+        let rule = LeftRec::new();
+
+        assert_eq!(expr(&b"7"[..], &rule).into_inner(), (&b""[..], Ok(7)));
+        assert_eq!(expr(&b"7-2-1"[..], &rule).into_inner(), (&b""[..], Ok(4)));
+        assert_eq!(
+            expr(&b"7-(2-1)-(3)x"[..], &rule).into_inner(),
+            (&b"x"[..], Ok(3))
+        );
+        assert_eq!(expr(&b"7-"[..], &rule).into_inner(), (&b"-"[..], Ok(7)));
+        assert_eq!(
+            expr(&b"-"[..], &rule).into_inner(),
+            (&b"-"[..], Err(Error::unexpected()))
+        );
+        assert_eq!(rule.active(), 0);
+
+        let rule = LeftRec::new();
+        let (i, r) = expr(InputBuf::new(b"9-4-(1-1)-2"), &rule).into_inner();
+
+        assert_eq!(r, Ok(3));
+        assert_eq!(i.len(), 0);
+
+        // Indirect: a = b "x" | "y", b = a "z"
+        fn a<I: Input<Token = u8>>(
+            i: I,
+            rule: &LeftRec<I, usize>,
+        ) -> ParseResult<I, usize, Error<u8>>
+        where
+            I::Marker: Clone,
+        {
+            left_rec(i, rule, |i| {
+                or(
+                    i,
+                    |i| {
+                        a(i, rule)
+                            .bind(|i, n| token(i, b'z').then(|i| token(i, b'x')).map(|_| n + 1))
+                    },
+                    |i| token(i, b'y').map(|_| 0),
+                )
+            })
+        }
+
+        let rule = LeftRec::new();
+
+        assert_eq!(a(&b"yzxzxz"[..], &rule).into_inner(), (&b"z"[..], Ok(2)));
+    }
+
+    #[test]
+    fn depth_limited_test() {
+        #[derive(Debug, PartialEq)]
+        enum E {
+            Parse(Error<u8>),
+            Depth,
+        }
+
+        impl From<DepthLimitExceeded> for E {
+            fn from(_: DepthLimitExceeded) -> Self {
+                E::Depth
+            }
+        }
+
+        fn nested<I: Input<Token = u8>>(mut i: I, d: &Depth) -> ParseResult<I, usize, E> {
+            if i.peek() != Some(b'(') {
+                return i.ret(0);
+            }
+
+            token(i, b'(').map_err(E::Parse).then(|i| {
+                depth_limited(i, d, |i| nested(i, d))
+                    .bind(|i, n| token(i, b')').map_err(E::Parse).map(|_| n + 1))
+            })
+        }
+
+        let d = Depth::new(2);
+
+        assert_eq!(d.max(), 2);
+        assert_eq!(nested(&b"x"[..], &d).into_inner(), (&b"x"[..], Ok(0)));
+        assert_eq!(nested(&b"(())x"[..], &d).into_inner(), (&b"x"[..], Ok(2)));
+        assert_eq!(
+            nested(&b"((()))"[..], &d).into_inner(),
+            (&b")))"[..], Err(E::Depth))
+        );
+        assert_eq!(d.current(), 0);
+
+        let deep = vec![b'('; 1_000_000];
+
+        assert_eq!(nested(&deep[..], &d).into_inner().1, Err(E::Depth));
+        assert_eq!(d.current(), 0);
+
+        let d = Depth::new(0);
+
+        assert_eq!(
+            depth_limited(&b""[..], &d, |i| i.ret::<_, E>(())).into_inner(),
+            (&b""[..], Err(E::Depth))
+        );
+    }
+
     #[cfg(feature = "std")]
     mod choice_tests {
         use crate::combinators::choice;
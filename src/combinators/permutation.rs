@@ -0,0 +1,399 @@
+//! Matching a fixed set of members in any order.
+//!
+//! `permutation` takes a tuple of members created through `required` and
+//! `optional`, like the fields of a configuration section or the attributes of
+//! an element, and returns their values in declaration order regardless of the
+//! order they appeared in.
+//!
+//! A member which fails without consuming any input is considered not present
+//! at that position. Once a member has consumed input its errors are final,
+//! so a syntax error in the value of a field is not reported as a missing
+//! field.
+
+use std::error;
+use std::fmt;
+
+use crate::primitives::{IntoInner, Primitives};
+use crate::types::{Buffer, Input, ParseResult};
+
+/// Error produced by `permutation` when required members did not match.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MissingFields(Vec<&'static str>);
+
+impl MissingFields {
+    /// Names of the required members which did not match, in declaration
+    /// order.
+    pub fn names(&self) -> &[&'static str] {
+        &self.0
+    }
+}
+
+impl fmt::Display for MissingFields {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "missing required fields: {}", self.0.join(", "))
+    }
+}
+
+impl error::Error for MissingFields {}
+
+/// A named member of a `permutation`.
+///
+/// Created through `required` and `optional`.
+pub trait Member<I: Input, E> {
+    /// The type produced by the member parser.
+    type Value;
+    /// The type of this member in the result of the permutation.
+    type Output;
+
+    /// Name used in `MissingFields`.
+    fn name(&self) -> &'static str;
+
+    /// Returns true if the permutation fails when this member is missing.
+    fn is_required(&self) -> bool;
+
+    /// Runs the member parser.
+    fn parse(&mut self, i: I) -> ParseResult<I, Self::Value, E>;
+
+    /// Converts the matched value to the output, `None` if the member did not
+    /// match.
+    fn finish(v: Option<Self::Value>) -> Self::Output;
+}
+
+/// A member which has to match, see `required`.
+#[derive(Debug)]
+pub struct Required<F> {
+    name: &'static str,
+    f: F,
+}
+
+/// A member which may be missing, see `optional`.
+#[derive(Debug)]
+pub struct Optional<F> {
+    name: &'static str,
+    f: F,
+}
+
+/// Creates a member of a `permutation` which has to match, its value is
+/// returned as is.
+pub fn required<F>(name: &'static str, f: F) -> Required<F> {
+    Required { name, f }
+}
+
+/// Creates a member of a `permutation` which may be missing, its value is
+/// returned as an `Option`.
+pub fn optional<F>(name: &'static str, f: F) -> Optional<F> {
+    Optional { name, f }
+}
+
+impl<I: Input, T, E, F> Member<I, E> for Required<F>
+where
+    F: FnMut(I) -> ParseResult<I, T, E>,
+{
+    type Value = T;
+    type Output = T;
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn is_required(&self) -> bool {
+        true
+    }
+
+    fn parse(&mut self, i: I) -> ParseResult<I, T, E> {
+        (self.f)(i)
+    }
+
+    fn finish(v: Option<T>) -> T {
+        v.expect("permutation returned without a required member")
+    }
+}
+
+impl<I: Input, T, E, F> Member<I, E> for Optional<F>
+where
+    F: FnMut(I) -> ParseResult<I, T, E>,
+{
+    type Value = T;
+    type Output = Option<T>;
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn is_required(&self) -> bool {
+        false
+    }
+
+    fn parse(&mut self, i: I) -> ParseResult<I, T, E> {
+        (self.f)(i)
+    }
+
+    fn finish(v: Option<T>) -> Option<T> {
+        v
+    }
+}
+
+/// A tuple of `Member`s which can be matched in any order.
+pub trait Permutation<I: Input, E> {
+    /// Tuple of the member outputs.
+    type Output;
+
+    /// Matches the members in any order, see `permutation`.
+    fn parse(&mut self, i: I) -> ParseResult<I, Self::Output, E>;
+}
+
+macro_rules! permutation_impl {
+    ( $( $m:ident $v:ident $n:tt ),+ ) => {
+        impl<I: Input, E, $( $m ),+> Permutation<I, E> for ( $( $m, )+ )
+        where
+            E: From<MissingFields>,
+            $( $m: Member<I, E> ),+
+        {
+            type Output = ( $( $m::Output, )+ );
+
+            #[allow(unused_assignments)]
+            fn parse(&mut self, mut i: I) -> ParseResult<I, Self::Output, E> {
+                $( let mut $v = None; )+
+                let mut progress = true;
+
+                while progress {
+                    progress = false;
+
+                    $(
+                        if $v.is_none() {
+                            let m = i.mark();
+
+                            i = match self.$n.parse(i).into_inner() {
+                                (b, Ok(t)) => {
+                                    $v = Some(t);
+                                    progress = true;
+
+                                    b
+                                }
+                                (mut b, Err(e)) => {
+                                    // Only absent if it failed before making progress
+                                    if !b.consume_from(m).is_empty() {
+                                        return b.err(e);
+                                    }
+
+                                    b
+                                }
+                            };
+                        }
+                    )+
+                }
+
+                let mut missing = Vec::new();
+
+                $(
+                    if $v.is_none() && self.$n.is_required() {
+                        missing.push(self.$n.name());
+                    }
+                )+
+
+                if missing.is_empty() {
+                    i.ret(( $( $m::finish($v), )+ ))
+                } else {
+                    i.err(MissingFields(missing).into())
+                }
+            }
+        }
+    };
+}
+
+permutation_impl!(A a 0);
+permutation_impl!(A a 0, B b 1);
+permutation_impl!(A a 0, B b 1, C c 2);
+permutation_impl!(A a 0, B b 1, C c 2, D d 3);
+permutation_impl!(A a 0, B b 1, C c 2, D d 3, F f 4);
+permutation_impl!(A a 0, B b 1, C c 2, D d 3, F f 4, G g 5);
+permutation_impl!(A a 0, B b 1, C c 2, D d 3, F f 4, G g 5, H h 6);
+permutation_impl!(A a 0, B b 1, C c 2, D d 3, F f 4, G g 5, H h 6, J j 7);
+permutation_impl!(A a 0, B b 1, C c 2, D d 3, F f 4, G g 5, H h 6, J j 7, K k 8);
+permutation_impl!(A a 0, B b 1, C c 2, D d 3, F f 4, G g 5, H h 6, J j 7, K k 8, L l 9);
+
+/// Matches the members of the tuple `p` in any order, each at most once, and
+/// returns their results in declaration order.
+///
+/// Members are created with `required` and `optional`. Matching stops once no
+/// remaining member matches without consuming input. A member which fails
+/// after consuming input fails the whole permutation with its error, members
+/// which should be able to start with the same tokens need to check their
+/// prefix without consuming it. If a required member did not match the
+/// permutation fails with `MissingFields` naming all of the missing members.
+/// Supports up to 10 members.
+///
+/// ```
+/// use chomp1::combinators::permutation::{optional, permutation, required, MissingFields};
+/// use chomp1::parsers::{self, string, take_while1, token};
+/// use chomp1::prelude::{parse_only, ParseResult, U8Input};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Error {
+///     Syntax,
+///     Missing(Vec<&'static str>),
+/// }
+///
+/// impl From<parsers::Error<u8>> for Error {
+///     fn from(_: parsers::Error<u8>) -> Self {
+///         Error::Syntax
+///     }
+/// }
+///
+/// impl From<MissingFields> for Error {
+///     fn from(e: MissingFields) -> Self {
+///         Error::Missing(e.names().to_vec())
+///     }
+/// }
+///
+/// fn field<I: U8Input>(i: I, key: &'static [u8]) -> ParseResult<I, I::Buffer, Error> {
+///     string(i, key)
+///         .then(|i| token(i, b'='))
+///         .then(|i| take_while1(i, |c| c != b';'))
+///         .bind(|i, v| token(i, b';').map(|_| v))
+///         .map_err(Error::from)
+/// }
+///
+/// let config = |i| {
+///     permutation(
+///         i,
+///         (
+///             required("host", |i| field(i, b"host")),
+///             required("port", |i| field(i, b"port")),
+///             optional("user", |i| field(i, b"user")),
+///         ),
+///     )
+/// };
+///
+/// assert_eq!(
+///     parse_only(config, b"port=80;host=localhost;"),
+///     Ok((&b"localhost"[..], &b"80"[..], None))
+/// );
+/// assert_eq!(
+///     parse_only(config, b"user=root;").map_err(|(_, e)| e),
+///     Err(Error::Missing(vec!["host", "port"]))
+/// );
+/// // The port was present but its value is invalid
+/// assert_eq!(
+///     parse_only(config, b"host=localhost;port=;").map_err(|(_, e)| e),
+///     Err(Error::Syntax)
+/// );
+/// ```
+#[inline]
+pub fn permutation<I: Input, E, P>(i: I, mut p: P) -> ParseResult<I, P::Output, E>
+where
+    P: Permutation<I, E>,
+{
+    p.parse(i)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parsers::{token, Error};
+
+    #[derive(Debug, PartialEq)]
+    enum E {
+        Parse(Error<u8>),
+        Missing(MissingFields),
+    }
+
+    impl From<MissingFields> for E {
+        fn from(e: MissingFields) -> Self {
+            E::Missing(e)
+        }
+    }
+
+    fn t<I: Input<Token = u8>>(i: I, c: u8) -> ParseResult<I, u8, E> {
+        token(i, c).map_err(E::Parse)
+    }
+
+    #[test]
+    fn orders() {
+        let mut p = |i| {
+            permutation(
+                i,
+                (
+                    required("a", |i| t(i, b'a')),
+                    required("b", |i| t(i, b'b')),
+                    optional("c", |i| t(i, b'c')),
+                ),
+            )
+        };
+
+        for s in &[&b"abc"[..], b"acb", b"bac", b"bca", b"cab", b"cba"] {
+            assert_eq!(
+                p(*s).into_inner(),
+                (&b""[..], Ok((b'a', b'b', Some(b'c')))),
+                "{:?}",
+                s
+            );
+        }
+
+        assert_eq!(
+            p(&b"bax"[..]).into_inner(),
+            (&b"x"[..], Ok((b'a', b'b', None)))
+        );
+        // Each member is matched at most once
+        assert_eq!(
+            p(&b"abb"[..]).into_inner(),
+            (&b"b"[..], Ok((b'a', b'b', None)))
+        );
+    }
+
+    #[test]
+    fn missing() {
+        let (i, r) = permutation(
+            &b"cx"[..],
+            (
+                required("a", |i| t(i, b'a')),
+                optional("c", |i| t(i, b'c')),
+                required("b", |i| t(i, b'b')),
+            ),
+        )
+        .into_inner();
+
+        assert_eq!(i, &b"x"[..]);
+
+        match r {
+            Err(E::Missing(m)) => {
+                assert_eq!(m.names(), &["a", "b"]);
+                assert_eq!(m.to_string(), "missing required fields: a, b");
+            }
+            r => panic!("{:?}", r),
+        }
+
+        assert_eq!(
+            permutation(&b""[..], (optional("a", |i| t(i, b'a')),)).into_inner(),
+            (&b""[..], Ok((None,)))
+        );
+    }
+
+    #[test]
+    fn member_errors() {
+        fn ab<I: Input<Token = u8>>(i: I) -> ParseResult<I, u8, E> {
+            t(i, b'a').then(|i| t(i, b'b'))
+        }
+
+        let p = |i| {
+            permutation(
+                i,
+                (
+                    required("ab", ab),
+                    optional("c", |i| t(i, b'c')),
+                    required("d", |i| t(i, b'd')),
+                ),
+            )
+        };
+
+        assert_eq!(
+            p(&b"dcab"[..]).into_inner(),
+            (&b""[..], Ok((b'b', Some(b'c'), b'd')))
+        );
+        // "ab" matched its "a" and then failed, which is not a missing member
+        assert_eq!(
+            p(&b"dacb"[..]).into_inner(),
+            (&b"cb"[..], Err(E::Parse(Error::expected(b'b'))))
+        );
+    }
+}
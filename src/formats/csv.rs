@@ -0,0 +1,673 @@
+//! CSV and TSV parser following [RFC 4180](https://tools.ietf.org/html/rfc4180).
+//!
+//! `record` parses a single record from any byte input. `Reader` parses
+//! records one at a time from a `buffer::Stream`, optionally treating the first
+//! record as a header row and checking that all records have the same number
+//! of fields.
+//!
+//! Fields are borrowed from the input unless they contain escaped quotes, so
+//! unquoted fields never allocate. Records may end in `CRLF`, a bare `LF` or
+//! the end of the input. The delimiter, quote and escape characters are
+//! configurable through `Config`.
+//!
+//! ```
+//! use chomp1::buffer::SliceStream;
+//! use chomp1::formats::csv::{Config, Reader};
+//!
+//! let mut s = SliceStream::new(b"name,lang\r\nchomp,\"Rust, stable\"\r\n");
+//! let mut r = Reader::new(Config::new().with_headers(true));
+//!
+//! let record = r.next_record(&mut s).unwrap().unwrap();
+//!
+//! assert_eq!(record.get(0), Some(&b"chomp"[..]));
+//! assert_eq!(r.headers().unwrap().get(&record, b"lang"), Some(&b"Rust, stable"[..]));
+//! assert_eq!(r.next_record(&mut s), Ok(None));
+//! ```
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+
+use crate::buffer::{Stream, StreamError};
+use crate::parsers;
+use crate::parsers::{skip_while, token, SimpleResult};
+use crate::primitives::{IntoInner, Primitives};
+use crate::types::{Input, ParseResult, U8Input};
+
+/// Errors produced by the CSV parsers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The input did not match the CSV grammar, for example a quote inside of
+    /// an unquoted field or data following a closing quote.
+    Syntax(parsers::Error<u8>),
+    /// A record had a different number of fields than the first record.
+    FieldCount {
+        /// Number of fields in the first record
+        expected: usize,
+        /// Number of fields in this record
+        found: usize,
+    },
+}
+
+impl From<parsers::Error<u8>> for Error {
+    #[inline]
+    fn from(e: parsers::Error<u8>) -> Self {
+        Error::Syntax(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::Syntax(ref e) => write!(f, "syntax error: {}", e),
+            Error::FieldCount { expected, found } => {
+                write!(f, "expected {} fields, found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// CSV dialect and reader configuration.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Config {
+    delimiter: u8,
+    quote: u8,
+    escape: Option<u8>,
+    headers: bool,
+    flexible: bool,
+}
+
+impl Config {
+    /// Creates an RFC 4180 configuration: fields separated by `,`, quoted
+    /// using `"` and quotes escaped by doubling them. There is no header row
+    /// and all records must have the same number of fields.
+    #[inline]
+    pub fn new() -> Self {
+        Config {
+            delimiter: b',',
+            quote: b'"',
+            escape: None,
+            headers: false,
+            flexible: false,
+        }
+    }
+
+    /// Creates a configuration for tab separated values, otherwise like
+    /// `new`.
+    #[inline]
+    pub fn tsv() -> Self {
+        Self::new().with_delimiter(b'\t')
+    }
+
+    /// Sets the field delimiter.
+    #[inline]
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+
+        self
+    }
+
+    /// Sets the quote character.
+    #[inline]
+    pub fn with_quote(mut self, quote: u8) -> Self {
+        self.quote = quote;
+
+        self
+    }
+
+    /// Sets the escape character used inside of quoted fields, `None` means
+    /// that a quote is escaped by doubling it.
+    #[inline]
+    pub fn with_escape(mut self, escape: Option<u8>) -> Self {
+        self.escape = escape;
+
+        self
+    }
+
+    /// If `true` the `Reader` treats the first record as a header row.
+    #[inline]
+    pub fn with_headers(mut self, headers: bool) -> Self {
+        self.headers = headers;
+
+        self
+    }
+
+    /// If `true` the `Reader` accepts records with differing numbers of
+    /// fields.
+    #[inline]
+    pub fn with_flexible(mut self, flexible: bool) -> Self {
+        self.flexible = flexible;
+
+        self
+    }
+
+    /// The field delimiter.
+    #[inline]
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    /// The quote character.
+    #[inline]
+    pub fn quote(&self) -> u8 {
+        self.quote
+    }
+
+    /// The escape character, `None` if quotes are doubled.
+    #[inline]
+    pub fn escape(&self) -> Option<u8> {
+        self.escape
+    }
+
+    /// If the first record is a header row.
+    #[inline]
+    pub fn headers(&self) -> bool {
+        self.headers
+    }
+
+    /// If records may have differing numbers of fields.
+    #[inline]
+    pub fn flexible(&self) -> bool {
+        self.flexible
+    }
+
+    /// Escape character distinct from the quote, a quote used as escape
+    /// character is the same as doubling quotes.
+    #[inline]
+    fn escape_char(&self) -> Option<u8> {
+        self.escape.filter(|&e| e != self.quote)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::new()
+    }
+}
+
+/// A record borrowing from the input.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Record<'a> {
+    /// Unquoted field contents, borrowed unless a field contained escapes
+    pub fields: Vec<Cow<'a, [u8]>>,
+}
+
+impl<'a> Record<'a> {
+    /// The number of fields.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// Returns `true` if the record has no fields.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Returns the field at `index`.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        self.fields.get(index).map(|f| &**f)
+    }
+
+    /// Iterates over the fields.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        self.fields.iter().map(|f| &**f)
+    }
+}
+
+/// Owned header row mapping field names to indices.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Headers {
+    names: Vec<Vec<u8>>,
+}
+
+impl Headers {
+    /// The field names in order.
+    #[inline]
+    pub fn names(&self) -> &[Vec<u8>] {
+        &self.names
+    }
+
+    /// Returns the index of the first field called `name`.
+    #[inline]
+    pub fn index(&self, name: &[u8]) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    /// Returns the field called `name` from `record`.
+    #[inline]
+    pub fn get<'r>(&self, record: &'r Record<'_>, name: &[u8]) -> Option<&'r [u8]> {
+        self.index(name).and_then(|i| record.get(i))
+    }
+}
+
+impl<'a> From<Record<'a>> for Headers {
+    fn from(r: Record<'a>) -> Self {
+        Headers {
+            names: r.fields.into_iter().map(Cow::into_owned).collect(),
+        }
+    }
+}
+
+#[inline]
+fn is_eol(c: u8) -> bool {
+    c == b'\r' || c == b'\n'
+}
+
+#[inline]
+fn end_of_line<I: U8Input>(i: I) -> SimpleResult<I, u8> {
+    parse! {i; (token(b'\r') <|> ret b'\0') >> token(b'\n')}
+}
+
+/// Parses the remainder of a quoted field after the opening quote.
+fn quoted<'a, I>(mut i: I, config: Config) -> ParseResult<I, Cow<'a, [u8]>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    let (quote, escape) = (config.quote, config.escape_char());
+    let mut owned: Option<Vec<u8>> = None;
+
+    loop {
+        let chunk = i.consume_while(|c| c != quote && Some(c) != escape);
+
+        match i.pop() {
+            Some(c) if c == quote && (escape.is_some() || i.peek() != Some(quote)) => {
+                return i.ret(match owned {
+                    Some(mut o) => {
+                        o.extend_from_slice(chunk);
+
+                        Cow::Owned(o)
+                    }
+                    None => Cow::Borrowed(chunk),
+                });
+            }
+            Some(_) => {
+                // Doubled quote or escape character, keep the following character
+                let o = owned.get_or_insert_with(Vec::new);
+
+                o.extend_from_slice(chunk);
+
+                match i.pop() {
+                    Some(c) => o.push(c),
+                    None => return i.err(Error::Syntax(parsers::Error::expected(quote))),
+                }
+            }
+            None => return i.err(Error::Syntax(parsers::Error::expected(quote))),
+        }
+    }
+}
+
+#[inline]
+fn field<'a, I>(mut i: I, config: Config) -> ParseResult<I, Cow<'a, [u8]>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    let (delimiter, quote) = (config.delimiter, config.quote);
+
+    if i.peek() == Some(quote) {
+        i.pop();
+
+        quoted(i, config)
+    } else {
+        let f = i.consume_while(|c| c != delimiter && c != quote && !is_eol(c));
+
+        i.ret(Cow::Borrowed(f))
+    }
+}
+
+/// Parses a record, `expected` is the required number of fields.
+fn record_fields<'a, I>(
+    mut i: I,
+    config: Config,
+    expected: Option<usize>,
+) -> ParseResult<I, Record<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    let mut fields = Vec::new();
+
+    loop {
+        i = match field(i, config).into_inner() {
+            (b, Ok(f)) => {
+                fields.push(f);
+
+                b
+            }
+            (b, Err(e)) => return b.err(e),
+        };
+
+        match i.peek() {
+            Some(c) if c == config.delimiter => {
+                i.pop();
+            }
+            Some(c) if is_eol(c) => match end_of_line(i).into_inner() {
+                (b, Ok(_)) => {
+                    i = b;
+
+                    break;
+                }
+                (b, Err(e)) => return b.err(Error::Syntax(e)),
+            },
+            None => break,
+            Some(_) => return i.err(Error::Syntax(parsers::Error::unexpected())),
+        }
+    }
+
+    match expected {
+        Some(n) if n != fields.len() => i.err(Error::FieldCount {
+            expected: n,
+            found: fields.len(),
+        }),
+        _ => i.ret(Record { fields }),
+    }
+}
+
+/// Parses a single record including its line ending, using the default
+/// `Config`.
+///
+/// ```
+/// use chomp1::formats::csv::record;
+/// use chomp1::parse_only;
+///
+/// let r = parse_only(record, b"a,\"b \"\"c\"\"\",\n").unwrap();
+///
+/// assert_eq!(r.iter().collect::<Vec<_>>(), vec![&b"a"[..], b"b \"c\"", b""]);
+/// ```
+#[inline]
+pub fn record<'a, I>(i: I) -> ParseResult<I, Record<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    record_with(i, Config::new())
+}
+
+/// Parses a single record including its line ending using the supplied
+/// configuration.
+///
+/// The header row and field count settings of `config` are only used by
+/// `Reader`.
+#[inline]
+pub fn record_with<'a, I>(i: I, config: Config) -> ParseResult<I, Record<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    record_fields(i, config, None)
+}
+
+/// Skips empty lines, then parses a record if there is any input left.
+fn next<'a, I>(
+    i: I,
+    config: Config,
+    expected: Option<usize>,
+) -> ParseResult<I, Option<Record<'a>>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    skip_while(i, is_eol)
+        .map_err(Error::from)
+        .bind(|mut i, _| match i.peek() {
+            None => i.ret(None),
+            Some(_) => record_fields(i, config, expected).map(Some),
+        })
+}
+
+/// Pull-based record reader over a `Stream`.
+///
+/// Empty lines are skipped. If the configuration enables a header row, the
+/// first record is stored as `Headers` instead of being returned. Unless the
+/// configuration is flexible, all records must have as many fields as the
+/// first record.
+///
+/// Like `json::EventParser` the reader only updates its state once the stream
+/// accepts a record, so `StreamError::Retry` can be handled by calling
+/// `next_record` again.
+///
+/// ```
+/// use chomp1::buffer::{Source, StreamError};
+/// use chomp1::formats::csv::{Config, Reader};
+///
+/// let mut src = Source::new(&b"1\t2\n3\t4\n"[..]);
+/// let mut r = Reader::new(Config::tsv());
+/// let mut sum = 0;
+///
+/// loop {
+///     match r.next_record(&mut src) {
+///         Ok(Some(rec)) => {
+///             for f in rec.iter() {
+///                 sum += std::str::from_utf8(f).unwrap().parse::<u32>().unwrap();
+///             }
+///         }
+///         Ok(None) => break,
+///         Err(StreamError::Retry(_)) => {}
+///         Err(e) => panic!("{:?}", e),
+///     }
+/// }
+///
+/// assert_eq!(sum, 10);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Reader {
+    config: Config,
+    headers: Option<Headers>,
+    fields: Option<usize>,
+}
+
+impl Reader {
+    /// Creates a reader using the supplied configuration.
+    #[inline]
+    pub fn new(config: Config) -> Self {
+        Reader {
+            config,
+            headers: None,
+            fields: None,
+        }
+    }
+
+    /// The header row, once it has been read.
+    #[inline]
+    pub fn headers(&self) -> Option<&Headers> {
+        self.headers.as_ref()
+    }
+
+    /// Parses the next record from the stream, `Ok(None)` at the end of the
+    /// input.
+    pub fn next_record<'a, 'i, S>(
+        &mut self,
+        s: &'a mut S,
+    ) -> Result<Option<Record<'i>>, StreamError<&'i [u8], Error>>
+    where
+        S: Stream<'a, 'i>,
+        S::Input: Input<Token = u8, Buffer = &'i [u8]>,
+    {
+        let config = self.config;
+        let expected = if config.flexible { None } else { self.fields };
+        let read_headers = config.headers && self.headers.is_none();
+
+        let r = s.parse(|i| {
+            if read_headers {
+                next(i, config, expected).bind(|i, h| match h {
+                    Some(h) => {
+                        let n = Some(h.len()).filter(|_| !config.flexible);
+
+                        next(i, config, n).map(|r| (Some(h), r))
+                    }
+                    None => i.ret((None, None)),
+                })
+            } else {
+                next(i, config, expected).map(|r| (None, r))
+            }
+        });
+
+        match r {
+            Ok((h, r)) => {
+                if let Some(h) = h {
+                    self.fields = Some(h.len());
+                    self.headers = Some(h.into());
+                }
+
+                if let Some(ref r) = r {
+                    self.fields = self.fields.or(Some(r.len()));
+                }
+
+                Ok(r)
+            }
+            Err(StreamError::EndOfInput) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::buffer::{FixedSizeBuffer, SliceStream, Source};
+    use crate::parse_only;
+
+    fn fields(r: &Record<'_>) -> Vec<String> {
+        r.iter()
+            .map(|f| String::from_utf8_lossy(f).into_owned())
+            .collect()
+    }
+
+    /// Reads all records from `data` through a buffer of `size` bytes.
+    fn read(config: Config, data: &[u8], size: usize) -> Result<Vec<Vec<String>>, Error> {
+        let mut src = Source::from_read(data, FixedSizeBuffer::with_size(size));
+        let mut r = Reader::new(config);
+        let mut out = Vec::new();
+
+        loop {
+            match r.next_record(&mut src) {
+                Ok(Some(rec)) => out.push(fields(&rec)),
+                Ok(None) => return Ok(out),
+                Err(StreamError::Retry(_)) => {}
+                Err(StreamError::ParseError(_, e)) => return Err(e),
+                Err(e) => panic!("unexpected stream error: {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn records() {
+        let r = parse_only(record, b"a,b,c\r\n").unwrap();
+        assert_eq!(fields(&r), vec!["a", "b", "c"]);
+
+        let r = parse_only(record, b",\"\",").unwrap();
+        assert_eq!(fields(&r), vec!["", "", ""]);
+
+        let r = parse_only(record, b"\"a,b\",\"c\r\nd\",\"e\"\"f\"\n").unwrap();
+        assert_eq!(fields(&r), vec!["a,b", "c\r\nd", "e\"f"]);
+
+        for s in &[&b"a\"b,c"[..], b"\"a\"b,c", b"\"abc", b"a,b\rc"] {
+            assert!(
+                parse_only(record, s).is_err(),
+                "{:?}",
+                String::from_utf8_lossy(s)
+            );
+        }
+    }
+
+    #[test]
+    fn borrowed_fields() {
+        let r = parse_only(record, b"plain,\"quoted\",\"esc\"\"aped\"").unwrap();
+
+        assert!(matches!(r.fields[0], Cow::Borrowed(b"plain")));
+        assert!(matches!(r.fields[1], Cow::Borrowed(b"quoted")));
+        assert!(matches!(r.fields[2], Cow::Owned(_)));
+    }
+
+    #[test]
+    fn dialects() {
+        let config = Config::tsv();
+        let r = parse_only(|i| record_with(i, config), b"a,b\tc").unwrap();
+        assert_eq!(fields(&r), vec!["a,b", "c"]);
+
+        let config = Config::new().with_delimiter(b';').with_quote(b'\'');
+        let r = parse_only(|i| record_with(i, config), b"'a;b';'c''d'").unwrap();
+        assert_eq!(fields(&r), vec!["a;b", "c'd"]);
+
+        let config = Config::new().with_escape(Some(b'\\'));
+        let r = parse_only(|i| record_with(i, config), br#""a\"b\\c",d"#).unwrap();
+        assert_eq!(fields(&r), vec!["a\"b\\c", "d"]);
+
+        let config = Config::new().with_escape(Some(b'"'));
+        let r = parse_only(|i| record_with(i, config), br#""a""b""#).unwrap();
+        assert_eq!(fields(&r), vec!["a\"b"]);
+    }
+
+    #[test]
+    fn reader() {
+        let data = b"a,b\r\n\r\n1,\"x\r\ny\"\n2,3";
+
+        for &size in &[16, 64] {
+            assert_eq!(
+                read(Config::new(), data, size),
+                Ok(vec![
+                    vec!["a".to_owned(), "b".to_owned()],
+                    vec!["1".to_owned(), "x\r\ny".to_owned()],
+                    vec!["2".to_owned(), "3".to_owned()],
+                ])
+            );
+        }
+
+        assert_eq!(read(Config::new(), b"", 8), Ok(vec![]));
+        assert_eq!(read(Config::new(), b"\n\n", 8), Ok(vec![]));
+        assert_eq!(
+            read(Config::new(), b"a,b\nc\n", 8),
+            Err(Error::FieldCount {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            read(Config::new().with_flexible(true), b"a,b\nc\n", 8).map(|r| r.len()),
+            Ok(2)
+        );
+    }
+
+    #[test]
+    fn header_row() {
+        let mut s = SliceStream::new(b"id,name\n1,one\n2,two\n");
+        let mut r = Reader::new(Config::new().with_headers(true));
+
+        assert!(r.headers().is_none());
+
+        let rec = r.next_record(&mut s).unwrap().unwrap();
+        let h = r.headers().unwrap().clone();
+
+        assert_eq!(h.names(), &[b"id".to_vec(), b"name".to_vec()]);
+        assert_eq!(h.index(b"name"), Some(1));
+        assert_eq!(h.get(&rec, b"name"), Some(&b"one"[..]));
+        assert_eq!(h.get(&rec, b"missing"), None);
+
+        let rec = r.next_record(&mut s).unwrap().unwrap();
+        assert_eq!(h.get(&rec, b"id"), Some(&b"2"[..]));
+        assert_eq!(r.next_record(&mut s), Ok(None));
+
+        let mut s = SliceStream::new(b"id,name\n1\n");
+        let mut r = Reader::new(Config::new().with_headers(true));
+
+        assert_eq!(
+            r.next_record(&mut s).map_err(|e| match e {
+                StreamError::ParseError(_, e) => e,
+                e => panic!("{:?}", e),
+            }),
+            Err(Error::FieldCount {
+                expected: 2,
+                found: 1
+            })
+        );
+
+        let mut s = SliceStream::new(b"id,name\n");
+        let mut r = Reader::new(Config::new().with_headers(true));
+
+        assert_eq!(r.next_record(&mut s), Ok(None));
+        assert_eq!(r.headers().map(Headers::names).map(<[_]>::len), Some(2));
+    }
+}
@@ -0,0 +1,1054 @@
+//! HTTP/1.1 message parser following
+//! [RFC 9112](https://www.rfc-editor.org/rfc/rfc9112).
+//!
+//! `request` and `response` parse a message head, the start line and the
+//! header section, without copying: all names, values and the request target
+//! are slices of the input. Both accept obsolete line folding (obs-fold) in
+//! header values, see `Header::lines`, and bare `LF` line endings.
+//!
+//! The message body is decoded separately using a `BodyDecoder`, which reads
+//! `Content-Length`, `Transfer-Encoding: chunked` and read-until-close bodies
+//! incrementally from a `buffer::Stream`.
+//!
+//! ```
+//! use chomp1::buffer::{SliceStream, Stream};
+//! use chomp1::formats::http::{request, BodyDecoder};
+//!
+//! let mut s = SliceStream::new(b"POST /upload HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhello");
+//!
+//! let body = {
+//!     let req = s.parse(request).unwrap();
+//!
+//!     assert_eq!(req.method, b"POST");
+//!     assert_eq!(req.header(b"host").map(|h| h.value), Some(&b"example.com"[..]));
+//!
+//!     req.body_length().unwrap()
+//! };
+//!
+//! let mut decoder = BodyDecoder::new(body);
+//!
+//! assert_eq!(decoder.next_chunk(&mut s), Ok(Some(&b"hello"[..])));
+//! assert_eq!(decoder.next_chunk(&mut s), Ok(None));
+//! ```
+
+use std::error;
+use std::fmt;
+
+use crate::ascii::{is_digit, HTTP_TOKEN};
+use crate::buffer::{InputBuf, Stream, StreamError};
+use crate::parsers;
+use crate::parsers::{satisfy, string, take, take_while, take_while1, token, SimpleResult};
+use crate::primitives::{IntoInner, Primitives};
+use crate::types::{Buffer, Input, ParseResult, U8Input};
+
+/// Default maximum number of header fields in a message head or trailer
+/// section.
+pub const DEFAULT_MAX_HEADERS: usize = 100;
+
+/// Default maximum size in bytes of a single header field, name and value.
+pub const DEFAULT_MAX_HEADER_SIZE: usize = 8192;
+
+/// Errors produced by the HTTP parsers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The input did not match the HTTP grammar.
+    Syntax(parsers::Error<u8>),
+    /// The message contained more than `Config::max_headers` header fields.
+    TooManyHeaders,
+    /// A header field or a chunk size line was larger than
+    /// `Config::max_header_size`.
+    HeaderTooLarge,
+    /// The `Content-Length` header was malformed or had conflicting values.
+    InvalidContentLength,
+    /// A request used a `Transfer-Encoding` which does not end in `chunked`.
+    InvalidTransferEncoding,
+    /// A chunk size was malformed or too large.
+    InvalidChunkSize,
+}
+
+impl From<parsers::Error<u8>> for Error {
+    #[inline]
+    fn from(e: parsers::Error<u8>) -> Self {
+        Error::Syntax(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::Syntax(ref e) => write!(f, "syntax error: {}", e),
+            Error::TooManyHeaders => write!(f, "too many header fields"),
+            Error::HeaderTooLarge => write!(f, "header field too large"),
+            Error::InvalidContentLength => write!(f, "invalid content-length"),
+            Error::InvalidTransferEncoding => write!(f, "invalid transfer-encoding"),
+            Error::InvalidChunkSize => write!(f, "invalid chunk size"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Limits applied while parsing header sections.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Config {
+    max_headers: usize,
+    max_header_size: usize,
+}
+
+impl Config {
+    /// Creates a configuration allowing `DEFAULT_MAX_HEADERS` header fields of
+    /// at most `DEFAULT_MAX_HEADER_SIZE` bytes each.
+    #[inline]
+    pub fn new() -> Self {
+        Config {
+            max_headers: DEFAULT_MAX_HEADERS,
+            max_header_size: DEFAULT_MAX_HEADER_SIZE,
+        }
+    }
+
+    /// Sets the maximum number of header fields.
+    #[inline]
+    pub fn with_max_headers(mut self, max_headers: usize) -> Self {
+        self.max_headers = max_headers;
+
+        self
+    }
+
+    /// Sets the maximum size of a single header field, measured from the start
+    /// of the name to the end of the value including any folded lines.
+    ///
+    /// Also limits the size of the chunk size lines of a chunked body,
+    /// including chunk extensions.
+    #[inline]
+    pub fn with_max_header_size(mut self, max_header_size: usize) -> Self {
+        self.max_header_size = max_header_size;
+
+        self
+    }
+
+    /// The maximum number of header fields.
+    #[inline]
+    pub fn max_headers(&self) -> usize {
+        self.max_headers
+    }
+
+    /// The maximum size of a single header field.
+    #[inline]
+    pub fn max_header_size(&self) -> usize {
+        self.max_header_size
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::new()
+    }
+}
+
+/// HTTP version from the start line.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Version {
+    /// Major version
+    pub major: u8,
+    /// Minor version
+    pub minor: u8,
+}
+
+/// A header field borrowing from the input.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Header<'a> {
+    /// Field name, compare using `eq_ignore_ascii_case`
+    pub name: &'a [u8],
+    /// Field value without surrounding whitespace.
+    ///
+    /// If the value was folded over multiple lines the line breaks are kept,
+    /// use `lines` to iterate over the unfolded parts.
+    pub value: &'a [u8],
+}
+
+impl<'a> Header<'a> {
+    /// Iterates over the lines of an obs-folded value with surrounding
+    /// whitespace removed, yields the value itself if it is not folded.
+    ///
+    /// ```
+    /// use chomp1::formats::http::Header;
+    ///
+    /// let h = Header { name: b"X-Folded", value: b"a\r\n  b\n\tc" };
+    ///
+    /// assert_eq!(h.lines().collect::<Vec<_>>(), vec![&b"a"[..], b"b", b"c"]);
+    /// ```
+    pub fn lines(&self) -> impl Iterator<Item = &'a [u8]> {
+        self.value.split(|&c| c == b'\n').map(trim)
+    }
+}
+
+/// A request head.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Request<'a> {
+    /// Request method
+    pub method: &'a [u8],
+    /// Request target
+    pub target: &'a [u8],
+    /// Protocol version
+    pub version: Version,
+    /// Header fields in input order
+    pub headers: Vec<Header<'a>>,
+}
+
+impl<'a> Request<'a> {
+    /// Looks up the first header field with the given case-insensitive name.
+    #[inline]
+    pub fn header(&self, name: &[u8]) -> Option<&Header<'a>> {
+        find(&self.headers, name)
+    }
+
+    /// Determines how the length of the request body is delimited.
+    ///
+    /// Requests without `Transfer-Encoding` or `Content-Length` have no body.
+    pub fn body_length(&self) -> Result<BodyLength, Error> {
+        match (
+            transfer_chunked(&self.headers),
+            content_length(&self.headers)?,
+        ) {
+            (Some(true), _) => Ok(BodyLength::Chunked),
+            (Some(false), _) => Err(Error::InvalidTransferEncoding),
+            (None, Some(n)) => Ok(BodyLength::Fixed(n)),
+            (None, None) => Ok(BodyLength::Fixed(0)),
+        }
+    }
+}
+
+/// A response head.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Response<'a> {
+    /// Protocol version
+    pub version: Version,
+    /// Status code
+    pub status: u16,
+    /// Reason phrase, possibly empty
+    pub reason: &'a [u8],
+    /// Header fields in input order
+    pub headers: Vec<Header<'a>>,
+}
+
+impl<'a> Response<'a> {
+    /// Looks up the first header field with the given case-insensitive name.
+    #[inline]
+    pub fn header(&self, name: &[u8]) -> Option<&Header<'a>> {
+        find(&self.headers, name)
+    }
+
+    /// Determines how the length of the response body is delimited.
+    ///
+    /// Informational (`1xx`), `204` and `304` responses never have a body.
+    /// Responses to `HEAD` requests do not have a body either, this has to be
+    /// handled by the caller since it depends on the request.
+    pub fn body_length(&self) -> Result<BodyLength, Error> {
+        if self.status < 200 || self.status == 204 || self.status == 304 {
+            return Ok(BodyLength::Fixed(0));
+        }
+
+        match (
+            transfer_chunked(&self.headers),
+            content_length(&self.headers)?,
+        ) {
+            (Some(true), _) => Ok(BodyLength::Chunked),
+            (Some(false), _) => Ok(BodyLength::UntilClose),
+            (None, Some(n)) => Ok(BodyLength::Fixed(n)),
+            (None, None) => Ok(BodyLength::UntilClose),
+        }
+    }
+}
+
+/// How the length of a message body is determined.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum BodyLength {
+    /// The body is exactly this many bytes long.
+    Fixed(u64),
+    /// The body uses the chunked transfer coding.
+    Chunked,
+    /// The body ends when the connection is closed.
+    UntilClose,
+}
+
+#[inline]
+fn is_ows(c: u8) -> bool {
+    c == b' ' || c == b'\t'
+}
+
+#[inline]
+fn is_eol(c: u8) -> bool {
+    c == b'\r' || c == b'\n'
+}
+
+/// Visible characters, obs-text and whitespace allowed in field values and
+/// reason phrases.
+#[inline]
+fn is_field_char(c: u8) -> bool {
+    c == b'\t' || (c >= 0x20 && c != 0x7F)
+}
+
+#[inline]
+fn is_hex(c: u8) -> bool {
+    c.is_ascii_hexdigit()
+}
+
+/// Removes surrounding whitespace and line breaks.
+fn trim(s: &[u8]) -> &[u8] {
+    let f = |c: &u8| !(is_ows(*c) || is_eol(*c));
+
+    match s.iter().position(f) {
+        Some(start) => &s[start..=s.iter().rposition(f).unwrap()],
+        None => &s[..0],
+    }
+}
+
+fn find<'a, 'h>(headers: &'h [Header<'a>], name: &[u8]) -> Option<&'h Header<'a>> {
+    headers.iter().find(|h| h.name.eq_ignore_ascii_case(name))
+}
+
+/// Iterates over the comma separated list elements of all headers called
+/// `name`.
+fn list_values<'a, 'h>(
+    headers: &'h [Header<'a>],
+    name: &'h [u8],
+) -> impl Iterator<Item = &'a [u8]> + 'h {
+    headers
+        .iter()
+        .filter(move |h| h.name.eq_ignore_ascii_case(name))
+        .flat_map(|h| h.value.split(|&c| c == b','))
+        .map(trim)
+        .filter(|v| !v.is_empty())
+}
+
+/// `Some(true)` if the final transfer coding is chunked, `None` if there is
+/// no `Transfer-Encoding` header.
+fn transfer_chunked(headers: &[Header<'_>]) -> Option<bool> {
+    list_values(headers, b"transfer-encoding")
+        .last()
+        .map(|c| c.eq_ignore_ascii_case(b"chunked"))
+}
+
+/// Parses all `Content-Length` values, which must agree.
+fn content_length(headers: &[Header<'_>]) -> Result<Option<u64>, Error> {
+    let mut length = None;
+
+    for v in list_values(headers, b"content-length") {
+        if !v.iter().all(|&c| is_digit(c)) {
+            return Err(Error::InvalidContentLength);
+        }
+
+        let n = v
+            .iter()
+            .try_fold(0u64, |n, &c| {
+                n.checked_mul(10)?.checked_add(u64::from(c - b'0'))
+            })
+            .ok_or(Error::InvalidContentLength)?;
+
+        match length {
+            Some(m) if m != n => return Err(Error::InvalidContentLength),
+            _ => length = Some(n),
+        }
+    }
+
+    Ok(length)
+}
+
+/// Parses a line ending, either `CRLF` or a bare `LF`.
+#[inline]
+fn end_of_line<I: U8Input>(i: I) -> SimpleResult<I, u8> {
+    parse! {i; (token(b'\r') <|> ret b'\0') >> token(b'\n')}
+}
+
+fn version<I: U8Input>(i: I) -> SimpleResult<I, Version> {
+    parse! {i;
+                    string(b"HTTP/");
+        let major = satisfy(is_digit);
+                    token(b'.');
+        let minor = satisfy(is_digit);
+
+        ret Version {
+            major: major - b'0',
+            minor: minor - b'0',
+        }
+    }
+}
+
+fn request_line<I: U8Input>(i: I) -> SimpleResult<I, (I::Buffer, I::Buffer, Version)> {
+    parse! {i;
+        take_while(is_eol);
+        let method  = take_while1(HTTP_TOKEN.predicate());
+                      token(b' ');
+        let target  = take_while1(|c| c > b' ' && c != 0x7F);
+                      token(b' ');
+        let version = version();
+                      end_of_line();
+
+        ret (method, target, version)
+    }
+}
+
+fn status_line<I: U8Input>(i: I) -> SimpleResult<I, (Version, u16, I::Buffer)> {
+    parse! {i;
+        let version = version();
+                      token(b' ');
+        let status  = take(3);
+                      token(b' ');
+        let reason  = take_while(is_field_char);
+                      end_of_line();
+
+        ret (version, status, reason)
+    }
+    .bind(|i, (version, status, reason)| {
+        let status = status.fold(Some(0), |n, d| {
+            n.filter(|_| is_digit(d))
+                .map(|n| n * 10 + u16::from(d - b'0'))
+        });
+
+        match status {
+            Some(status) => i.ret((version, status, reason)),
+            None => i.err(parsers::Error::unexpected()),
+        }
+    })
+}
+
+/// Parses a field value up to and including the final line ending, following
+/// obs-folded continuation lines. `limit` is the remaining allowed size.
+fn field_value<'a, I>(mut i: I, limit: usize) -> ParseResult<I, &'a [u8], Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    let m = i.mark();
+    let mut len = 0;
+
+    loop {
+        i.consume_while(|c| {
+            is_field_char(c) && {
+                len += 1;
+
+                len <= limit
+            }
+        });
+
+        match i.peek() {
+            Some(c) if is_eol(c) => {}
+            Some(c) if is_field_char(c) => return i.err(Error::HeaderTooLarge),
+            // Control character, or the end of the input
+            _ => return i.err(Error::Syntax(parsers::Error::unexpected())),
+        }
+
+        // Length of the line ending, excluded from the value
+        let eol = match end_of_line(i).into_inner() {
+            (b, Ok(b'\r')) => {
+                i = b;
+
+                2
+            }
+            (b, Ok(_)) => {
+                i = b;
+
+                1
+            }
+            (b, Err(e)) => return b.err(Error::Syntax(e)),
+        };
+
+        match i.peek() {
+            // obs-fold, the value continues on the next line
+            Some(c) if is_ows(c) => len += eol,
+            Some(_) => {
+                let value = i.consume_from(m);
+
+                return i.ret(trim(&value[..value.len() - eol]));
+            }
+            None => return i.err(Error::Syntax(parsers::Error::unexpected())),
+        }
+    }
+}
+
+fn header<'a, I>(i: I, config: Config) -> ParseResult<I, Header<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    take_while1(i, HTTP_TOKEN.predicate())
+        .bind(|i, name| token(i, b':').map(|_| name))
+        .map_err(Error::from)
+        .bind(|i, name| {
+            if name.len() + 1 > config.max_header_size {
+                return i.err(Error::HeaderTooLarge);
+            }
+
+            field_value(i, config.max_header_size - name.len() - 1)
+                .map(|value| Header { name, value })
+        })
+}
+
+/// Parses header fields up to and including the empty line ending the
+/// section.
+fn headers<'a, I>(mut i: I, config: Config) -> ParseResult<I, Vec<Header<'a>>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    let mut headers = Vec::new();
+
+    loop {
+        match i.peek() {
+            Some(c) if is_eol(c) => return end_of_line(i).map(|_| headers).map_err(Error::from),
+            _ if headers.len() >= config.max_headers => return i.err(Error::TooManyHeaders),
+            _ => {}
+        }
+
+        match header(i, config).into_inner() {
+            (b, Ok(h)) => {
+                headers.push(h);
+
+                i = b;
+            }
+            (b, Err(e)) => return b.err(e),
+        }
+    }
+}
+
+/// Parses a request head using the default `Config`.
+///
+/// Empty lines preceding the request line are ignored.
+///
+/// ```
+/// use chomp1::formats::http::{request, Version};
+/// use chomp1::parse_only;
+///
+/// let r = parse_only(request, b"GET /robots.txt HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+///
+/// assert_eq!(r.target, b"/robots.txt");
+/// assert_eq!(r.version, Version { major: 1, minor: 1 });
+/// assert_eq!(r.headers.len(), 1);
+/// ```
+#[inline]
+pub fn request<'a, I>(i: I) -> ParseResult<I, Request<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    request_with(i, Config::new())
+}
+
+/// Parses a request head using the supplied configuration.
+pub fn request_with<'a, I>(i: I, config: Config) -> ParseResult<I, Request<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    request_line(i)
+        .map_err(Error::from)
+        .bind(|i, (method, target, version)| {
+            headers(i, config).map(|headers| Request {
+                method,
+                target,
+                version,
+                headers,
+            })
+        })
+}
+
+/// Parses a response head using the default `Config`.
+///
+/// ```
+/// use chomp1::formats::http::response;
+/// use chomp1::parse_only;
+///
+/// let r = parse_only(response, b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n").unwrap();
+///
+/// assert_eq!(r.status, 404);
+/// assert_eq!(r.reason, b"Not Found");
+/// ```
+#[inline]
+pub fn response<'a, I>(i: I) -> ParseResult<I, Response<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    response_with(i, Config::new())
+}
+
+/// Parses a response head using the supplied configuration.
+pub fn response_with<'a, I>(i: I, config: Config) -> ParseResult<I, Response<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    status_line(i)
+        .map_err(Error::from)
+        .bind(|i, (version, status, reason)| {
+            headers(i, config).map(|headers| Response {
+                version,
+                status,
+                reason,
+                headers,
+            })
+        })
+}
+
+/// Current position of a `BodyDecoder`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum BodyState {
+    /// This many bytes remain of a `Content-Length` body.
+    Fixed(u64),
+    /// Everything until the end of the input.
+    UntilClose,
+    /// Expecting a chunk-size line.
+    ChunkSize,
+    /// This many bytes remain of the current chunk.
+    ChunkData(u64),
+    /// Expecting the line ending following chunk data.
+    ChunkDataEnd,
+    /// The complete body has been read.
+    Done,
+}
+
+/// Incremental message body decoder.
+///
+/// Each call to `next_chunk` yields the body data available in the stream,
+/// without copying, until the end of the body is reached. Chunked framing is
+/// removed, chunk size lines are limited to `Config::max_header_size` and
+/// trailer fields are parsed according to the `Config` limits but discarded. Like `json::EventParser` the state is only updated once the
+/// stream accepts the parsed data, so `StreamError::Retry` can be handled by
+/// calling `next_chunk` again.
+///
+/// ```
+/// use chomp1::buffer::SliceStream;
+/// use chomp1::formats::http::{BodyDecoder, BodyLength};
+///
+/// let mut s = SliceStream::new(b"5;ext=1\r\nhello\r\n6\r\n world\r\n0\r\nExpires: never\r\n\r\n");
+/// let mut d = BodyDecoder::new(BodyLength::Chunked);
+/// let mut body = Vec::new();
+///
+/// while let Some(data) = d.next_chunk(&mut s).unwrap() {
+///     body.extend_from_slice(data);
+/// }
+///
+/// assert_eq!(body, b"hello world");
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BodyDecoder {
+    config: Config,
+    state: BodyState,
+}
+
+impl BodyDecoder {
+    /// Creates a decoder for a body of the given length using the default
+    /// `Config` for trailer fields.
+    #[inline]
+    pub fn new(length: BodyLength) -> Self {
+        Self::with_config(length, Config::new())
+    }
+
+    /// Creates a decoder for a body of the given length using the supplied
+    /// configuration for trailer fields.
+    #[inline]
+    pub fn with_config(length: BodyLength, config: Config) -> Self {
+        let state = match length {
+            BodyLength::Fixed(0) => BodyState::Done,
+            BodyLength::Fixed(n) => BodyState::Fixed(n),
+            BodyLength::Chunked => BodyState::ChunkSize,
+            BodyLength::UntilClose => BodyState::UntilClose,
+        };
+
+        BodyDecoder { config, state }
+    }
+
+    /// Returns `true` once the whole body has been read.
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.state == BodyState::Done
+    }
+
+    /// Reads the next part of the body from the stream, `Ok(None)` once the
+    /// body is complete.
+    ///
+    /// If the stream ends before the body is complete
+    /// `StreamError::Incomplete` is returned.
+    pub fn next_chunk<'a, 'i, S>(
+        &mut self,
+        s: &'a mut S,
+    ) -> Result<Option<&'i [u8]>, StreamError<&'i [u8], Error>>
+    where
+        S: Stream<'a, 'i, Input = InputBuf<'i, u8>>,
+    {
+        if self.state == BodyState::Done {
+            return Ok(None);
+        }
+
+        let (state, config) = (self.state, self.config);
+
+        match s.parse(|i| body_step(i, state, config)) {
+            Ok((state, data)) => {
+                self.state = state;
+
+                Ok(data)
+            }
+            Err(StreamError::EndOfInput) if self.state == BodyState::UntilClose => {
+                self.state = BodyState::Done;
+
+                Ok(None)
+            }
+            Err(StreamError::EndOfInput) => Err(StreamError::Incomplete(None)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Consumes at most `max` bytes of what is currently available in the buffer,
+/// requesting more data only if it is empty.
+#[inline]
+fn available(i: InputBuf<'_, u8>, max: u64) -> SimpleResult<InputBuf<'_, u8>, &[u8]> {
+    let n = if (i.len() as u64) < max {
+        i.len()
+    } else {
+        max as usize
+    };
+
+    take(i, n.max(1))
+}
+
+/// Parses a chunk size line of at most `limit` bytes, excluding the line
+/// ending.
+fn chunk_size<I: U8Input>(mut i: I, limit: usize) -> ParseResult<I, u64, Error> {
+    let mut len = 0;
+    let mut within = || {
+        len += 1;
+
+        len <= limit
+    };
+
+    let digits = i.consume_while(|c| is_hex(c) && within());
+
+    if digits.is_empty() {
+        return match i.peek() {
+            Some(c) if is_hex(c) => i.err(Error::HeaderTooLarge),
+            _ => i.err(Error::Syntax(parsers::Error::unexpected())),
+        };
+    }
+
+    let n = digits.fold(Some(0u64), |n, c| {
+        let d = (c as char).to_digit(16).unwrap();
+
+        n.and_then(|n| n.checked_mul(16)).map(|n| n + u64::from(d))
+    });
+
+    let n = match n {
+        Some(n) => n,
+        None => return i.err(Error::InvalidChunkSize),
+    };
+
+    // Chunk extensions are ignored
+    i.consume_while(|c| !is_eol(c) && within());
+
+    match i.peek() {
+        Some(c) if is_eol(c) => end_of_line(i).map(|_| n).map_err(Error::from),
+        Some(_) => i.err(Error::HeaderTooLarge),
+        None => i.err(Error::Syntax(parsers::Error::unexpected())),
+    }
+}
+
+/// Parses the framing up to the next body data, yielding the new state and the
+/// data.
+#[allow(clippy::type_complexity)]
+fn body_step(
+    i: InputBuf<'_, u8>,
+    state: BodyState,
+    config: Config,
+) -> ParseResult<InputBuf<'_, u8>, (BodyState, Option<&[u8]>), Error> {
+    let data = |i, n, next: BodyState, more: fn(u64) -> BodyState| {
+        available(i, n).map_err(Error::from).map(|d| {
+            let rest = n - d.len() as u64;
+
+            (if rest == 0 { next } else { more(rest) }, Some(d))
+        })
+    };
+
+    match state {
+        BodyState::Done => i.ret((BodyState::Done, None)),
+        BodyState::Fixed(n) => data(i, n, BodyState::Done, BodyState::Fixed),
+        BodyState::UntilClose => available(i, u64::MAX)
+            .map(|d| (BodyState::UntilClose, Some(d)))
+            .map_err(Error::from),
+        BodyState::ChunkData(n) => data(i, n, BodyState::ChunkDataEnd, BodyState::ChunkData),
+        BodyState::ChunkDataEnd => end_of_line(i)
+            .map_err(Error::from)
+            .bind(|i, _| body_step(i, BodyState::ChunkSize, config)),
+        BodyState::ChunkSize => chunk_size(i, config.max_header_size).bind(|i, n| {
+            if n == 0 {
+                headers(i, config).map(|_| (BodyState::Done, None))
+            } else {
+                body_step(i, BodyState::ChunkData(n), config)
+            }
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{self, Read};
+
+    use crate::buffer::{FixedSizeBuffer, GrowingBuffer, SliceStream, Source};
+    use crate::parse_only;
+
+    /// Decodes a body read from `data` through a buffer of `size` bytes, `None`
+    /// signals a truncated body.
+    fn body(length: BodyLength, data: &[u8], size: usize) -> Result<Vec<u8>, Option<Error>> {
+        let mut s = Source::from_read(data, FixedSizeBuffer::with_size(size));
+        let mut d = BodyDecoder::new(length);
+        let mut out = Vec::new();
+
+        loop {
+            match d.next_chunk(&mut s) {
+                Ok(Some(b)) => out.extend_from_slice(b),
+                Ok(None) => return Ok(out),
+                Err(StreamError::Retry(_)) => {}
+                Err(StreamError::ParseError(_, e)) => return Err(Some(e)),
+                Err(StreamError::Incomplete(_)) => return Err(None),
+                Err(e) => panic!("unexpected stream error: {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn request_head() {
+        let r = parse_only(
+            request,
+            b"\r\nGET /a?b=c HTTP/1.0\nHost:  example.com \r\nAccept: */*\r\nX-Empty:\r\n\r\nbody",
+        )
+        .unwrap();
+
+        assert_eq!(r.method, b"GET");
+        assert_eq!(r.target, b"/a?b=c");
+        assert_eq!(r.version, Version { major: 1, minor: 0 });
+        assert_eq!(
+            r.headers,
+            vec![
+                Header {
+                    name: b"Host",
+                    value: b"example.com"
+                },
+                Header {
+                    name: b"Accept",
+                    value: b"*/*"
+                },
+                Header {
+                    name: b"X-Empty",
+                    value: b""
+                },
+            ]
+        );
+        assert_eq!(r.header(b"ACCEPT").map(|h| h.value), Some(&b"*/*"[..]));
+        assert_eq!(r.header(b"missing"), None);
+        assert_eq!(r.body_length(), Ok(BodyLength::Fixed(0)));
+
+        for s in &[
+            &b"GET /  HTTP/1.1\r\n\r\n"[..],
+            b"GET / HTTP/1.1 \r\n\r\n",
+            b"GET / HTTP/11\r\n\r\n",
+            b"G(T / HTTP/1.1\r\n\r\n",
+            b"GET / HTTP/1.1\r\nHost : x\r\n\r\n",
+            b"GET / HTTP/1.1\r\n Host: x\r\n\r\n",
+            b"GET / HTTP/1.1\r\nHost: a\x00b\r\n\r\n",
+            b"GET / HTTP/1.1\r\nHost: x\r\n",
+        ] {
+            assert!(
+                parse_only(request, s).is_err(),
+                "{:?}",
+                String::from_utf8_lossy(s)
+            );
+        }
+    }
+
+    #[test]
+    fn response_head() {
+        let r = parse_only(response, b"HTTP/1.1 200 OK\r\nServer: chomp\r\n\r\n").unwrap();
+
+        assert_eq!(r.version, Version { major: 1, minor: 1 });
+        assert_eq!(r.status, 200);
+        assert_eq!(r.reason, b"OK");
+        assert_eq!(r.body_length(), Ok(BodyLength::UntilClose));
+
+        let r = parse_only(response, b"HTTP/1.1 204 \r\nContent-Length: 10\r\n\r\n").unwrap();
+
+        assert_eq!(r.reason, b"");
+        assert_eq!(r.body_length(), Ok(BodyLength::Fixed(0)));
+
+        assert!(parse_only(response, b"HTTP/1.1 20 OK\r\n\r\n").is_err());
+        assert!(parse_only(response, b"HTTP/1.1 2000 OK\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn obs_fold() {
+        let r = parse_only(
+            request,
+            b"GET / HTTP/1.1\r\nX-A: one\r\n two\r\n\tthree \r\nX-B: b\r\n\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(r.headers[0].value, b"one\r\n two\r\n\tthree");
+        assert_eq!(
+            r.headers[0].lines().collect::<Vec<_>>(),
+            vec![&b"one"[..], b"two", b"three"]
+        );
+        assert_eq!(
+            r.headers[1],
+            Header {
+                name: b"X-B",
+                value: b"b"
+            }
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let head = b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nCookie: 0123456789\r\n\r\n";
+
+        let config = Config::new().with_max_headers(2);
+        assert_eq!(
+            parse_only(|i| request_with(i, config), head).map_err(|(_, e)| e),
+            Err(Error::TooManyHeaders)
+        );
+
+        let config = Config::new().with_max_header_size(16);
+        assert_eq!(
+            parse_only(|i| request_with(i, config), head).map_err(|(_, e)| e),
+            Err(Error::HeaderTooLarge)
+        );
+
+        let config = Config::new().with_max_headers(3).with_max_header_size(18);
+        assert!(parse_only(|i| request_with(i, config), head).is_ok());
+    }
+
+    #[test]
+    fn body_length() {
+        let len = |h: &[u8]| parse_only(request, h).unwrap().body_length();
+
+        assert_eq!(
+            len(b"POST / HTTP/1.1\r\nContent-Length: 42\r\n\r\n"),
+            Ok(BodyLength::Fixed(42))
+        );
+        assert_eq!(
+            len(b"POST / HTTP/1.1\r\nContent-Length: 4, 4\r\nContent-Length: 4\r\n\r\n"),
+            Ok(BodyLength::Fixed(4))
+        );
+        assert_eq!(
+            len(b"POST / HTTP/1.1\r\nContent-Length: 4\r\nContent-Length: 5\r\n\r\n"),
+            Err(Error::InvalidContentLength)
+        );
+        assert_eq!(
+            len(b"POST / HTTP/1.1\r\nContent-Length: -1\r\n\r\n"),
+            Err(Error::InvalidContentLength)
+        );
+        assert_eq!(
+            len(b"POST / HTTP/1.1\r\nContent-Length: 99999999999999999999\r\n\r\n"),
+            Err(Error::InvalidContentLength)
+        );
+        assert_eq!(
+            len(
+                b"POST / HTTP/1.1\r\nTransfer-Encoding: gzip, Chunked\r\nContent-Length: 3\r\n\r\n"
+            ),
+            Ok(BodyLength::Chunked)
+        );
+        assert_eq!(
+            len(b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked, gzip\r\n\r\n"),
+            Err(Error::InvalidTransferEncoding)
+        );
+    }
+
+    #[test]
+    fn fixed_body() {
+        for &size in &[1, 3, 64] {
+            assert_eq!(
+                body(BodyLength::Fixed(5), b"helloEXTRA", size),
+                Ok(b"hello".to_vec())
+            );
+            assert_eq!(body(BodyLength::Fixed(5), b"hel", size), Err(None));
+            assert_eq!(
+                body(BodyLength::UntilClose, b"hello", size),
+                Ok(b"hello".to_vec())
+            );
+        }
+
+        assert_eq!(body(BodyLength::Fixed(0), b"", 1), Ok(Vec::new()));
+        assert_eq!(body(BodyLength::UntilClose, b"", 1), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn chunked_body() {
+        let data = b"4\r\nWiki\r\n5;name=value\r\npedia\r\nE\r\n in\r\n\r\nchunks.\r\n0\r\nTrailer: x\r\n\r\nnext";
+
+        for &size in &[24, 64, 128] {
+            assert_eq!(
+                body(BodyLength::Chunked, data, size),
+                Ok(b"Wikipedia in\r\n\r\nchunks.".to_vec())
+            );
+        }
+
+        assert_eq!(body(BodyLength::Chunked, b"0\n\n", 8), Ok(Vec::new()));
+        assert_eq!(body(BodyLength::Chunked, b"4\r\nWi", 8), Err(None));
+        assert!(body(BodyLength::Chunked, b"4\r\nWikiX\r\n0\r\n\r\n", 64).is_err());
+        assert_eq!(
+            body(BodyLength::Chunked, b"fffffffffffffffff\r\n", 64),
+            Err(Some(Error::InvalidChunkSize))
+        );
+    }
+
+    #[test]
+    fn chunk_size_limit() {
+        let config = Config::new().with_max_header_size(8);
+        let decode = |data: &[u8]| {
+            let mut s = SliceStream::new(data);
+
+            BodyDecoder::with_config(BodyLength::Chunked, config)
+                .next_chunk(&mut s)
+                .map(|d| d.map(|d| d.to_vec()))
+                .map_err(|e| match e {
+                    StreamError::ParseError(_, e) => Some(e),
+                    _ => None,
+                })
+        };
+
+        assert_eq!(
+            decode(b"5;abcdef\r\nhello\r\n"),
+            Ok(Some(b"hello".to_vec()))
+        );
+        assert_eq!(
+            decode(b"5;abcdefg\r\nhello\r\n"),
+            Err(Some(Error::HeaderTooLarge))
+        );
+        assert_eq!(
+            decode(b"000000005\r\nhello\r\n"),
+            Err(Some(Error::HeaderTooLarge))
+        );
+
+        // A peer which never ends the chunk extension cannot grow the buffer without limit
+        let endless = io::Cursor::new(&b"5;"[..]).chain(io::repeat(b'a'));
+        let mut s = Source::from_read(endless, GrowingBuffer::new());
+        let mut d = BodyDecoder::with_config(BodyLength::Chunked, config);
+
+        loop {
+            match d.next_chunk(&mut s) {
+                Err(StreamError::Retry(_)) => assert!(s.len() < 1024),
+                Err(StreamError::ParseError(_, e)) => {
+                    assert_eq!(e, Error::HeaderTooLarge);
+                    break;
+                }
+                r => panic!("{:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn stream_message() {
+        let data = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n";
+        let mut s = SliceStream::new(data);
+
+        let length = s.parse(response).unwrap().body_length().unwrap();
+        let mut d = BodyDecoder::new(length);
+
+        assert_eq!(d.next_chunk(&mut s), Ok(Some(&b"abc"[..])));
+        assert!(!d.is_done());
+        assert_eq!(d.next_chunk(&mut s), Ok(None));
+        assert!(d.is_done());
+        assert!(s.is_empty());
+    }
+}
@@ -0,0 +1,1082 @@
+//! JSON parser following [RFC 8259](https://tools.ietf.org/html/rfc8259).
+//!
+//! Two interfaces are provided:
+//!
+//! * `value` and `document` parse a complete JSON text into a `Value`. Strings
+//!   without escape sequences and all numbers are borrowed directly from the
+//!   input buffer.
+//! * `EventParser` yields one `Event` at a time and keeps its nesting state
+//!   between calls, which makes it possible to parse arbitrarily large
+//!   documents incrementally from a `buffer::Source`.
+//!
+//! Both interfaces limit the nesting depth of arrays and objects, configured
+//! through `Config`, so that untrusted input cannot exhaust the stack.
+//!
+//! ```
+//! use chomp1::formats::json::{document, Value};
+//! use chomp1::parse_only;
+//!
+//! let v = parse_only(document, br#"{"name": "chomp", "tags": [1, true, null]}"#).unwrap();
+//!
+//! assert_eq!(v.get("name").and_then(Value::as_str), Some("chomp"));
+//! assert_eq!(v.get("tags").and_then(Value::as_array).map(Vec::len), Some(3));
+//! ```
+
+#[cfg(feature = "serde")]
+pub mod de;
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+use std::str;
+
+use crate::ascii::is_digit;
+use crate::buffer::{Stream, StreamError};
+use crate::combinators::{matched_by, option, or};
+use crate::parsers;
+use crate::parsers::{
+    eof, peek_next, satisfy, skip_while, skip_while1, string as literal, token, SimpleResult,
+};
+use crate::primitives::{IntoInner, Primitives};
+use crate::types::{Input, ParseResult, U8Input};
+
+/// Default maximum nesting depth of arrays and objects.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Errors produced by the JSON parsers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The input did not match the JSON grammar.
+    Syntax(parsers::Error<u8>),
+    /// A string contained an unescaped control character or invalid UTF-8.
+    InvalidString,
+    /// A string contained an unknown escape sequence or an unpaired surrogate.
+    InvalidEscape,
+    /// Arrays and objects were nested deeper than `Config::max_depth`.
+    DepthLimitExceeded,
+}
+
+impl From<parsers::Error<u8>> for Error {
+    #[inline]
+    fn from(e: parsers::Error<u8>) -> Self {
+        Error::Syntax(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Error::Syntax(ref e) => write!(f, "syntax error: {}", e),
+            Error::InvalidString => write!(f, "invalid character in string"),
+            Error::InvalidEscape => write!(f, "invalid escape sequence in string"),
+            Error::DepthLimitExceeded => write!(f, "nesting depth limit exceeded"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Parser configuration.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Config {
+    max_depth: usize,
+}
+
+impl Config {
+    /// Creates a configuration with a maximum nesting depth of
+    /// `DEFAULT_MAX_DEPTH`.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_max_depth(DEFAULT_MAX_DEPTH)
+    }
+
+    /// Creates a configuration allowing at most `max_depth` nested arrays and
+    /// objects. A depth of `0` only allows scalar values.
+    #[inline]
+    pub fn with_max_depth(max_depth: usize) -> Self {
+        Config { max_depth }
+    }
+
+    /// The maximum nesting depth of arrays and objects.
+    #[inline]
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config::new()
+    }
+}
+
+/// A JSON number, kept as the matched text to avoid loss of precision.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Number<'a>(&'a str);
+
+impl<'a> Number<'a> {
+    /// The number exactly as written in the input.
+    #[inline]
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+
+    /// Converts the number to the nearest `f64`, numbers outside of the range
+    /// of `f64` become infinite.
+    #[inline]
+    pub fn as_f64(&self) -> f64 {
+        // The JSON number grammar is a subset of what `f64::from_str` accepts
+        self.0
+            .parse()
+            .expect("json: number grammar is a subset of f64 syntax")
+    }
+
+    /// Converts the number to an `i64` if it is an integer within range.
+    #[inline]
+    pub fn as_i64(&self) -> Option<i64> {
+        self.0.parse().ok()
+    }
+
+    /// Converts the number to a `u64` if it is a non-negative integer within
+    /// range.
+    #[inline]
+    pub fn as_u64(&self) -> Option<u64> {
+        self.0.parse().ok()
+    }
+}
+
+/// A parsed JSON value borrowing from the input.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value<'a> {
+    /// `null`
+    Null,
+    /// `true` or `false`
+    Bool(bool),
+    /// A number
+    Number(Number<'a>),
+    /// A string, borrowed unless it contained escape sequences
+    String(Cow<'a, str>),
+    /// An array
+    Array(Vec<Value<'a>>),
+    /// An object, members are kept in input order including duplicates
+    Object(Vec<(Cow<'a, str>, Value<'a>)>),
+}
+
+impl<'a> Value<'a> {
+    /// Looks up a member of an object, `None` if this is not an object or the
+    /// key is not present. If the key is duplicated the last member wins.
+    pub fn get(&self, key: &str) -> Option<&Value<'a>> {
+        match *self {
+            Value::Object(ref m) => m.iter().rev().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if this is `null`.
+    #[inline]
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+
+    /// Returns the boolean if this is `true` or `false`.
+    #[inline]
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if this is a number.
+    #[inline]
+    pub fn as_number(&self) -> Option<Number<'a>> {
+        match *self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Returns the string if this is a string.
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements if this is an array.
+    #[inline]
+    pub fn as_array(&self) -> Option<&Vec<Value<'a>>> {
+        match *self {
+            Value::Array(ref a) => Some(a),
+            _ => None,
+        }
+    }
+
+    /// Returns the members if this is an object.
+    #[inline]
+    pub fn as_object(&self) -> Option<&Vec<(Cow<'a, str>, Value<'a>)>> {
+        match *self {
+            Value::Object(ref m) => Some(m),
+            _ => None,
+        }
+    }
+}
+
+/// A single event produced by `EventParser`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event<'a> {
+    /// `{`
+    StartObject,
+    /// `}`
+    EndObject,
+    /// `[`
+    StartArray,
+    /// `]`
+    EndArray,
+    /// An object key including the following `:`
+    Key(Cow<'a, str>),
+    /// A string value
+    String(Cow<'a, str>),
+    /// A number value
+    Number(Number<'a>),
+    /// `true` or `false`
+    Bool(bool),
+    /// `null`
+    Null,
+}
+
+/// Whitespace as defined by RFC 8259, a subset of `ascii::is_whitespace`.
+#[inline]
+fn is_ws(c: u8) -> bool {
+    c == b' ' || c == b'\t' || c == b'\n' || c == b'\r'
+}
+
+/// String content which can be copied verbatim.
+#[inline]
+fn is_unescaped(c: u8) -> bool {
+    c != b'"' && c != b'\\' && c >= 0x20
+}
+
+#[inline]
+fn ws<I: U8Input>(i: I) -> SimpleResult<I, ()> {
+    skip_while(i, is_ws)
+}
+
+/// Consumes `close` if it is the next token, yielding `true` if it was.
+#[inline]
+fn closing<I: U8Input>(mut i: I, close: u8) -> SimpleResult<I, bool> {
+    match i.peek() {
+        Some(c) if c == close => {
+            i.pop();
+
+            i.ret(true)
+        }
+        Some(_) => i.ret(false),
+        None => i.err(parsers::Error::expected(close)),
+    }
+}
+
+/// Skips whitespace and parses either a `,` or `close`, yielding `true` if the
+/// container was closed.
+#[inline]
+fn separator<I: U8Input>(i: I, close: u8) -> SimpleResult<I, bool> {
+    ws(i)
+        .then(|i| satisfy(i, |c| c == b',' || c == close))
+        .map(|c| c == close)
+}
+
+#[inline]
+fn hex4<I: U8Input>(i: &mut I) -> Result<u32, Error> {
+    let mut n = 0;
+
+    for _ in 0..4 {
+        let d = match i.pop() {
+            Some(c @ b'0'..=b'9') => c - b'0',
+            Some(c @ b'a'..=b'f') => c - b'a' + 10,
+            Some(c @ b'A'..=b'F') => c - b'A' + 10,
+            Some(_) => return Err(Error::InvalidEscape),
+            None => return Err(Error::Syntax(parsers::Error::unexpected())),
+        };
+
+        n = n * 16 + u32::from(d);
+    }
+
+    Ok(n)
+}
+
+/// Decodes the escape sequence following a `\`.
+fn escape<I: U8Input>(i: &mut I) -> Result<char, Error> {
+    let c = match i.pop() {
+        Some(b'"') => '"',
+        Some(b'\\') => '\\',
+        Some(b'/') => '/',
+        Some(b'b') => '\u{8}',
+        Some(b'f') => '\u{c}',
+        Some(b'n') => '\n',
+        Some(b'r') => '\r',
+        Some(b't') => '\t',
+        Some(b'u') => {
+            let n = match hex4(i)? {
+                hi @ 0xD800..=0xDBFF => {
+                    // High surrogate, must be followed by an escaped low surrogate
+                    for &c in b"\\u" {
+                        match i.pop() {
+                            Some(d) if d == c => {}
+                            Some(_) => return Err(Error::InvalidEscape),
+                            None => return Err(Error::Syntax(parsers::Error::unexpected())),
+                        }
+                    }
+
+                    match hex4(i)? {
+                        lo @ 0xDC00..=0xDFFF => 0x10000 + ((hi - 0xD800) << 10) + (lo - 0xDC00),
+                        _ => return Err(Error::InvalidEscape),
+                    }
+                }
+                n => n,
+            };
+
+            // Lone low surrogates are rejected here
+            return std::char::from_u32(n).ok_or(Error::InvalidEscape);
+        }
+        Some(_) => return Err(Error::InvalidEscape),
+        None => return Err(Error::Syntax(parsers::Error::unexpected())),
+    };
+
+    Ok(c)
+}
+
+/// Parses the remainder of a string after the opening quote.
+fn string_body<'a, I>(mut i: I) -> ParseResult<I, Cow<'a, str>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    let mut owned: Option<String> = None;
+
+    loop {
+        let chunk = i.consume_while(is_unescaped);
+
+        let end = match i.peek() {
+            Some(b'"') => true,
+            Some(b'\\') => false,
+            // Unescaped control character
+            Some(_) => return i.err(Error::InvalidString),
+            None => return i.err(Error::Syntax(parsers::Error::expected(b'"'))),
+        };
+
+        i.pop();
+
+        // Escape sequences are always ASCII, so a valid UTF-8 sequence cannot be split by one
+        let s = match str::from_utf8(chunk) {
+            Ok(s) => s,
+            Err(_) => return i.err(Error::InvalidString),
+        };
+
+        if end {
+            return i.ret(match owned {
+                Some(mut o) => {
+                    o.push_str(s);
+
+                    Cow::Owned(o)
+                }
+                None => Cow::Borrowed(s),
+            });
+        }
+
+        let o = owned.get_or_insert_with(String::new);
+
+        o.push_str(s);
+
+        match escape(&mut i) {
+            Ok(c) => o.push(c),
+            Err(e) => return i.err(e),
+        }
+    }
+}
+
+/// Parses a quoted string.
+#[inline]
+fn string<'a, I>(i: I) -> ParseResult<I, Cow<'a, str>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    token(i, b'"').bind(|i, _| string_body(i))
+}
+
+/// Parses `-? (0 | [1-9][0-9]*) (\.[0-9]+)? ([eE][+-]?[0-9]+)?`.
+fn number<'a, I>(i: I) -> ParseResult<I, Number<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    matched_by(i, |i| {
+        option(i, |i| token(i, b'-').map(|_| ()), ())
+            .then(|i| {
+                or(
+                    i,
+                    |i| token(i, b'0').map(|_| ()),
+                    |i| {
+                        satisfy(i, |c| (b'1'..=b'9').contains(&c)).then(|i| skip_while(i, is_digit))
+                    },
+                )
+            })
+            .then(|i| option(i, |i| token(i, b'.').then(|i| skip_while1(i, is_digit)), ()))
+            .then(|i| {
+                option(
+                    i,
+                    |i| {
+                        satisfy(i, |c| c == b'e' || c == b'E')
+                            .then(|i| option(i, |i| satisfy(i, |c| c == b'+' || c == b'-'), b'+'))
+                            .then(|i| skip_while1(i, is_digit))
+                    },
+                    (),
+                )
+            })
+    })
+    // The matched buffer only contains [-+.0-9eE] and is therefore valid UTF-8
+    .map(|(b, _)| Number(unsafe { str::from_utf8_unchecked(b) }))
+    .map_err(Error::from)
+}
+
+/// Skips whitespace and parses the start of a value. Scalars are parsed in
+/// full, for arrays and objects only the opening bracket is consumed.
+///
+/// `depth` is the number of arrays and objects enclosing the value.
+fn value_start<'a, I>(i: I, depth: usize, max_depth: usize) -> ParseResult<I, Event<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    ws(i).then(peek_next).bind(|i, c| match c {
+        b'{' | b'[' if depth >= max_depth => i.err(Error::DepthLimitExceeded),
+        b'{' => token(i, b'{')
+            .map(|_| Event::StartObject)
+            .map_err(Error::from),
+        b'[' => token(i, b'[')
+            .map(|_| Event::StartArray)
+            .map_err(Error::from),
+        b'"' => string(i).map(Event::String),
+        b'-' | b'0'..=b'9' => number(i).map(Event::Number),
+        b't' => literal(i, b"true")
+            .map(|_| Event::Bool(true))
+            .map_err(Error::from),
+        b'f' => literal(i, b"false")
+            .map(|_| Event::Bool(false))
+            .map_err(Error::from),
+        b'n' => literal(i, b"null")
+            .map(|_| Event::Null)
+            .map_err(Error::from),
+        _ => i.err(Error::Syntax(parsers::Error::unexpected())),
+    })
+}
+
+/// Skips whitespace and parses an object key and the following `:`.
+#[inline]
+fn key<'a, I>(i: I) -> ParseResult<I, Cow<'a, str>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    ws(i).then(string).bind(|i, k| {
+        ws(i)
+            .then(|i| token(i, b':'))
+            .map(|_| k)
+            .map_err(Error::from)
+    })
+}
+
+fn value_depth<'a, I>(i: I, depth: usize, max_depth: usize) -> ParseResult<I, Value<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    value_start(i, depth, max_depth).bind(|i, e| match e {
+        Event::StartArray => array_rest(i, depth + 1, max_depth).map(Value::Array),
+        Event::StartObject => object_rest(i, depth + 1, max_depth).map(Value::Object),
+        Event::String(s) => i.ret(Value::String(s)),
+        Event::Number(n) => i.ret(Value::Number(n)),
+        Event::Bool(b) => i.ret(Value::Bool(b)),
+        Event::Null => i.ret(Value::Null),
+        Event::Key(_) | Event::EndArray | Event::EndObject => unreachable!(),
+    })
+}
+
+/// Parses the elements of an array following the `[`.
+fn array_rest<'a, I>(i: I, depth: usize, max_depth: usize) -> ParseResult<I, Vec<Value<'a>>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    ws(i).then(|i| closing(i, b']')).bind(|mut i, closed| {
+        let mut items = Vec::new();
+
+        if closed {
+            return i.ret(items);
+        }
+
+        loop {
+            let r = value_depth(i, depth, max_depth)
+                .bind(|i, v| separator(i, b']').map(|c| (v, c)).map_err(Error::from));
+
+            match r.into_inner() {
+                (b, Ok((v, closed))) => {
+                    items.push(v);
+
+                    if closed {
+                        return b.ret(items);
+                    }
+
+                    i = b;
+                }
+                (b, Err(e)) => return b.err(e),
+            }
+        }
+    })
+}
+
+/// Parses the members of an object following the `{`.
+#[allow(clippy::type_complexity)]
+fn object_rest<'a, I>(
+    i: I,
+    depth: usize,
+    max_depth: usize,
+) -> ParseResult<I, Vec<(Cow<'a, str>, Value<'a>)>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    ws(i).then(|i| closing(i, b'}')).bind(|mut i, closed| {
+        let mut members = Vec::new();
+
+        if closed {
+            return i.ret(members);
+        }
+
+        loop {
+            let r = key(i).bind(|i, k| {
+                value_depth(i, depth, max_depth)
+                    .bind(|i, v| separator(i, b'}').map(|c| (k, v, c)).map_err(Error::from))
+            });
+
+            match r.into_inner() {
+                (b, Ok((k, v, closed))) => {
+                    members.push((k, v));
+
+                    if closed {
+                        return b.ret(members);
+                    }
+
+                    i = b;
+                }
+                (b, Err(e)) => return b.err(e),
+            }
+        }
+    })
+}
+
+/// Parses a JSON value preceded by optional whitespace, using the default
+/// `Config`.
+///
+/// Trailing input is left unparsed, use `document` to require that the whole
+/// input is a single JSON text.
+///
+/// ```
+/// use chomp1::formats::json::{value, Value};
+/// use chomp1::parse_only;
+///
+/// assert_eq!(parse_only(value, b" [] trailing"), Ok(Value::Array(vec![])));
+/// ```
+#[inline]
+pub fn value<'a, I>(i: I) -> ParseResult<I, Value<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    value_with(i, Config::new())
+}
+
+/// Parses a JSON value preceded by optional whitespace using the supplied
+/// configuration.
+///
+/// ```
+/// use chomp1::formats::json::{value_with, Config, Error};
+/// use chomp1::parse_only;
+///
+/// let r = parse_only(|i| value_with(i, Config::with_max_depth(2)), b"[[[]]]");
+///
+/// assert_eq!(r.map_err(|(_, e)| e), Err(Error::DepthLimitExceeded));
+/// ```
+#[inline]
+pub fn value_with<'a, I>(i: I, config: Config) -> ParseResult<I, Value<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    value_depth(i, 0, config.max_depth)
+}
+
+/// Parses a complete JSON text: a value surrounded by optional whitespace
+/// followed by the end of the input, using the default `Config`.
+///
+/// ```
+/// use chomp1::formats::json::{document, Value};
+/// use chomp1::parse_only;
+///
+/// assert_eq!(parse_only(document, b" true\n"), Ok(Value::Bool(true)));
+/// assert!(parse_only(document, b"true false").is_err());
+/// ```
+#[inline]
+pub fn document<'a, I>(i: I) -> ParseResult<I, Value<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    document_with(i, Config::new())
+}
+
+/// Parses a complete JSON text using the supplied configuration.
+#[inline]
+pub fn document_with<'a, I>(i: I, config: Config) -> ParseResult<I, Value<'a>, Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    value_with(i, config).bind(|i, v| ws(i).then(eof).map(|_| v).map_err(Error::from))
+}
+
+/// Kind of container the event parser is currently inside of.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Container {
+    Array,
+    Object,
+}
+
+/// What the event parser expects next.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Expect {
+    /// A top-level value or the end of the input.
+    Top,
+    /// A value following an object key.
+    Value,
+    /// The first element of an array or `]`.
+    FirstElement,
+    /// `,` followed by an element, or `]`.
+    NextElement,
+    /// The first key of an object or `}`.
+    FirstKey,
+    /// `,` followed by a key, or `}`.
+    NextKey,
+}
+
+/// Pull-based event parser which keeps the nesting state between calls.
+///
+/// Each call to `next_event` parses exactly one event from a `Stream`. Since
+/// the state is only updated once the stream has accepted the parsed event, a
+/// `StreamError::Retry` can simply be followed by another call to
+/// `next_event`. This makes it possible to parse documents which are larger
+/// than the stream buffer.
+///
+/// The parser accepts a sequence of whitespace-separated top-level values and
+/// yields `Ok(None)` once the input is exhausted at a value boundary.
+///
+/// ```
+/// use chomp1::buffer::{Source, StreamError};
+/// use chomp1::formats::json::{Event, EventParser};
+///
+/// let mut src = Source::new(&br#"{"a": [1, "b"]}"#[..]);
+/// let mut p = EventParser::new();
+/// let mut keys = 0;
+/// let mut scalars = 0;
+///
+/// loop {
+///     match p.next_event(&mut src) {
+///         Ok(Some(Event::Key(_))) => keys += 1,
+///         Ok(Some(Event::Number(_))) | Ok(Some(Event::String(_))) => scalars += 1,
+///         Ok(Some(_)) => {}
+///         Ok(None) => break,
+///         Err(StreamError::Retry(_)) => {}
+///         Err(e) => panic!("{:?}", e),
+///     }
+/// }
+///
+/// assert_eq!((keys, scalars), (1, 2));
+/// ```
+#[derive(Clone, Debug)]
+pub struct EventParser {
+    config: Config,
+    stack: Vec<Container>,
+    expect: Expect,
+}
+
+impl EventParser {
+    /// Creates an event parser using the default `Config`.
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_config(Config::new())
+    }
+
+    /// Creates an event parser using the supplied configuration.
+    #[inline]
+    pub fn with_config(config: Config) -> Self {
+        EventParser {
+            config,
+            stack: Vec::new(),
+            expect: Expect::Top,
+        }
+    }
+
+    /// The number of arrays and objects currently open.
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Parses the next event from the stream.
+    ///
+    /// Yields `Ok(None)` when the input ends between two top-level values, if
+    /// it ends inside of a value `StreamError::Incomplete` is returned.
+    pub fn next_event<'a, 'i, S>(
+        &mut self,
+        s: &'a mut S,
+    ) -> Result<Option<Event<'i>>, StreamError<&'i [u8], Error>>
+    where
+        S: Stream<'a, 'i>,
+        S::Input: Input<Token = u8, Buffer = &'i [u8]>,
+    {
+        match s.parse(|i| self.event(i)) {
+            Ok(e) => {
+                if let Some(ref e) = e {
+                    self.advance(e);
+                }
+
+                Ok(e)
+            }
+            Err(StreamError::EndOfInput) if self.expect != Expect::Top => {
+                Err(StreamError::Incomplete(None))
+            }
+            Err(StreamError::EndOfInput) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Parses the next event without updating the parser state.
+    fn event<'a, I>(&self, i: I) -> ParseResult<I, Option<Event<'a>>, Error>
+    where
+        I: Input<Token = u8, Buffer = &'a [u8]>,
+    {
+        let (depth, max_depth) = (self.stack.len(), self.config.max_depth);
+
+        match self.expect {
+            Expect::Top => ws(i).bind(|mut i, _| match i.peek() {
+                None => i.ret(None),
+                Some(_) => value_start(i, depth, max_depth).map(Some),
+            }),
+            Expect::Value => value_start(i, depth, max_depth).map(Some),
+            Expect::FirstElement => ws(i).then(|i| closing(i, b']')).bind(|i, closed| {
+                if closed {
+                    i.ret(Some(Event::EndArray))
+                } else {
+                    value_start(i, depth, max_depth).map(Some)
+                }
+            }),
+            Expect::NextElement => separator(i, b']').bind(|i, closed| {
+                if closed {
+                    i.ret(Some(Event::EndArray))
+                } else {
+                    value_start(i, depth, max_depth).map(Some)
+                }
+            }),
+            Expect::FirstKey => ws(i).then(|i| closing(i, b'}')).bind(|i, closed| {
+                if closed {
+                    i.ret(Some(Event::EndObject))
+                } else {
+                    key(i).map(|k| Some(Event::Key(k)))
+                }
+            }),
+            Expect::NextKey => separator(i, b'}').bind(|i, closed| {
+                if closed {
+                    i.ret(Some(Event::EndObject))
+                } else {
+                    key(i).map(|k| Some(Event::Key(k)))
+                }
+            }),
+        }
+    }
+
+    /// Updates the state after `e` has been accepted.
+    fn advance(&mut self, e: &Event<'_>) {
+        self.expect = match *e {
+            Event::StartObject => {
+                self.stack.push(Container::Object);
+
+                Expect::FirstKey
+            }
+            Event::StartArray => {
+                self.stack.push(Container::Array);
+
+                Expect::FirstElement
+            }
+            Event::Key(_) => Expect::Value,
+            Event::EndObject | Event::EndArray => {
+                self.stack.pop();
+
+                self.after_value()
+            }
+            _ => self.after_value(),
+        }
+    }
+
+    #[inline]
+    fn after_value(&self) -> Expect {
+        match self.stack.last() {
+            Some(Container::Array) => Expect::NextElement,
+            Some(Container::Object) => Expect::NextKey,
+            None => Expect::Top,
+        }
+    }
+}
+
+impl Default for EventParser {
+    fn default() -> Self {
+        EventParser::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::buffer::{FixedSizeBuffer, GrowingBuffer, SliceStream, Source};
+    use crate::parse_only;
+
+    /// Renders the events of `input` in a compact textual form.
+    fn events(input: &[u8], config: Config) -> Result<Vec<String>, Error> {
+        // Feed the source a single byte at a time to exercise the retry logic
+        struct Trickle<'a>(&'a [u8]);
+
+        impl<'a> std::io::Read for Trickle<'a> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.0.is_empty() || buf.is_empty() {
+                    return Ok(0);
+                }
+
+                buf[0] = self.0[0];
+                self.0 = &self.0[1..];
+
+                Ok(1)
+            }
+        }
+
+        let mut src = Source::from_read(Trickle(input), GrowingBuffer::new());
+        let mut p = EventParser::with_config(config);
+        let mut out = Vec::new();
+
+        loop {
+            match p.next_event(&mut src) {
+                Ok(Some(e)) => out.push(match e {
+                    Event::StartObject => "{".to_owned(),
+                    Event::EndObject => "}".to_owned(),
+                    Event::StartArray => "[".to_owned(),
+                    Event::EndArray => "]".to_owned(),
+                    Event::Key(k) => format!("{}:", k),
+                    Event::String(s) => format!("{:?}", s),
+                    Event::Number(n) => n.as_str().to_owned(),
+                    Event::Bool(b) => b.to_string(),
+                    Event::Null => "null".to_owned(),
+                }),
+                Ok(None) => return Ok(out),
+                Err(StreamError::Retry(_)) => {}
+                Err(StreamError::ParseError(_, e)) => return Err(e),
+                Err(e) => panic!("unexpected stream error: {:?}", e),
+            }
+        }
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(parse_only(document, b"null"), Ok(Value::Null));
+        assert_eq!(parse_only(document, b"true"), Ok(Value::Bool(true)));
+        assert_eq!(parse_only(document, b" false "), Ok(Value::Bool(false)));
+        assert_eq!(
+            parse_only(document, b"-12.5e+3"),
+            Ok(Value::Number(Number("-12.5e+3")))
+        );
+        assert_eq!(
+            parse_only(document, b"\"abc\""),
+            Ok(Value::String(Cow::Borrowed("abc")))
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        for n in &[
+            "0",
+            "-0",
+            "1",
+            "-1",
+            "10",
+            "0.5",
+            "1e5",
+            "1E-5",
+            "1.5e+10",
+            "123456789",
+        ] {
+            assert_eq!(
+                parse_only(document, n.as_bytes()),
+                Ok(Value::Number(Number(n))),
+                "{}",
+                n
+            );
+        }
+
+        for n in &[
+            "01", "-", "+1", "1.", ".1", "1e", "1e+", "--1", "0x1", "1.e1", "Infinity",
+        ] {
+            assert!(parse_only(document, n.as_bytes()).is_err(), "{}", n);
+        }
+
+        let n = Number("-42");
+        assert_eq!(n.as_i64(), Some(-42));
+        assert_eq!(n.as_u64(), None);
+        assert_eq!(n.as_f64(), -42.0);
+        assert_eq!(Number("1.5").as_i64(), None);
+        assert!(Number("1e400").as_f64().is_infinite());
+    }
+
+    #[test]
+    fn strings() {
+        let r = parse_only(document, br#""a\"b\\c\/d\b\f\n\r\t""#);
+        assert_eq!(
+            r,
+            Ok(Value::String(Cow::Owned(
+                "a\"b\\c/d\u{8}\u{c}\n\r\t".to_owned()
+            )))
+        );
+
+        let r = parse_only(document, r#""\u00e5\uD834\uDD1E x""#.as_bytes());
+        assert_eq!(r, Ok(Value::String(Cow::Owned("å𝄞 x".to_owned()))));
+
+        let r = parse_only(document, "\"Wernstål\"".as_bytes());
+        assert_eq!(r, Ok(Value::String(Cow::Borrowed("Wernstål"))));
+
+        fn err(s: &[u8]) -> Result<Value<'_>, Error> {
+            parse_only(document, s).map_err(|(_, e)| e)
+        }
+
+        assert_eq!(err(b"\"a\x01\""), Err(Error::InvalidString));
+        assert_eq!(err(b"\"\xff\""), Err(Error::InvalidString));
+        assert_eq!(err(br#""\x""#), Err(Error::InvalidEscape));
+        assert_eq!(err(br#""\u12G4""#), Err(Error::InvalidEscape));
+        assert_eq!(err(br#""\uD800""#), Err(Error::InvalidEscape));
+        assert_eq!(err(br#""\uDC00""#), Err(Error::InvalidEscape));
+        assert_eq!(err(br#""\uD800A""#), Err(Error::InvalidEscape));
+        assert!(err(b"\"abc").is_err());
+    }
+
+    #[test]
+    fn containers() {
+        let v = parse_only(
+            document,
+            br#" { "a" : [ 1 , { } , [ ] ] , "b" : null , "a" : 2 } "#,
+        )
+        .unwrap();
+
+        assert_eq!(v.get("b").map(Value::is_null), Some(true));
+        // Last duplicate wins
+        assert_eq!(v.get("a"), Some(&Value::Number(Number("2"))));
+        assert_eq!(v.as_object().map(Vec::len), Some(3));
+        assert_eq!(
+            v.as_object().unwrap()[0].1,
+            Value::Array(vec![
+                Value::Number(Number("1")),
+                Value::Object(vec![]),
+                Value::Array(vec![]),
+            ])
+        );
+
+        for s in &[
+            "[1,]",
+            "[,1]",
+            "[1 2]",
+            "{\"a\"}",
+            "{\"a\":1,}",
+            "{1:1}",
+            "[",
+            "{",
+            "]",
+        ] {
+            assert!(parse_only(document, s.as_bytes()).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn depth_limit() {
+        let deep = |n: usize| {
+            let mut s = vec![b'['; n];
+            s.extend(vec![b']'; n]);
+            s
+        };
+
+        let limited = |i| document_with(i, Config::with_max_depth(3));
+
+        let (ok, too_deep, very_deep) = (deep(3), deep(4), deep(100_000));
+
+        assert!(parse_only(limited, &ok).is_ok());
+        assert_eq!(
+            parse_only(limited, &too_deep).map_err(|(_, e)| e),
+            Err(Error::DepthLimitExceeded)
+        );
+        assert_eq!(
+            parse_only(limited, b"[1, [2, [3, {\"a\": 4}]]]").map_err(|(_, e)| e),
+            Err(Error::DepthLimitExceeded)
+        );
+        assert_eq!(
+            parse_only(document, &very_deep).map_err(|(_, e)| e),
+            Err(Error::DepthLimitExceeded)
+        );
+        assert_eq!(
+            parse_only(|i| document_with(i, Config::with_max_depth(0)), b"1"),
+            Ok(Value::Number(Number("1")))
+        );
+    }
+
+    #[test]
+    fn event_stream() {
+        let e = events(br#" {"a": [1, "b", true], "c": {}} 2 "x" "#, Config::new()).unwrap();
+
+        assert_eq!(e.join(" "), r#"{ a: [ 1 "b" true ] c: { } } 2 "x""#);
+
+        assert_eq!(events(b"", Config::new()), Ok(Vec::new()));
+        assert_eq!(events(b"  ", Config::new()), Ok(Vec::new()));
+        assert!(events(b"[1 2]", Config::new()).is_err());
+        assert_eq!(
+            events(b"[[[]]]", Config::with_max_depth(2)),
+            Err(Error::DepthLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn event_incomplete() {
+        let mut src = Source::from_read(&b"[1, 2"[..], FixedSizeBuffer::new());
+        let mut p = EventParser::new();
+        let mut n = 0;
+
+        loop {
+            match p.next_event(&mut src) {
+                Ok(Some(_)) => n += 1,
+                Err(StreamError::Retry(_)) => {}
+                Err(StreamError::Incomplete(_)) => break,
+                r => panic!("unexpected result: {:?}", r),
+            }
+        }
+
+        // The final number is complete since the source reached the end of its input
+        assert_eq!(n, 3);
+        assert_eq!(p.depth(), 1);
+    }
+
+    #[test]
+    fn event_slice_stream() {
+        let mut s = SliceStream::new(b"[null] 12");
+        let mut p = EventParser::new();
+
+        assert_eq!(p.next_event(&mut s), Ok(Some(Event::StartArray)));
+        assert_eq!(p.next_event(&mut s), Ok(Some(Event::Null)));
+        assert_eq!(p.depth(), 1);
+        assert_eq!(p.next_event(&mut s), Ok(Some(Event::EndArray)));
+        assert_eq!(p.next_event(&mut s), Ok(Some(Event::Number(Number("12")))));
+        assert_eq!(p.next_event(&mut s), Ok(None));
+    }
+}
@@ -0,0 +1,820 @@
+//! Serde `Deserializer` for JSON, enabled by the `serde` feature.
+//!
+//! `from_slice` deserializes any `Deserialize` type from a complete JSON text,
+//! borrowing strings from the input where possible. `from_stream` does the
+//! same for a single value read from a `buffer::Stream`.
+//!
+//! Errors carry the `Position` in the input where parsing or deserialization
+//! failed.
+//!
+//! ```
+//! use chomp1::formats::json::de::from_slice;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, Debug, PartialEq)]
+//! struct Config<'a> {
+//!     name: &'a str,
+//!     port: u16,
+//!     tags: Vec<String>,
+//! }
+//!
+//! let c: Config = from_slice(br#"{"name": "chomp", "port": 8080, "tags": ["a"]}"#).unwrap();
+//!
+//! assert_eq!(c, Config { name: "chomp", port: 8080, tags: vec!["a".to_owned()] });
+//!
+//! let e = from_slice::<Config>(b"{\n  \"name\": 1\n}").unwrap_err();
+//!
+//! assert_eq!((e.position().line.0, e.position().column), (1, 10));
+//! ```
+
+use std::borrow::Cow;
+use std::error;
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+use super::{closing, key, separator, string, value_start, ws, Config, Event, EventParser};
+use crate::buffer::{Stream, StreamError};
+use crate::combinators::matched_by;
+use crate::parsers::{self, eof};
+use crate::primitives::{IntoInner, Primitives};
+use crate::run_parser;
+use crate::types::numbering::{LineNumber, Numbering};
+use crate::types::{Input, ParseResult};
+
+/// Location in the input.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Position {
+    /// Byte offset from the start of the input
+    pub offset: usize,
+    /// Zero-indexed line
+    pub line: LineNumber,
+    /// Zero-indexed byte offset from the start of the line
+    pub column: usize,
+}
+
+impl Position {
+    fn new(input: &[u8], offset: usize) -> Self {
+        let mut line = LineNumber::new();
+
+        line.update(&&input[..offset]);
+
+        let column = match input[..offset].iter().rposition(|&c| c == b'\n') {
+            Some(n) => offset - n - 1,
+            None => offset,
+        };
+
+        Position {
+            offset,
+            line,
+            column,
+        }
+    }
+}
+
+/// Cause of a deserialization error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ErrorKind {
+    /// The input is not valid JSON.
+    Json(super::Error),
+    /// The JSON value does not match the type being deserialized, or another
+    /// error reported by a `Deserialize` implementation.
+    Custom(String),
+}
+
+/// Deserialization error with the position where it occurred.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Error {
+    kind: ErrorKind,
+    position: Position,
+}
+
+impl Error {
+    /// The cause of the error.
+    #[inline]
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    /// The position in the input where the error occurred.
+    #[inline]
+    pub fn position(&self) -> Position {
+        self.position
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ErrorKind::Json(ref e) => write!(f, "{}", e)?,
+            ErrorKind::Custom(ref m) => write!(f, "{}", m)?,
+        }
+
+        write!(
+            f,
+            " at line {} column {}",
+            self.position.line.0 + 1,
+            self.position.column + 1
+        )
+    }
+}
+
+impl error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        // Positions are filled in by the `Deserializer` which observes the error
+        Error {
+            kind: ErrorKind::Custom(msg.to_string()),
+            position: Position {
+                offset: usize::MAX,
+                line: LineNumber::new(),
+                column: 0,
+            },
+        }
+    }
+}
+
+/// JSON deserializer reading from a byte slice.
+#[derive(Debug)]
+pub struct Deserializer<'de> {
+    input: &'de [u8],
+    rest: &'de [u8],
+    depth: usize,
+    config: Config,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Creates a deserializer using the default `Config`.
+    #[inline]
+    pub fn from_slice(input: &'de [u8]) -> Self {
+        Self::with_config(input, Config::new())
+    }
+
+    /// Creates a deserializer using the supplied configuration.
+    #[inline]
+    pub fn with_config(input: &'de [u8], config: Config) -> Self {
+        Deserializer {
+            input,
+            rest: input,
+            depth: 0,
+            config,
+        }
+    }
+
+    /// Checks that only whitespace remains in the input.
+    pub fn end(&mut self) -> Result<(), Error> {
+        self.run(|i| ws(i).then(eof).map_err(super::Error::from))
+    }
+
+    fn error(&self, kind: ErrorKind) -> Error {
+        Error {
+            kind,
+            position: Position::new(self.input, self.input.len() - self.rest.len()),
+        }
+    }
+
+    /// Fills in the position of errors created through `de::Error::custom`.
+    fn locate(&self, e: Error) -> Error {
+        if e.position.offset == usize::MAX {
+            self.error(e.kind)
+        } else {
+            e
+        }
+    }
+
+    /// Runs a parser on the remaining input, on error the position is where
+    /// the parser stopped.
+    fn run<T, F>(&mut self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&'de [u8]) -> ParseResult<&'de [u8], T, super::Error>,
+    {
+        let (rest, r) = run_parser(self.rest, f);
+
+        self.rest = rest;
+
+        r.map_err(|e| self.error(ErrorKind::Json(e)))
+    }
+
+    /// Skips whitespace and returns the next byte without consuming it.
+    fn peek(&mut self) -> Result<Option<u8>, Error> {
+        self.run(|i| ws(i).map_err(super::Error::from))?;
+
+        Ok(self.rest.first().cloned())
+    }
+
+    fn unexpected<T>(&self) -> Result<T, Error> {
+        Err(self.error(ErrorKind::Json(super::Error::Syntax(
+            parsers::Error::unexpected(),
+        ))))
+    }
+
+    /// Consumes `c` after optional whitespace.
+    fn expect(&mut self, c: u8) -> Result<(), Error> {
+        self.run(|i| {
+            ws(i)
+                .then(|i| parsers::token(i, c))
+                .map(|_| ())
+                .map_err(super::Error::from)
+        })
+    }
+}
+
+/// Deserializes an instance of `T` from a complete JSON text.
+pub fn from_slice<'de, T>(input: &'de [u8]) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    let mut de = Deserializer::from_slice(input);
+    let t = T::deserialize(&mut de).map_err(|e| de.locate(e))?;
+
+    de.end()?;
+
+    Ok(t)
+}
+
+/// Checks the syntax of a single value using the `EventParser` state machine,
+/// without building a `Value`.
+fn skip_value<'a, I>(mut i: I) -> ParseResult<I, (), super::Error>
+where
+    I: Input<Token = u8, Buffer = &'a [u8]>,
+{
+    let mut p = EventParser::new();
+
+    loop {
+        match p.event(i).into_inner() {
+            (b, Ok(Some(e))) => {
+                p.advance(&e);
+
+                if p.depth() == 0 {
+                    return b.ret(());
+                }
+
+                i = b;
+            }
+            (b, Ok(None)) => return b.err(super::Error::Syntax(parsers::Error::unexpected())),
+            (b, Err(e)) => return b.err(e),
+        }
+    }
+}
+
+/// Deserializes an instance of `T` from the next JSON value in a stream.
+///
+/// The value is located in the stream first, so `StreamError::Retry` is
+/// returned until the whole value is available and `T` is only deserialized
+/// once. Positions in errors are relative to the start of the value, including
+/// any whitespace before it.
+///
+/// ```
+/// use chomp1::buffer::{Source, StreamError};
+/// use chomp1::formats::json::de::from_stream;
+///
+/// let mut src = Source::new(&b"[1, 2] [3]"[..]);
+/// let mut sum = 0;
+///
+/// loop {
+///     match from_stream::<_, Vec<u32>>(&mut src) {
+///         Ok(v) => sum += v.iter().sum::<u32>(),
+///         Err(StreamError::Retry(_)) => {}
+///         Err(StreamError::EndOfInput) => break,
+///         Err(e) => panic!("{:?}", e),
+///     }
+/// }
+///
+/// assert_eq!(sum, 6);
+/// ```
+pub fn from_stream<'a, 'i, S, T>(s: &'a mut S) -> Result<T, StreamError<&'i [u8], Error>>
+where
+    S: Stream<'a, 'i>,
+    S::Input: Input<Token = u8, Buffer = &'i [u8]>,
+    T: de::Deserialize<'i>,
+{
+    // Trailing whitespace is consumed to reach the end of input after the last value
+    let raw = s.parse(|i| {
+        let m = i.mark();
+
+        match matched_by(i, skip_value).into_inner() {
+            (i, Ok((raw, _))) => ws(i).map(|_| raw).map_err(|e| (raw, e.into())),
+            // Keep the data from the start of the value to the error, to locate it
+            (mut i, Err(e)) => {
+                let b = i.consume_from(m);
+
+                i.err((b, e))
+            }
+        }
+    });
+
+    let raw = match raw {
+        Ok(raw) => raw,
+        Err(StreamError::ParseError(b, (parsed, e))) => {
+            return Err(StreamError::ParseError(
+                b,
+                Error {
+                    kind: ErrorKind::Json(e),
+                    position: Position::new(parsed, parsed.len()),
+                },
+            ))
+        }
+        Err(StreamError::Incomplete(n)) => return Err(StreamError::Incomplete(n)),
+        Err(StreamError::IoError(e)) => return Err(StreamError::IoError(e)),
+        Err(StreamError::EndOfInput) => return Err(StreamError::EndOfInput),
+        Err(StreamError::Retry(n)) => return Err(StreamError::Retry(n)),
+    };
+
+    from_slice(raw).map_err(|e| StreamError::ParseError(&raw[e.position.offset..], e))
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (depth, max_depth) = (self.depth, self.config.max_depth());
+
+        self.peek()?;
+
+        let start = self.rest;
+        let event = self.run(|i| value_start(i, depth, max_depth))?;
+        let scalar = !matches!(event, Event::StartArray | Event::StartObject);
+
+        let r = match event {
+            Event::Null => visitor.visit_unit(),
+            Event::Bool(b) => visitor.visit_bool(b),
+            Event::Number(n) => {
+                if let Some(u) = n.as_u64() {
+                    visitor.visit_u64(u)
+                } else if let Some(i) = n.as_i64() {
+                    visitor.visit_i64(i)
+                } else {
+                    visitor.visit_f64(n.as_f64())
+                }
+            }
+            Event::String(Cow::Borrowed(s)) => visitor.visit_borrowed_str(s),
+            Event::String(Cow::Owned(s)) => visitor.visit_string(s),
+            Event::StartArray => {
+                self.depth += 1;
+
+                let v = visitor.visit_seq(Seq {
+                    de: &mut *self,
+                    first: true,
+                });
+
+                self.depth -= 1;
+
+                v.and_then(|v| self.expect(b']').map(|_| v))
+            }
+            Event::StartObject => {
+                self.depth += 1;
+
+                let v = visitor.visit_map(Map {
+                    de: &mut *self,
+                    first: true,
+                });
+
+                self.depth -= 1;
+
+                v.and_then(|v| self.expect(b'}').map(|_| v))
+            }
+            Event::Key(_) | Event::EndArray | Event::EndObject => unreachable!(),
+        };
+
+        r.map_err(|e| {
+            if scalar {
+                // Rejected scalars are reported at their start rather than after them
+                self.rest = start;
+            }
+
+            self.locate(e)
+        })
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.peek()? {
+            Some(b'"') => match self.run(string)? {
+                Cow::Borrowed(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
+                Cow::Owned(s) => visitor.visit_byte_buf(s.into_bytes()),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.peek()? {
+            Some(b'n') => {
+                self.run(|i| {
+                    parsers::string(i, b"null")
+                        .map(|_| ())
+                        .map_err(super::Error::from)
+                })?;
+
+                visitor.visit_none()
+            }
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.peek()? {
+            // Unit variant
+            Some(b'"') => {
+                let s = self.run(string)?;
+
+                visitor
+                    .visit_enum(de::value::CowStrDeserializer::new(s))
+                    .map_err(|e| self.locate(e))
+            }
+            // Single-member object mapping the variant to its contents
+            Some(b'{') => {
+                let (depth, max_depth) = (self.depth, self.config.max_depth());
+
+                self.run(|i| value_start(i, depth, max_depth))?;
+                self.depth += 1;
+
+                let v = visitor.visit_enum(Enum { de: &mut *self });
+
+                self.depth -= 1;
+
+                v.and_then(|v| self.expect(b'}').map(|_| v))
+                    .map_err(|e| self.locate(e))
+            }
+            _ => self.unexpected(),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// Elements of an array.
+struct Seq<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    first: bool,
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for Seq<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let first = self.first;
+
+        self.first = false;
+
+        // The closing bracket is left for `deserialize_any` to consume
+        let closed = self.de.run(|i| {
+            let m = i.mark();
+
+            if first {
+                ws(i).then(|i| closing(i, b']'))
+            } else {
+                separator(i, b']')
+            }
+            .map_err(super::Error::from)
+            .bind(|i, closed| {
+                if closed {
+                    i.restore(m).ret(true)
+                } else {
+                    i.ret(false)
+                }
+            })
+        })?;
+
+        if closed {
+            Ok(None)
+        } else {
+            seed.deserialize(&mut *self.de).map(Some)
+        }
+    }
+}
+
+/// Members of an object.
+struct Map<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    first: bool,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for Map<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let first = self.first;
+
+        self.first = false;
+
+        let closed = self.de.run(|i| {
+            let m = i.mark();
+
+            if first {
+                ws(i).then(|i| closing(i, b'}'))
+            } else {
+                separator(i, b'}')
+            }
+            .map_err(super::Error::from)
+            .bind(|i, closed| {
+                if closed {
+                    i.restore(m).ret(true)
+                } else {
+                    i.ret(false)
+                }
+            })
+        })?;
+
+        if closed {
+            return Ok(None);
+        }
+
+        let k = self.de.run(key)?;
+
+        seed.deserialize(de::value::CowStrDeserializer::new(k))
+            .map(Some)
+            .map_err(|e| self.de.locate(e))
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+/// Variant of an enum represented as `{"variant": contents}`.
+struct Enum<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for Enum<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let k = self.de.run(key)?;
+        let v = seed
+            .deserialize(k.into_deserializer())
+            .map_err(|e: Error| self.de.locate(e))?;
+
+        Ok((v, self))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for Enum<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(&mut *self.de)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(&mut *self.de, visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(&mut *self.de, visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::buffer::{FixedSizeBuffer, Source};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Line(Point, Point),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Doc<'a> {
+        #[serde(borrow)]
+        name: Cow<'a, str>,
+        id: Option<u64>,
+        shapes: Vec<Shape>,
+        meta: BTreeMap<String, bool>,
+        #[serde(default)]
+        missing: Vec<u8>,
+    }
+
+    fn kind<T>(r: Result<T, Error>) -> ErrorKind {
+        match r {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e.kind,
+        }
+    }
+
+    #[test]
+    fn structs() {
+        let d: Doc<'_> = from_slice(
+            br#" {
+                "name": "shapes",
+                "id": null,
+                "shapes": ["Empty", {"Circle": 1.5}, {"Line": [{"x": 1, "y": -2}, {"y": 4, "x": 3}]},
+                           {"Rect": {"w": 2, "h": 3}}],
+                "meta": {"a": true, "b": false},
+                "ignored": [{"deep": [1, 2, {}]}]
+            } "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            d,
+            Doc {
+                name: Cow::Borrowed("shapes"),
+                id: None,
+                shapes: vec![
+                    Shape::Empty,
+                    Shape::Circle(1.5),
+                    Shape::Line(Point { x: 1, y: -2 }, Point { x: 3, y: 4 }),
+                    Shape::Rect { w: 2, h: 3 },
+                ],
+                meta: vec![("a".to_owned(), true), ("b".to_owned(), false)]
+                    .into_iter()
+                    .collect(),
+                missing: Vec::new(),
+            }
+        );
+        assert!(matches!(d.name, Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn primitives() {
+        assert_eq!(from_slice::<u8>(b"255"), Ok(255));
+        assert_eq!(from_slice::<i64>(b"-9223372036854775808"), Ok(i64::MIN));
+        assert_eq!(from_slice::<f32>(b"1e2"), Ok(100.0));
+        assert_eq!(from_slice::<Option<bool>>(b"true"), Ok(Some(true)));
+        assert_eq!(
+            from_slice::<(u8, String)>(br#"[1, "a\nb"]"#),
+            Ok((1, "a\nb".to_owned()))
+        );
+        assert_eq!(from_slice::<&[u8]>(br#""bytes""#), Ok(&b"bytes"[..]));
+        assert_eq!(from_slice::<char>(br#""x""#), Ok('x'));
+        assert_eq!(from_slice::<()>(b"null"), Ok(()));
+
+        assert!(matches!(
+            kind(from_slice::<u8>(b"256")),
+            ErrorKind::Custom(_)
+        ));
+        assert!(matches!(
+            kind(from_slice::<(u8, u8)>(b"[1, 2, 3]")),
+            ErrorKind::Json(_)
+        ));
+        assert!(matches!(kind(from_slice::<u8>(b"1 2")), ErrorKind::Json(_)));
+        assert!(matches!(
+            kind(from_slice::<&str>(br#""a\nb""#)),
+            ErrorKind::Custom(_)
+        ));
+    }
+
+    #[test]
+    fn positions() {
+        let e = from_slice::<Point>(b"{\"x\": 1,\n \"y\": true}").unwrap_err();
+
+        assert!(matches!(e.kind, ErrorKind::Custom(_)));
+        assert_eq!(e.position.line, LineNumber(1));
+        assert_eq!(e.position.column, 6);
+        assert_eq!(e.position.offset, 15);
+
+        let e = from_slice::<Point>(b"{\"x\": 1,\n\n \"y\": 2,}").unwrap_err();
+
+        assert!(matches!(e.kind, ErrorKind::Json(_)));
+        assert_eq!(e.position.line, LineNumber(2));
+        assert_eq!(e.position.column, 8);
+
+        let e = from_slice::<Point>(b"{\"x\": 1}").unwrap_err();
+
+        assert_eq!(e.to_string(), "missing field `y` at line 1 column 8");
+
+        let e = from_slice::<Vec<Vec<u8>>>(b"[[[]]]").unwrap_err();
+
+        assert!(matches!(e.kind, ErrorKind::Custom(_)));
+        assert_eq!(
+            kind(from_slice::<serde::de::IgnoredAny>(&vec![b'['; 1000])),
+            ErrorKind::Json(super::super::Error::DepthLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn stream() {
+        let mut src = Source::from_read(
+            &b"{\"x\": 1, \"y\": 2}\n{\"x\": 3, \"y\": 4}\n"[..],
+            FixedSizeBuffer::with_size(20),
+        );
+        let mut points = Vec::new();
+
+        loop {
+            match from_stream::<_, Point>(&mut src) {
+                Ok(p) => points.push(p),
+                Err(StreamError::Retry(_)) => {}
+                Err(StreamError::EndOfInput) => break,
+                Err(e) => panic!("{:?}", e),
+            }
+        }
+
+        assert_eq!(points, vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]);
+
+        let mut src = Source::new(&b"{\"x\": 1, \"y\": \"2\"}"[..]);
+
+        loop {
+            match from_stream::<_, Point>(&mut src) {
+                Err(StreamError::Retry(_)) => {}
+                Err(StreamError::ParseError(rest, e)) => {
+                    assert_eq!(rest, b"\"2\"}");
+                    assert_eq!(e.position.offset, 14);
+                    break;
+                }
+                r => panic!("{:?}", r),
+            }
+        }
+
+        // Syntax errors are located relative to the start of the value
+        let mut src = Source::new(&b"{\"x\": 0, \"y\": 0}\n{\"x\": 1,\n \"y\": 2x}"[..]);
+
+        loop {
+            match from_stream::<_, Point>(&mut src) {
+                Ok(p) => assert_eq!(p, Point { x: 0, y: 0 }),
+                Err(StreamError::Retry(_)) => {}
+                Err(StreamError::ParseError(rest, e)) => {
+                    assert_eq!(rest, b"x}");
+                    assert_eq!(e.position.offset, 16);
+                    assert_eq!((e.position.line.0, e.position.column), (1, 7));
+                    break;
+                }
+                r => panic!("{:?}", r),
+            }
+        }
+    }
+}
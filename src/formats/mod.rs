@@ -0,0 +1,11 @@
+//! Parsers for common data formats built on top of the Chomp primitives.
+//!
+//! Every format lives behind its own cargo feature so that users only pay for
+//! the formats they actually use.
+
+#[cfg(feature = "csv")]
+pub mod csv;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "json")]
+pub mod json;
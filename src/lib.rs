@@ -203,12 +203,43 @@
 //! does not incur any    cost when built using the `release` profile unless the
 //! `backtrace` feature is enabled.
 //!
+//! * `bytes`:
+#![cfg_attr(not(feature = "bytes"), doc = " disabled (default).")]
+#![cfg_attr(feature = "bytes", doc = " enabled.")]
+//!    Implements `Input` and `Buffer` for `bytes::Bytes` and enables
+//! `buffer::BytesMutStream`, which parses frames out of a `BytesMut`
+//! accumulator. Implies `std`.
+//!
+//! * `csv`:
+#![cfg_attr(not(feature = "csv"), doc = " disabled (default).")]
+#![cfg_attr(feature = "csv", doc = " enabled.")]
+//!    Enables the `formats::csv` module, an RFC 4180 CSV/TSV parser with a
+//! streaming record `Reader`. Implies `std`.
+//!
+//! * `http`:
+#![cfg_attr(not(feature = "http"), doc = " disabled (default).")]
+#![cfg_attr(feature = "http", doc = " enabled.")]
+//!    Enables the `formats::http` module, a zero-copy HTTP/1.1 message head
+//! parser with an incremental body decoder. Implies `std`.
+//!
+//! * `json`:
+#![cfg_attr(not(feature = "json"), doc = " disabled (default).")]
+#![cfg_attr(feature = "json", doc = " enabled.")]
+//!    Enables the `formats::json` module, an RFC 8259 JSON parser with both a
+//! borrowing `Value` DOM and an incremental event parser. Implies `std`.
+//!
 //! * `noop_error`:
 #![cfg_attr(not(feature = "noop_error"), doc = " disabled (default).")]
 #![cfg_attr(feature = "noop_error", doc = " enabled.")]
 //!    The built-in `chomp1::parsers::Error` type is zero-sized and carry no
 //! error-information. This    increases performance somewhat.
 //!
+//! * `serde`:
+#![cfg_attr(not(feature = "serde"), doc = " disabled (default).")]
+#![cfg_attr(feature = "serde", doc = " enabled.")]
+//!    Enables `formats::json::de`, a `serde::Deserializer` for JSON reading
+//! from byte slices or a `buffer::Stream`. Implies `json`.
+//!
 //! * `std`:
 #![cfg_attr(not(feature = "std"), doc = " disabled.")]
 #![cfg_attr(feature = "std", doc = " enabled (default).")]
@@ -225,6 +256,13 @@
 //!     * `parsers::Error` no longer implements the `std::error::Error` trait.
 //!     * `types::Buffer::to_vec`
 //!     * `types::Buffer::into_vec`
+//!
+//! * `trace`:
+#![cfg_attr(not(feature = "trace"), doc = " disabled (default).")]
+#![cfg_attr(feature = "trace", doc = " enabled.")]
+//!    Enables the `trace` module, which records enter and exit events of
+//! parsers named with `combinators::trace` to a pluggable sink and renders
+//! them as an indented parse tree. Implies `std`.
 
 #![warn(
     missing_docs,
@@ -275,8 +313,15 @@ pub mod ascii;
 #[cfg(feature = "std")]
 pub mod buffer;
 pub mod combinators;
+#[cfg(any(feature = "csv", feature = "http", feature = "json", feature = "serde"))]
+pub mod formats;
+#[cfg(feature = "std")]
+pub mod parallel;
 pub mod parsers;
 pub mod primitives;
+pub mod search;
+#[cfg(feature = "trace")]
+pub mod trace;
 pub mod types;
 
 pub use crate::parse::parse_only;
@@ -0,0 +1,311 @@
+//! Parsing of inputs made of independent records on multiple threads.
+//!
+//! Formats like newline-delimited JSON or log files consist of records which
+//! can be parsed without knowing anything about the records before them.
+//! `parse_records` splits such an input at record boundaries into one part per
+//! thread, parses the parts on scoped threads and concatenates the results in
+//! input order.
+//!
+//! Note: Requires the `std` feature.
+//!
+//! ```
+//! use chomp1::ascii::decimal;
+//! use chomp1::parallel::parse_records;
+//! use chomp1::prelude::{token, SimpleResult, U8Input};
+//!
+//! fn record<I: U8Input>(i: I) -> SimpleResult<I, u32> {
+//!     decimal(i).bind(|i, n| token(i, b'\n').map(|_| n))
+//! }
+//!
+//! let input = b"1\n22\n333\n4444\n55555\n";
+//!
+//! assert_eq!(
+//!     parse_records(input, 2, |i| token(i, b'\n'), record),
+//!     Ok(vec![1, 22, 333, 4444, 55555])
+//! );
+//!
+//! // Error offsets are relative to the whole input
+//! let e = parse_records(b"1\n22\n3x3\n", 2, |i| token(i, b'\n'), record).unwrap_err();
+//!
+//! assert_eq!(e.offset(), 6);
+//! ```
+
+use std::cmp;
+use std::error;
+use std::fmt;
+use std::panic;
+use std::thread;
+
+use crate::parsers::Error;
+use crate::primitives::IntoInner;
+use crate::types::ParseResult;
+
+/// Error from a record parser together with the offset in the whole input it
+/// stopped at.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct RecordError<E> {
+    offset: usize,
+    error: E,
+}
+
+impl<E> RecordError<E> {
+    /// Offset of the remainder of the input when the parser failed, counted
+    /// from the start of the whole input.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The error returned by the record parser.
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Consumes the `RecordError` returning the error from the record parser.
+    pub fn into_error(self) -> E {
+        self.error
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for RecordError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset {}", self.error, self.offset)
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> error::Error for RecordError<E> {}
+
+/// Splits `input` into at most `parts` consecutive parts of roughly equal size,
+/// each ending right after a match of the `boundary` parser or at the end of
+/// the input.
+///
+/// The search for a boundary starts at the even split point and moves forward
+/// one token at a time until `boundary` succeeds and consumes input, so
+/// `boundary` must not match inside a record. Empty input yields no parts.
+///
+/// ```
+/// use chomp1::parallel::split_records;
+/// use chomp1::parsers::token;
+///
+/// assert_eq!(
+///     split_records(b"ab\ncd\nef", 3, |i| token(i, b'\n')),
+///     [&b"ab\n"[..], b"cd\n", b"ef"]
+/// );
+/// ```
+pub fn split_records<'a, B, U, E>(input: &'a [u8], parts: usize, mut boundary: B) -> Vec<&'a [u8]>
+where
+    B: FnMut(&'a [u8]) -> ParseResult<&'a [u8], U, E>,
+{
+    let parts = cmp::max(parts, 1);
+    let mut v = Vec::with_capacity(parts);
+    let mut start = 0;
+
+    for k in 1..parts {
+        let mut p = cmp::max(input.len() / parts * k, start);
+
+        let end = loop {
+            if p >= input.len() {
+                break input.len();
+            }
+
+            match boundary(&input[p..]).into_inner() {
+                (rest, Ok(_)) if rest.len() < input.len() - p => break input.len() - rest.len(),
+                _ => p += 1,
+            }
+        };
+
+        if end >= input.len() {
+            break;
+        }
+
+        v.push(&input[start..end]);
+
+        start = end;
+    }
+
+    if start < input.len() {
+        v.push(&input[start..]);
+    }
+
+    v
+}
+
+/// Runs `parser` repeatedly on `part` until it is empty, returning the offset
+/// of the first record which failed to parse.
+fn parse_part<'a, T, E, F>(part: &'a [u8], parser: &F) -> Result<Vec<T>, usize>
+where
+    F: Fn(&'a [u8]) -> ParseResult<&'a [u8], T, E>,
+{
+    let mut v = Vec::new();
+    let mut i = part;
+
+    while !i.is_empty() {
+        match parser(i).into_inner() {
+            (rest, Ok(t)) if rest.len() < i.len() => {
+                v.push(t);
+
+                i = rest;
+            }
+            _ => return Err(part.len() - i.len()),
+        }
+    }
+
+    Ok(v)
+}
+
+/// Runs `parser` again on the record at `start` in the part which begins at
+/// `offset` in the input, to obtain the error without sending it between
+/// threads.
+fn record_error<'a, T, E, F>(
+    part: &'a [u8],
+    offset: usize,
+    start: usize,
+    parser: &F,
+) -> RecordError<E>
+where
+    F: Fn(&'a [u8]) -> ParseResult<&'a [u8], T, E>,
+    E: From<Error<u8>>,
+{
+    let (rest, r) = parser(&part[start..]).into_inner();
+
+    RecordError {
+        offset: offset + part.len() - rest.len(),
+        error: match r {
+            Err(e) => e,
+            // Succeeded without consuming any input, would loop forever
+            Ok(_) => Error::unexpected().into(),
+        },
+    }
+}
+
+/// Parses all the records in `input` using up to `threads` threads, returning
+/// the records in input order.
+///
+/// The input is split with `split_records` using the `boundary` parser, then
+/// `parser` is run repeatedly on each part until the part is empty. `parser`
+/// has to consume a whole record including the boundary following it, a
+/// successful parse which does not consume any input fails with
+/// `Error::unexpected`.
+///
+/// If any record fails to parse the error closest to the start of the input is
+/// returned, with its offset relative to the start of `input`. Errors are not
+/// sent between threads, instead the failed record is parsed again on the
+/// calling thread to obtain the error.
+///
+/// Panics in `parser` are propagated to the caller.
+pub fn parse_records<'a, T, E, F, B, U, V>(
+    input: &'a [u8],
+    threads: usize,
+    boundary: B,
+    parser: F,
+) -> Result<Vec<T>, RecordError<E>>
+where
+    B: FnMut(&'a [u8]) -> ParseResult<&'a [u8], U, V>,
+    F: Fn(&'a [u8]) -> ParseResult<&'a [u8], T, E> + Sync,
+    T: Send,
+    E: From<Error<u8>>,
+{
+    let parts = split_records(input, threads, boundary);
+    let parser = &parser;
+
+    let results: Vec<_> = if parts.len() <= 1 {
+        parts.iter().map(|p| parse_part(p, parser)).collect()
+    } else {
+        thread::scope(|s| {
+            let handles: Vec<_> = parts
+                .iter()
+                .map(|&p| s.spawn(move || parse_part(p, parser)))
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        })
+    };
+
+    let mut v = Vec::new();
+    let mut offset = 0;
+
+    for (p, r) in parts.iter().zip(results) {
+        match r {
+            Ok(t) => v.extend(t),
+            Err(start) => return Err(record_error(p, offset, start, parser)),
+        }
+
+        offset += p.len();
+    }
+
+    Ok(v)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ascii::decimal;
+    use crate::parsers::{take_while, token, SimpleResult};
+    use crate::types::U8Input;
+
+    fn record<I: U8Input>(i: I) -> SimpleResult<I, u32> {
+        decimal(i).bind(|i, n| token(i, b'\n').map(|_| n))
+    }
+
+    fn newline<I: U8Input>(i: I) -> SimpleResult<I, u8> {
+        token(i, b'\n')
+    }
+
+    #[test]
+    fn split() {
+        let input = b"1\n22\n333\n4444\n";
+
+        for n in 0..20 {
+            let parts = split_records(input, n, newline);
+
+            assert!(parts.len() <= cmp::max(n, 1));
+            assert!(parts.iter().all(|p| p.last() == Some(&b'\n')));
+            assert_eq!(parts.concat(), &input[..]);
+        }
+
+        assert_eq!(split_records(b"", 4, newline), Vec::<&[u8]>::new());
+        assert_eq!(split_records(b"abc", 4, newline), [&b"abc"[..]]);
+        assert_eq!(split_records(b"ab\ncd", 2, newline), [&b"ab\n"[..], b"cd"]);
+    }
+
+    #[test]
+    fn in_order() {
+        let input: Vec<u8> = (0..1000u32)
+            .flat_map(|n| format!("{}\n", n).into_bytes())
+            .collect();
+        let expected: Vec<u32> = (0..1000).collect();
+
+        for n in [1, 2, 3, 8, 64] {
+            assert_eq!(
+                parse_records(&input, n, newline, record),
+                Ok(expected.clone())
+            );
+        }
+
+        assert_eq!(parse_records(b"", 4, newline, record), Ok(vec![]));
+    }
+
+    #[test]
+    fn errors() {
+        // Both the second and the last part contain errors
+        let input = b"1\n2\n3\n4\n5x\n6\n7\n8x\n";
+        let e = parse_records(input, 4, newline, record).unwrap_err();
+
+        assert_eq!(e.offset(), 9);
+        assert_eq!(e.error(), &Error::expected(b'\n'));
+        assert_eq!(
+            parse_records(input, 1, newline, record)
+                .unwrap_err()
+                .offset(),
+            9
+        );
+
+        // No progress
+        let e = parse_records(b"a\nb\n", 2, newline, |i| take_while(i, |c| c == b'b')).unwrap_err();
+
+        assert_eq!(e.offset(), 0);
+        assert_eq!(e.into_error(), Error::unexpected());
+    }
+}
@@ -444,24 +444,9 @@ pub fn string<T: Copy + PartialEq, I: Input<Token = T>>(
     mut i: I,
     s: &[T],
 ) -> SimpleResult<I, I::Buffer> {
-    let mut n = 0;
-    let len = s.len();
-
-    // TODO: There has to be some more efficient way here
-    let b = i.consume_while(|c| {
-        if n >= len || c != s[n] {
-            false
-        } else {
-            n += 1;
-
-            true
-        }
-    });
-
-    if n >= len {
-        i.ret(b)
-    } else {
-        i.err(Error::expected(s[n]))
+    match i.consume_prefix(s) {
+        Ok(b) => i.ret(b),
+        Err(n) => i.err(Error::expected(s[n])),
     }
 }
 
@@ -906,6 +891,28 @@ mod test {
         );
     }
 
+    #[test]
+    fn string_input_buf_test() {
+        use crate::buffer::InputBuf;
+
+        let (i, r) = string(InputBuf::new(b"abcd"), b"abc").into_inner();
+
+        assert_eq!(r, Ok(&b"abc"[..]));
+        assert!(!i.is_incomplete());
+        assert_eq!(i.len(), 1);
+
+        let (i, r) = string(InputBuf::new(b"abd"), b"abc").into_inner();
+
+        assert_eq!(r, Err(Error::expected(b'c')));
+        assert!(!i.is_incomplete());
+        assert_eq!(i.len(), 1);
+
+        let (i, r) = string(InputBuf::new(b"ab"), b"abc").into_inner();
+
+        assert_eq!(r, Err(Error::expected(b'c')));
+        assert!(i.is_incomplete());
+    }
+
     #[test]
     fn skip_while1_test() {
         assert_eq!(
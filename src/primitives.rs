@@ -5,6 +5,7 @@
 //! read the documentation since the methods are not useful when using the
 //! library or writing primitive parsers.
 
+use crate::search::Needle;
 use crate::types::Input;
 
 /// Consumes self and reveals the inner state.
@@ -119,6 +120,30 @@ pub trait Primitives: Input {
         self._skip_while(Guard(()), f)
     }
 
+    /// Consumes tokens until one matching the needle `n` is found, all tokens
+    /// up to that token will be returned as a buffer.
+    ///
+    /// If the end of the input is reached, the whole input is returned.
+    ///
+    /// Slice-backed inputs search using `Needle::find`, other inputs test each
+    /// token in order.
+    #[inline(always)]
+    fn consume_till<N>(&mut self, n: &N) -> Self::Buffer
+    where
+        N: Needle<Self::Token>,
+    {
+        self._consume_till(Guard(()), n)
+    }
+
+    /// Consumes `s` if the input starts with it, returning the matched buffer.
+    ///
+    /// Otherwise the tokens matching a prefix of `s` are consumed and their
+    /// number is returned as an error.
+    #[inline(always)]
+    fn consume_prefix(&mut self, s: &[Self::Token]) -> Result<Self::Buffer, usize> {
+        self._consume_prefix(Guard(()), s)
+    }
+
     /// Marks the current position to be able to backtrack to it using
     /// `restore`.
     #[inline(always)]
@@ -131,6 +156,15 @@ pub trait Primitives: Input {
     fn restore(self, m: Self::Marker) -> Self {
         self._restore(Guard(()), m)
     }
+
+    /// Reports a trace event for the parser `name` at the current position.
+    ///
+    /// Ignored unless the input is wrapped in `trace::Traced`.
+    #[cfg(feature = "trace")]
+    #[inline(always)]
+    fn trace(&self, name: &'static str, kind: crate::trace::EventKind) {
+        self._trace(Guard(()), name, kind)
+    }
 }
 
 impl<I: Input> Primitives for I {}
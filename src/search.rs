@@ -0,0 +1,474 @@
+//! Parsers searching for bytes using accelerated scans.
+//!
+//! The parsers in `chomp1::parsers` test one token at a time using a closure,
+//! which the compiler cannot turn into a vectorized search. The `*_bytes`
+//! parsers in this module instead take a `Needle` describing the bytes to look
+//! for, which lets slice-backed inputs (`&[u8]` and `buffer::InputBuf`) use
+//! `memchr` when looking for up to three distinct bytes, and a lookup table for
+//! arbitrary sets of bytes through `ByteSet`. Other inputs fall back to testing
+//! each token.
+//!
+//! Prefer these over `parsers::take_till`, `parsers::take_while`,
+//! `parsers::take_while1` and `parsers::skip_while` when the tokens to match
+//! form a fixed set of bytes and the parser runs over long stretches of input,
+//! eg. scanning for a line ending. Keep the closure versions for conditions
+//! which cannot be expressed as a set of bytes.
+//!
+//! ```
+//! use chomp1::parse_only;
+//! use chomp1::search::{take_till_bytes, take_while_bytes, ByteSet};
+//!
+//! let digits = ByteSet::from_range(b'0', b'9');
+//!
+//! assert_eq!(parse_only(|i| take_till_bytes(i, (b'\r', b'\n')), b"GET /\r\n"), Ok(&b"GET /"[..]));
+//! assert_eq!(parse_only(|i| take_while_bytes(i, digits), b"123abc"), Ok(&b"123"[..]));
+//! ```
+
+use std::fmt;
+use std::ops;
+
+use crate::parsers::{Error, SimpleResult};
+use crate::primitives::Primitives;
+use crate::types::{Buffer, Input};
+
+/// A set of tokens which can be searched for in a slice.
+///
+/// Implemented for `u8`, pairs and triples of `u8` (using `memchr`, `memchr2`
+/// and `memchr3` respectively) as well as for `ByteSet`.
+pub trait Needle<T: Copy> {
+    /// Returns true if `t` is part of this needle.
+    fn matches(&self, t: T) -> bool;
+
+    /// Returns the index of the first token in `haystack` which matches.
+    #[inline]
+    fn find(&self, haystack: &[T]) -> Option<usize> {
+        haystack.iter().position(|&t| self.matches(t))
+    }
+}
+
+impl Needle<u8> for u8 {
+    #[inline]
+    fn matches(&self, t: u8) -> bool {
+        *self == t
+    }
+
+    #[inline]
+    fn find(&self, haystack: &[u8]) -> Option<usize> {
+        memchr::memchr(*self, haystack)
+    }
+}
+
+impl Needle<u8> for (u8, u8) {
+    #[inline]
+    fn matches(&self, t: u8) -> bool {
+        self.0 == t || self.1 == t
+    }
+
+    #[inline]
+    fn find(&self, haystack: &[u8]) -> Option<usize> {
+        memchr::memchr2(self.0, self.1, haystack)
+    }
+}
+
+impl Needle<u8> for (u8, u8, u8) {
+    #[inline]
+    fn matches(&self, t: u8) -> bool {
+        self.0 == t || self.1 == t || self.2 == t
+    }
+
+    #[inline]
+    fn find(&self, haystack: &[u8]) -> Option<usize> {
+        memchr::memchr3(self.0, self.1, self.2, haystack)
+    }
+}
+
+/// Set of bytes backed by a 256-entry lookup table.
+#[derive(Clone, Copy)]
+pub struct ByteSet([bool; 256]);
+
+impl ByteSet {
+    /// Creates an empty set.
+    pub const fn new() -> Self {
+        ByteSet([false; 256])
+    }
+
+    /// Creates a set containing all the bytes in `bytes`.
+    pub const fn from_bytes(bytes: &[u8]) -> Self {
+        let mut s = [false; 256];
+        let mut n = 0;
+
+        while n < bytes.len() {
+            s[bytes[n] as usize] = true;
+            n += 1;
+        }
+
+        ByteSet(s)
+    }
+
+    /// Creates a set containing the bytes from `start` to `end`, inclusive.
+    pub const fn from_range(start: u8, end: u8) -> Self {
+        let mut s = [false; 256];
+        let mut n = start as usize;
+
+        while n <= end as usize {
+            s[n] = true;
+            n += 1;
+        }
+
+        ByteSet(s)
+    }
+
+    /// Adds `b` to the set.
+    pub fn insert(&mut self, b: u8) {
+        self.0[b as usize] = true;
+    }
+
+    /// Returns true if `b` is in the set.
+    #[inline]
+    pub const fn contains(&self, b: u8) -> bool {
+        self.0[b as usize]
+    }
+
+    /// Returns the set of bytes in either `self` or `other`.
+    pub const fn union(self, other: ByteSet) -> Self {
+        let mut s = self.0;
+        let mut n = 0;
+
+        while n < 256 {
+            s[n] = s[n] || other.0[n];
+            n += 1;
+        }
+
+        ByteSet(s)
+    }
+
+    /// Returns the set of bytes in both `self` and `other`.
+    pub const fn intersection(self, other: ByteSet) -> Self {
+        let mut s = self.0;
+        let mut n = 0;
+
+        while n < 256 {
+            s[n] = s[n] && other.0[n];
+            n += 1;
+        }
+
+        ByteSet(s)
+    }
+
+    /// Returns the set of bytes in `self` but not in `other`.
+    pub const fn difference(self, other: ByteSet) -> Self {
+        self.intersection(other.complement())
+    }
+
+    /// Returns the set of all bytes not in `self`.
+    pub const fn complement(self) -> Self {
+        let mut s = self.0;
+        let mut n = 0;
+
+        while n < 256 {
+            s[n] = !s[n];
+            n += 1;
+        }
+
+        ByteSet(s)
+    }
+
+    /// Returns a predicate closure testing membership in this set, for use
+    /// with parsers like `parsers::satisfy` and `parsers::take_while`.
+    ///
+    /// ```
+    /// use chomp1::ascii::HEX_DIGIT;
+    /// use chomp1::parse_only;
+    /// use chomp1::parsers::take_while1;
+    ///
+    /// let r = parse_only(|i| take_while1(i, HEX_DIGIT.predicate()), b"c0ffee!");
+    ///
+    /// assert_eq!(r, Ok(&b"c0ffee"[..]));
+    /// ```
+    #[inline]
+    pub fn predicate(self) -> impl Fn(u8) -> bool + Copy {
+        move |b| self.contains(b)
+    }
+}
+
+impl ops::BitOr for ByteSet {
+    type Output = ByteSet;
+
+    fn bitor(self, other: ByteSet) -> ByteSet {
+        self.union(other)
+    }
+}
+
+impl ops::BitAnd for ByteSet {
+    type Output = ByteSet;
+
+    fn bitand(self, other: ByteSet) -> ByteSet {
+        self.intersection(other)
+    }
+}
+
+impl ops::Sub for ByteSet {
+    type Output = ByteSet;
+
+    fn sub(self, other: ByteSet) -> ByteSet {
+        self.difference(other)
+    }
+}
+
+impl ops::Not for ByteSet {
+    type Output = ByteSet;
+
+    fn not(self) -> ByteSet {
+        self.complement()
+    }
+}
+
+impl Default for ByteSet {
+    fn default() -> Self {
+        ByteSet::new()
+    }
+}
+
+impl fmt::Debug for ByteSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set()
+            .entries((0..=255u8).filter(|&b| self.contains(b)))
+            .finish()
+    }
+}
+
+impl PartialEq for ByteSet {
+    fn eq(&self, other: &ByteSet) -> bool {
+        self.0[..] == other.0[..]
+    }
+}
+
+impl Eq for ByteSet {}
+
+impl Needle<u8> for ByteSet {
+    #[inline]
+    fn matches(&self, t: u8) -> bool {
+        self.contains(t)
+    }
+}
+
+/// Matches every token *not* matched by the wrapped needle.
+struct Not<'a, N>(&'a N);
+
+impl<'a, T: Copy, N: Needle<T>> Needle<T> for Not<'a, N> {
+    #[inline]
+    fn matches(&self, t: T) -> bool {
+        !self.0.matches(t)
+    }
+}
+
+/// Matches all items until one matching `n` is found, all items to that point
+/// will be returned as a slice upon success.
+///
+/// If no match can be found the parser will be considered to be incomplete as
+/// there might be more input which needs to be matched.
+///
+/// ```
+/// use chomp1::parse_only;
+/// use chomp1::search::take_till_bytes;
+///
+/// assert_eq!(parse_only(|i| take_till_bytes(i, b'd'), b"abcdef"), Ok(&b"abc"[..]));
+/// assert!(parse_only(|i| take_till_bytes(i, b'd'), b"abc").is_err());
+/// ```
+#[inline]
+pub fn take_till_bytes<I: Input, N>(mut i: I, n: N) -> SimpleResult<I, I::Buffer>
+where
+    N: Needle<I::Token>,
+{
+    let b = i.consume_till(&n);
+
+    if i.peek().is_some() {
+        i.ret(b)
+    } else {
+        i.err(Error::unexpected())
+    }
+}
+
+/// Matches all items while they match `n`, returning a slice of all the
+/// matched items.
+///
+/// If no failure can be found the parser will be considered to be incomplete as
+/// there might be more input which needs to be matched.
+///
+/// ```
+/// use chomp1::parse_only;
+/// use chomp1::search::{take_while_bytes, ByteSet};
+///
+/// let r = parse_only(|i| take_while_bytes(i, ByteSet::from_bytes(b"ab")), b"abcdcba");
+///
+/// assert_eq!(r, Ok(&b"ab"[..]));
+/// ```
+#[inline]
+pub fn take_while_bytes<I: Input, N>(mut i: I, n: N) -> SimpleResult<I, I::Buffer>
+where
+    N: Needle<I::Token>,
+{
+    let b = i.consume_till(&Not(&n));
+
+    i.ret(b)
+}
+
+/// Matches all items while they match `n`, if at least one item matched this
+/// parser succeeds and returns a slice of all the matched items.
+///
+/// ```
+/// use chomp1::parse_only;
+/// use chomp1::parsers::Error;
+/// use chomp1::search::take_while1_bytes;
+///
+/// assert_eq!(parse_only(|i| take_while1_bytes(i, b' '), b"  a"), Ok(&b"  "[..]));
+/// assert_eq!(parse_only(|i| take_while1_bytes(i, b' '), b"a"), Err((&b"a"[..], Error::unexpected())));
+/// ```
+#[inline]
+pub fn take_while1_bytes<I: Input, N>(mut i: I, n: N) -> SimpleResult<I, I::Buffer>
+where
+    N: Needle<I::Token>,
+{
+    let b = i.consume_till(&Not(&n));
+
+    if b.is_empty() {
+        i.err(Error::unexpected())
+    } else {
+        i.ret(b)
+    }
+}
+
+/// Skips over tokens in the input while they match `n`.
+///
+/// ```
+/// use chomp1::parse_only;
+/// use chomp1::parsers::take_remainder;
+/// use chomp1::search::skip_while_bytes;
+///
+/// let r = parse_only(|i| skip_while_bytes(i, (b' ', b'\t')).then(take_remainder), b" \t a");
+///
+/// assert_eq!(r, Ok(&b"a"[..]));
+/// ```
+#[inline]
+pub fn skip_while_bytes<I: Input, N>(mut i: I, n: N) -> SimpleResult<I, ()>
+where
+    N: Needle<I::Token>,
+{
+    i.consume_till(&Not(&n));
+
+    i.ret(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::buffer::InputBuf;
+    use crate::primitives::IntoInner;
+    use crate::types::numbering::{InputPosition, LineNumber};
+
+    #[test]
+    fn needles() {
+        assert_eq!(b'c'.find(b"abcabc"), Some(2));
+        assert_eq!((b'x', b'c').find(b"abcabc"), Some(2));
+        assert_eq!((b'x', b'y', b'z').find(b"abcabc"), None);
+        assert!((b'a', b'b', b'c').matches(b'b'));
+        assert!(!(b'a', b'b').matches(b'c'));
+
+        let s = ByteSet::from_bytes(b"cb");
+
+        assert_eq!(s.find(b"abcabc"), Some(1));
+        assert!(s.contains(b'c'));
+        assert!(!s.contains(b'a'));
+        assert_eq!(format!("{:?}", s), "{98, 99}");
+        assert_eq!(ByteSet::from_range(b'b', b'c'), s);
+        assert_eq!(ByteSet::from_range(0, 255).find(b"\xff"), Some(0));
+
+        let mut e = ByteSet::default();
+
+        e.insert(b'b');
+        e.insert(b'c');
+
+        assert_eq!(e, s);
+    }
+
+    #[test]
+    fn set_operations() {
+        const AB: ByteSet = ByteSet::from_bytes(b"ab");
+        const BC: ByteSet = ByteSet::from_range(b'b', b'c');
+        const UNION: ByteSet = AB.union(BC);
+        const NOT_AB: ByteSet = AB.complement();
+
+        assert_eq!(UNION, ByteSet::from_bytes(b"abc"));
+        assert_eq!(AB | BC, UNION);
+        assert_eq!(AB & BC, ByteSet::from_bytes(b"b"));
+        assert_eq!(AB - BC, ByteSet::from_bytes(b"a"));
+        assert_eq!(!AB, NOT_AB);
+        assert!(!NOT_AB.contains(b'a'));
+        assert!(NOT_AB.contains(b'c'));
+        assert!(NOT_AB.contains(255));
+        assert_eq!(!!AB, AB);
+        assert_eq!(AB & !AB, ByteSet::new());
+        assert_eq!(AB | !AB, ByteSet::from_range(0, 255));
+
+        let p = AB.predicate();
+
+        assert!(p(b'a'));
+        assert!(!p(b'c'));
+    }
+
+    #[test]
+    fn slices() {
+        assert_eq!(
+            take_till_bytes(&b"abc\ndef"[..], b'\n').into_inner(),
+            (&b"\ndef"[..], Ok(&b"abc"[..]))
+        );
+        assert_eq!(
+            take_till_bytes(&b"abc"[..], b'\n').into_inner(),
+            (&b""[..], Err(Error::unexpected()))
+        );
+        assert_eq!(
+            take_while_bytes(&b"  \t x"[..], (b' ', b'\t')).into_inner(),
+            (&b"x"[..], Ok(&b"  \t "[..]))
+        );
+        assert_eq!(
+            take_while_bytes(&b"   "[..], b' ').into_inner(),
+            (&b""[..], Ok(&b"   "[..]))
+        );
+        assert_eq!(
+            skip_while_bytes(&b"aab"[..], b'a').into_inner(),
+            (&b"b"[..], Ok(()))
+        );
+    }
+
+    #[test]
+    fn input_buf() {
+        let (i, r) = take_till_bytes(InputBuf::new(b"abc\n"), b'\n').into_inner();
+
+        assert_eq!(r, Ok(&b"abc"[..]));
+        assert!(!i.is_incomplete());
+
+        let (i, r) = take_till_bytes(InputBuf::new(b"abc"), b'\n').into_inner();
+
+        assert_eq!(r, Err(Error::unexpected()));
+        assert!(i.is_incomplete());
+
+        let (i, r) = take_while_bytes(InputBuf::new(b"aa"), b'a').into_inner();
+
+        assert_eq!(r, Ok(&b"aa"[..]));
+        assert!(i.is_incomplete());
+
+        let (i, r) = take_while1_bytes(InputBuf::new(b"ba"), b'a').into_inner();
+
+        assert_eq!(r, Err(Error::unexpected()));
+        assert!(!i.is_incomplete());
+    }
+
+    #[test]
+    fn fallback() {
+        let i = InputPosition::new(&b"ab\ncd\nef"[..], LineNumber::new());
+        let (i, r) = take_till_bytes(i, (b'e', b'f')).into_inner();
+
+        assert_eq!(r, Ok(&b"ab\ncd\n"[..]));
+        assert_eq!(i.position(), LineNumber(2));
+    }
+}
@@ -0,0 +1,375 @@
+//! Grammar-level tracing of named parsers.
+//!
+//! Parsers wrapped in `combinators::trace` report when they are entered and
+//! when they exit, together with the input offset at that point. Events are
+//! only produced when the input is wrapped in `Traced`, which keeps track of
+//! the offset and forwards every event to a `Sink`. Other inputs ignore them.
+//!
+//! `render` turns a recorded list of events into an indented parse tree,
+//! which is useful for finding out which part of a grammar rejected an
+//! input:
+//!
+//! ```
+//! use std::cell::RefCell;
+//!
+//! use chomp1::combinators::{many, trace};
+//! use chomp1::prelude::{token, SimpleResult, U8Input};
+//! use chomp1::run_parser;
+//! use chomp1::trace::{render, Traced};
+//!
+//! fn pair<I: U8Input>(i: I) -> SimpleResult<I, (u8, u8)> {
+//!     trace(i, "pair", |i| {
+//!         trace(i, "a", |i| token(i, b'a'))
+//!             .bind(|i, a| trace(i, "b", |i| token(i, b'b')).map(|b| (a, b)))
+//!     })
+//! }
+//!
+//! let sink = RefCell::new(Vec::new());
+//! let (_, r) = run_parser(Traced::new(&b"abac"[..], &sink), |i| {
+//!     many::<_, Vec<_>, _, _, _>(i, pair)
+//! });
+//!
+//! assert_eq!(r, Ok(vec![(b'a', b'b')]));
+//! assert_eq!(
+//!     render(&sink.borrow()),
+//!     "pair 0..2 ok
+//!   a 0..1 ok
+//!   b 1..2 ok
+//! pair 2..3 failed
+//!   a 2..3 ok
+//!   b 3..3 failed
+//! "
+//! );
+//! ```
+
+use std::cell::RefCell;
+use std::fmt;
+
+use crate::primitives::{Guard, IntoInner};
+use crate::types::{Buffer, Input};
+
+/// The kind of a trace event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EventKind {
+    /// The named parser is about to run.
+    Enter,
+    /// The named parser succeeded.
+    Success,
+    /// The named parser failed.
+    Failure,
+}
+
+/// An event emitted by a parser wrapped in `combinators::trace`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Event {
+    /// Name given to the parser.
+    pub name: &'static str,
+    /// Number of tokens consumed from the start of the traced input.
+    ///
+    /// For `Failure` this is the position the parser reported its error at.
+    pub offset: usize,
+    /// What happened.
+    pub kind: EventKind,
+}
+
+/// Receiver of trace events.
+pub trait Sink {
+    /// Records a trace event.
+    fn event(&mut self, event: Event);
+}
+
+impl Sink for Vec<Event> {
+    fn event(&mut self, event: Event) {
+        self.push(event)
+    }
+}
+
+/// Wrapper around an `Input` implementation which counts consumed tokens and
+/// forwards trace events to a sink.
+pub struct Traced<'s, I: Input> {
+    input: I,
+    offset: usize,
+    sink: &'s RefCell<dyn Sink + 's>,
+}
+
+impl<'s, I: Input> Traced<'s, I> {
+    /// Wraps `i`, sending trace events to `sink`.
+    pub fn new<S: Sink + 's>(i: I, sink: &'s RefCell<S>) -> Self {
+        Traced {
+            input: i,
+            offset: 0,
+            sink,
+        }
+    }
+
+    /// Number of tokens consumed so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'s, I: Input + fmt::Debug> fmt::Debug for Traced<'s, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Traced")
+            .field("input", &self.input)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+impl<'s, I: Input> IntoInner for Traced<'s, I> {
+    type Inner = (I, usize);
+
+    fn into_inner(self) -> Self::Inner {
+        (self.input, self.offset)
+    }
+}
+
+impl<'s, I: Input> Input for Traced<'s, I> {
+    type Buffer = I::Buffer;
+    type Marker = (usize, I::Marker);
+    type Token = I::Token;
+
+    #[inline]
+    fn _peek(&mut self, g: Guard) -> Option<Self::Token> {
+        self.input._peek(g)
+    }
+
+    #[inline]
+    fn _pop(&mut self, g: Guard) -> Option<Self::Token> {
+        self.input._pop(g).inspect(|_| self.offset += 1)
+    }
+
+    #[inline]
+    fn _consume(&mut self, g: Guard, n: usize) -> Option<Self::Buffer> {
+        self.input._consume(g, n).inspect(|_| self.offset += n)
+    }
+
+    #[inline]
+    fn _consume_while<F>(&mut self, g: Guard, f: F) -> Self::Buffer
+    where
+        F: FnMut(Self::Token) -> bool,
+    {
+        let b = self.input._consume_while(g, f);
+
+        self.offset += b.len();
+
+        b
+    }
+
+    #[inline]
+    fn _consume_from(&mut self, g: Guard, m: Self::Marker) -> Self::Buffer {
+        self.input._consume_from(g, m.1)
+    }
+
+    #[inline]
+    fn _consume_remaining(&mut self, g: Guard) -> Self::Buffer {
+        let b = self.input._consume_remaining(g);
+
+        self.offset += b.len();
+
+        b
+    }
+
+    #[inline]
+    fn _mark(&self, g: Guard) -> Self::Marker {
+        (self.offset, self.input._mark(g))
+    }
+
+    #[inline]
+    fn _restore(self, g: Guard, m: Self::Marker) -> Self {
+        Traced {
+            input: self.input._restore(g, m.1),
+            offset: m.0,
+            sink: self.sink,
+        }
+    }
+
+    #[inline]
+    fn _trace(&self, _: Guard, name: &'static str, kind: EventKind) {
+        self.sink.borrow_mut().event(Event {
+            name,
+            offset: self.offset,
+            kind,
+        })
+    }
+}
+
+/// Renders a list of events as an indented tree, one line per parser
+/// invocation.
+///
+/// Each line contains the name of the parser, the range of offsets it covered
+/// and whether it succeeded. Children are indented by two spaces below their
+/// parent. Invocations which never exited are rendered with an open range.
+pub fn render(events: &[Event]) -> String {
+    let mut calls: Vec<(usize, &Event, Option<&Event>)> = Vec::new();
+    let mut stack = Vec::new();
+
+    for e in events {
+        match e.kind {
+            EventKind::Enter => {
+                stack.push(calls.len());
+                calls.push((stack.len() - 1, e, None));
+            }
+            EventKind::Success | EventKind::Failure => {
+                if let Some(n) = stack.pop() {
+                    calls[n].2 = Some(e);
+                }
+            }
+        }
+    }
+
+    let mut out = String::new();
+
+    for (depth, enter, exit) in calls {
+        for _ in 0..depth {
+            out.push_str("  ");
+        }
+
+        match exit {
+            Some(exit) => out.push_str(&format!(
+                "{} {}..{} {}\n",
+                enter.name,
+                enter.offset,
+                exit.offset,
+                if exit.kind == EventKind::Success {
+                    "ok"
+                } else {
+                    "failed"
+                }
+            )),
+            None => out.push_str(&format!("{} {}..\n", enter.name, enter.offset)),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::combinators::{or, trace};
+    use crate::parsers::{string, take_while, token, Error};
+    use crate::primitives::Primitives;
+    use crate::run_parser;
+    use crate::types::ParseResult;
+
+    type R<'s, 'a, T> = ParseResult<Traced<'s, &'a [u8]>, T, Error<u8>>;
+
+    #[test]
+    fn offsets() {
+        let sink = RefCell::new(Vec::new());
+        let (i, r) = run_parser(Traced::new(&b"foo  bar"[..], &sink), |i| -> R<_> {
+            trace(i, "word", |i| take_while(i, |c| c != b' '))
+                .bind(|i, _| trace(i, "space", |i| take_while(i, |c| c == b' ')))
+                .bind(|i, _| trace(i, "end", |i| string(i, b"baz")))
+        });
+
+        assert_eq!(r, Err(Error::expected(b'z')));
+        assert_eq!(i.offset(), 7);
+        assert_eq!(
+            *sink.borrow(),
+            vec![
+                Event {
+                    name: "word",
+                    offset: 0,
+                    kind: EventKind::Enter
+                },
+                Event {
+                    name: "word",
+                    offset: 3,
+                    kind: EventKind::Success
+                },
+                Event {
+                    name: "space",
+                    offset: 3,
+                    kind: EventKind::Enter
+                },
+                Event {
+                    name: "space",
+                    offset: 5,
+                    kind: EventKind::Success
+                },
+                Event {
+                    name: "end",
+                    offset: 5,
+                    kind: EventKind::Enter
+                },
+                Event {
+                    name: "end",
+                    offset: 7,
+                    kind: EventKind::Failure
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn backtracking() {
+        let sink = RefCell::new(Vec::new());
+        let (i, r) = run_parser(Traced::new(&b"abd"[..], &sink), |i| -> R<_> {
+            trace(i, "alt", |i| {
+                or(
+                    i,
+                    |i| trace(i, "abc", |i| string(i, b"abc")),
+                    |i| trace(i, "abd", |i| string(i, b"abd")),
+                )
+            })
+        });
+
+        assert_eq!(r, Ok(&b"abd"[..]));
+        assert_eq!(i.offset(), 3);
+        assert_eq!(
+            render(&sink.borrow()),
+            "alt 0..3 ok\n  abc 0..2 failed\n  abd 0..3 ok\n"
+        );
+
+        let mut i = Traced::new(&b"abc"[..], &sink);
+        let m = i.mark();
+
+        assert_eq!(i.consume(2), Some(&b"ab"[..]));
+        assert_eq!(i.offset(), 2);
+        assert_eq!(i.consume_from(m), &b"ab"[..]);
+        assert_eq!(i.offset(), 2);
+
+        let m = i.mark();
+
+        i.skip_while(|_| true);
+
+        assert_eq!(i.offset(), 3);
+        assert_eq!(i.restore(m).into_inner(), (&b"c"[..], 2));
+    }
+
+    #[test]
+    fn render_unfinished() {
+        let events = [
+            Event {
+                name: "outer",
+                offset: 0,
+                kind: EventKind::Enter,
+            },
+            Event {
+                name: "inner",
+                offset: 1,
+                kind: EventKind::Enter,
+            },
+            Event {
+                name: "inner",
+                offset: 4,
+                kind: EventKind::Failure,
+            },
+        ];
+
+        assert_eq!(render(&events), "outer 0..\n  inner 1..4 failed\n");
+        assert_eq!(render(&[]), "");
+    }
+
+    #[test]
+    fn untraced_input() {
+        let r: ParseResult<_, _, Error<u8>> = trace(&b"ab"[..], "a", |i| token(i, b'a'));
+
+        assert_eq!(r.into_inner(), (&b"b"[..], Ok(b'a')));
+    }
+}
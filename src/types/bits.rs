@@ -0,0 +1,508 @@
+//! Module containing tools for parsing data which is not aligned to bytes.
+//!
+//! `BitInput` wraps a byte slice and yields its bits one at a time as `bool`
+//! tokens, so all the generic parsers and combinators work on it. Markers are
+//! bit offsets, backtracking works just like on a byte slice. `take_bits` reads
+//! up to 64 bits as an integer, `align`, `bytes` and `bits` move between bit
+//! and byte-aligned parsing.
+//!
+//! ```
+//! use chomp1::parsers::Error;
+//! use chomp1::primitives::IntoInner;
+//! use chomp1::types::bits::{bits, take_bits, BitInput, BitOrder};
+//! use chomp1::types::ParseResult;
+//!
+//! // First two bytes of the flags of a DNS header: QR, OPCODE, AA, TC, RD
+//! fn flags(i: BitInput) -> ParseResult<BitInput, (u64, u64, u64, u64, u64), Error<bool>> {
+//!     take_bits(i, 1).bind(|i, qr| {
+//!         take_bits(i, 4).bind(|i, opcode| {
+//!             take_bits(i, 1).bind(|i, aa| {
+//!                 take_bits(i, 1).bind(|i, tc| take_bits(i, 1).map(|rd| (qr, opcode, aa, tc, rd)))
+//!             })
+//!         })
+//!     })
+//! }
+//!
+//! let r = bits(&[0b1001_0001, 0xff][..], BitOrder::Msb, flags).into_inner();
+//!
+//! assert_eq!(r, (&[0xff][..], Ok((1, 2, 0, 0, 1))));
+//! ```
+
+use std::fmt;
+
+use crate::parsers::{Error, SimpleResult};
+use crate::primitives::{Guard, IntoInner, Primitives};
+use crate::types::{Buffer, Input, ParseResult};
+
+/// The order in which the bits of each byte are read.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum BitOrder {
+    /// Most significant bit first, as used by network protocols and most media
+    /// headers. `take_bits` places the first bit read in the most significant
+    /// position of the result.
+    Msb,
+    /// Least significant bit first, as used by DEFLATE. `take_bits` places the
+    /// first bit read in the least significant position of the result.
+    Lsb,
+}
+
+impl BitOrder {
+    #[inline]
+    fn bit(self, data: &[u8], offset: usize) -> bool {
+        let byte = data[offset / 8];
+
+        match self {
+            BitOrder::Msb => byte >> (7 - offset % 8) & 1 == 1,
+            BitOrder::Lsb => byte >> (offset % 8) & 1 == 1,
+        }
+    }
+}
+
+/// Input over the bits of a byte slice.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BitInput<'a> {
+    data: &'a [u8],
+    offset: usize,
+    order: BitOrder,
+}
+
+impl<'a> BitInput<'a> {
+    /// Creates an input over the bits of `data`, read in the given `order`.
+    pub fn new(data: &'a [u8], order: BitOrder) -> Self {
+        BitInput {
+            data,
+            offset: 0,
+            order,
+        }
+    }
+
+    /// The bit order of this input.
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    /// Number of bits consumed from the start of the data.
+    pub fn bit_offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Number of bits remaining.
+    pub fn remaining_bits(&self) -> usize {
+        self.data.len() * 8 - self.offset
+    }
+
+    /// Returns true if the input is positioned at the start of a byte.
+    pub fn is_aligned(&self) -> bool {
+        self.offset & 7 == 0
+    }
+
+    #[inline]
+    fn bits(&self, start: usize, end: usize) -> Bits<'a> {
+        Bits {
+            data: self.data,
+            start,
+            end,
+            order: self.order,
+        }
+    }
+}
+
+impl<'a> IntoInner for BitInput<'a> {
+    /// The data and the current bit offset.
+    type Inner = (&'a [u8], usize);
+
+    fn into_inner(self) -> Self::Inner {
+        (self.data, self.offset)
+    }
+}
+
+impl<'a> Input for BitInput<'a> {
+    type Token = bool;
+    type Marker = usize;
+    type Buffer = Bits<'a>;
+
+    #[inline]
+    fn _peek(&mut self, _g: Guard) -> Option<Self::Token> {
+        if self.remaining_bits() > 0 {
+            Some(self.order.bit(self.data, self.offset))
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn _pop(&mut self, g: Guard) -> Option<Self::Token> {
+        self._peek(g).inspect(|_| self.offset += 1)
+    }
+
+    #[inline]
+    fn _consume(&mut self, _g: Guard, n: usize) -> Option<Self::Buffer> {
+        if n > self.remaining_bits() {
+            None
+        } else {
+            let b = self.bits(self.offset, self.offset + n);
+
+            self.offset += n;
+
+            Some(b)
+        }
+    }
+
+    #[inline]
+    fn _consume_while<F>(&mut self, _g: Guard, mut f: F) -> Self::Buffer
+    where
+        F: FnMut(Self::Token) -> bool,
+    {
+        let start = self.offset;
+        let end = self.data.len() * 8;
+
+        while self.offset < end && f(self.order.bit(self.data, self.offset)) {
+            self.offset += 1;
+        }
+
+        self.bits(start, self.offset)
+    }
+
+    #[inline]
+    fn _consume_from(&mut self, _g: Guard, m: Self::Marker) -> Self::Buffer {
+        self.bits(m, self.offset)
+    }
+
+    #[inline]
+    fn _consume_remaining(&mut self, _g: Guard) -> Self::Buffer {
+        let start = self.offset;
+
+        self.offset = self.data.len() * 8;
+
+        self.bits(start, self.offset)
+    }
+
+    #[inline]
+    fn _mark(&self, _g: Guard) -> Self::Marker {
+        self.offset
+    }
+
+    #[inline]
+    fn _restore(mut self, _g: Guard, m: Self::Marker) -> Self {
+        self.offset = m;
+
+        self
+    }
+}
+
+/// A sequence of bits consumed from a `BitInput`.
+#[derive(Clone, Copy)]
+pub struct Bits<'a> {
+    data: &'a [u8],
+    start: usize,
+    end: usize,
+    order: BitOrder,
+}
+
+impl<'a> Bits<'a> {
+    /// Returns the bits as an unsigned integer, combined according to the bit
+    /// order of the input they were read from.
+    ///
+    /// # Panics
+    ///
+    /// Will panic if the buffer contains more than 64 bits.
+    pub fn value(&self) -> u64 {
+        assert!(self.len() <= 64, "Bits::value on more than 64 bits");
+
+        let mut v = 0;
+
+        for (n, o) in (self.start..self.end).enumerate() {
+            let b = u64::from(self.order.bit(self.data, o));
+
+            match self.order {
+                BitOrder::Msb => v = v << 1 | b,
+                BitOrder::Lsb => v |= b << n,
+            }
+        }
+
+        v
+    }
+}
+
+impl<'a> PartialEq for Bits<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && (0..self.len()).all(|n| {
+                self.order.bit(self.data, self.start + n)
+                    == other.order.bit(other.data, other.start + n)
+            })
+    }
+}
+
+impl<'a> Eq for Bits<'a> {}
+
+impl<'a> fmt::Debug for Bits<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Bits(")?;
+
+        for o in self.start..self.end {
+            write!(f, "{}", u8::from(self.order.bit(self.data, o)))?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+impl<'a> Buffer for Bits<'a> {
+    type Token = bool;
+
+    fn fold<B, F>(self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, Self::Token) -> B,
+    {
+        (self.start..self.end).fold(init, |b, o| f(b, self.order.bit(self.data, o)))
+    }
+
+    fn iterate<F>(&self, mut f: F)
+    where
+        F: FnMut(Self::Token),
+    {
+        for o in self.start..self.end {
+            f(self.order.bit(self.data, o))
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    #[cfg(feature = "std")]
+    fn to_vec(&self) -> Vec<Self::Token> {
+        (self.start..self.end)
+            .map(|o| self.order.bit(self.data, o))
+            .collect()
+    }
+
+    #[cfg(feature = "std")]
+    fn into_vec(self) -> Vec<Self::Token> {
+        self.to_vec()
+    }
+}
+
+/// Reads `n` bits as an unsigned integer, see `Bits::value`.
+///
+/// # Panics
+///
+/// Will panic if `n` is larger than 64.
+///
+/// ```
+/// use chomp1::primitives::IntoInner;
+/// use chomp1::types::bits::{take_bits, BitInput, BitOrder};
+///
+/// let i = BitInput::new(&[0b1011_0010], BitOrder::Msb);
+///
+/// assert_eq!(take_bits(i, 3).into_inner().1, Ok(0b101));
+///
+/// let i = BitInput::new(&[0b1011_0010], BitOrder::Lsb);
+///
+/// assert_eq!(take_bits(i, 3).into_inner().1, Ok(0b010));
+/// ```
+#[inline]
+pub fn take_bits(mut i: BitInput, n: usize) -> SimpleResult<BitInput, u64> {
+    assert!(n <= 64, "take_bits on more than 64 bits");
+
+    match i.consume(n) {
+        Some(b) => i.ret(b.value()),
+        None => i.err(Error::unexpected()),
+    }
+}
+
+/// Skips the bits remaining in the current byte, if any.
+#[inline]
+pub fn align<E>(mut i: BitInput) -> ParseResult<BitInput, (), E> {
+    i.offset = i.offset.div_ceil(8) * 8;
+
+    i.ret(())
+}
+
+/// Skips to the next byte boundary and runs the byte parser `f` on the remaining
+/// bytes, continuing with bit-level parsing after the bytes `f` consumed.
+///
+/// ```
+/// use chomp1::parsers::take;
+/// use chomp1::primitives::IntoInner;
+/// use chomp1::types::bits::{bytes, take_bits, BitInput, BitOrder};
+///
+/// let i = BitInput::new(&[0xf0, b'a', b'b', 0x80], BitOrder::Msb);
+/// // Bit and byte parsers have different error types
+/// let (i, r) = take_bits(i, 4)
+///     .map_err(|_| ())
+///     .bind(|i, _| bytes(i, |i| take(i, 2).map_err(|_| ())))
+///     .bind(|i, s| take_bits(i, 1).map(|b| (s, b)).map_err(|_| ()))
+///     .into_inner();
+///
+/// assert_eq!(r, Ok((&b"ab"[..], 1)));
+/// assert_eq!(i.bit_offset(), 25);
+/// ```
+#[inline]
+pub fn bytes<'a, T, E, F>(i: BitInput<'a>, f: F) -> ParseResult<BitInput<'a>, T, E>
+where
+    F: FnOnce(&'a [u8]) -> ParseResult<&'a [u8], T, E>,
+{
+    let start = i.offset.div_ceil(8);
+    let (rest, r) = f(&i.data[start..]).into_inner();
+    let i = BitInput {
+        offset: (i.data.len() - rest.len()) * 8,
+        ..i
+    };
+
+    match r {
+        Ok(t) => i.ret(t),
+        Err(e) => i.err(e),
+    }
+}
+
+/// Runs the bit parser `f` on the bits of the byte slice `i`, read in the given
+/// `order`. The remainder starts at the byte following the last bit consumed by
+/// `f`, partially consumed bytes are skipped.
+///
+/// ```
+/// use chomp1::parsers::token;
+/// use chomp1::primitives::IntoInner;
+/// use chomp1::types::bits::{bits, take_bits, BitOrder};
+///
+/// let r = bits(&b"\x80a"[..], BitOrder::Msb, |i| take_bits(i, 1).map_err(|_| ()))
+///     .bind(|i, b| token(i, b'a').map(|_| b).map_err(|_| ()));
+///
+/// assert_eq!(r.into_inner(), (&b""[..], Ok(1)));
+/// ```
+#[inline]
+pub fn bits<'a, T, E, F>(i: &'a [u8], order: BitOrder, f: F) -> ParseResult<&'a [u8], T, E>
+where
+    F: FnOnce(BitInput<'a>) -> ParseResult<BitInput<'a>, T, E>,
+{
+    let (b, r) = f(BitInput::new(i, order)).into_inner();
+    let rest = &i[b.offset.div_ceil(8)..];
+
+    match r {
+        Ok(t) => rest.ret(t),
+        Err(e) => rest.err(e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::combinators::{count, many};
+    use crate::parsers::{any, token};
+
+    #[test]
+    fn tokens() {
+        let mut i = BitInput::new(&[0b1010_0000, 0x01], BitOrder::Msb);
+
+        assert_eq!(i.remaining_bits(), 16);
+        assert_eq!(i.peek(), Some(true));
+        assert_eq!(i.pop(), Some(true));
+        assert_eq!(i.pop(), Some(false));
+
+        let m = i.mark();
+
+        assert_eq!(i.consume(2).map(|b| b.value()), Some(0b10));
+        assert_eq!(i.bit_offset(), 4);
+        assert!(!i.is_aligned());
+
+        let mut i = i.restore(m);
+
+        assert_eq!(i.bit_offset(), 2);
+        assert_eq!(i.consume_while(|b| !b).len(), 0);
+        assert_eq!(i.pop(), Some(true));
+        assert_eq!(i.consume_while(|b| !b).len(), 12);
+        assert_eq!(i.consume_remaining().value(), 1);
+        assert_eq!(i.peek(), None);
+        assert_eq!(i.consume(1), None);
+        assert_eq!(i.into_inner(), (&[0b1010_0000, 0x01][..], 16));
+
+        let mut i = BitInput::new(&[0b0000_0110], BitOrder::Lsb);
+
+        assert_eq!(i.pop(), Some(false));
+        assert_eq!(i.pop(), Some(true));
+        assert_eq!(i.pop(), Some(true));
+        assert_eq!(i.pop(), Some(false));
+    }
+
+    #[test]
+    fn parsers() {
+        let i = BitInput::new(&[0b1110_1100], BitOrder::Msb);
+        let (i, r) = many::<_, Vec<_>, _, _, _>(i, |i| token(i, true)).into_inner();
+
+        assert_eq!(r, Ok(vec![true, true, true]));
+        assert_eq!(i.bit_offset(), 3);
+
+        let (i, r) = count::<_, Vec<_>, _, _, _>(i, 2, any).into_inner();
+
+        assert_eq!(r, Ok(vec![false, true]));
+        assert_eq!(take_bits(i, 4).into_inner().1, Err(Error::unexpected()));
+        assert_eq!(take_bits(i, 3).into_inner().1, Ok(0b100));
+        assert_eq!(take_bits(i, 0).into_inner().1, Ok(0));
+    }
+
+    #[test]
+    fn values() {
+        let data = [0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0, 0xff];
+
+        let r = take_bits(BitInput::new(&data, BitOrder::Msb), 64).into_inner();
+        assert_eq!(r.1, Ok(0x1234_5678_9abc_def0));
+        assert_eq!(r.0.bit_offset(), 64);
+
+        let r = take_bits(BitInput::new(&data, BitOrder::Lsb), 64).into_inner();
+        assert_eq!(r.1, Ok(0xf0de_bc9a_7856_3412));
+
+        // LSB-first fields straddling a byte boundary, as in DEFLATE
+        let i = BitInput::new(&[0b1010_1101, 0b0000_0001], BitOrder::Lsb);
+        let r = take_bits(i, 3).bind(|i, a| take_bits(i, 6).map(|b| (a, b)));
+        assert_eq!(r.into_inner().1, Ok((0b101, 0b110101)));
+    }
+
+    #[test]
+    fn bits_eq() {
+        let mut a = BitInput::new(&[0b1010_0000], BitOrder::Msb);
+        let mut b = BitInput::new(&[0b0000_0101], BitOrder::Lsb);
+        let mut c = BitInput::new(&[0b0101_0000], BitOrder::Msb);
+
+        let (a, b, c) = (
+            a.consume(3).unwrap(),
+            b.consume(3).unwrap(),
+            c.consume(3).unwrap(),
+        );
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(format!("{:?}", a), "Bits(101)");
+        assert_eq!(a.to_vec(), vec![true, false, true]);
+    }
+
+    #[test]
+    fn alignment() {
+        let i = BitInput::new(&[0xff, b'a', b'b'], BitOrder::Msb);
+        let (i, r) = take_bits(i, 1).then(align::<Error<bool>>).into_inner();
+
+        assert_eq!(r, Ok(()));
+        assert_eq!(i.bit_offset(), 8);
+        assert_eq!(align::<()>(i).into_inner().0.bit_offset(), 8);
+
+        let (i, r) = bytes(i, |i| token(i, b'b')).into_inner();
+
+        assert_eq!(r, Err(Error::expected(b'b')));
+        assert_eq!(i.bit_offset(), 8);
+
+        let (i, r) = bytes(i, any).into_inner();
+
+        assert_eq!(r, Ok(b'a'));
+        assert!(i.is_aligned());
+        assert_eq!(i.remaining_bits(), 8);
+
+        // Partially consumed bytes are skipped when going back to bytes
+        let r = bits(&[0xff, b'a'][..], BitOrder::Msb, |i| take_bits(i, 9));
+        assert_eq!(r.into_inner(), (&b""[..], Ok(0x1fe)));
+        let r = bits(&[0xff, b'a'][..], BitOrder::Msb, |i| take_bits(i, 20));
+        assert_eq!(
+            r.into_inner(),
+            (&[0xff, b'a'][..], Err(Error::unexpected()))
+        );
+    }
+}
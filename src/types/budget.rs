@@ -0,0 +1,320 @@
+//! Module containing tools for bounding the work performed by a parser.
+//!
+//! Backtracking grammars can take a very long time on pathological input. The
+//! `Budget` wrapper charges every primitive operation performed on the input
+//! against a fixed amount of fuel. Once the fuel is used up the input behaves
+//! as if it had ended, which makes the parser fail quickly, and the budget is
+//! marked as exhausted.
+//!
+//! ```
+//! use chomp1::combinators::{many, or};
+//! use chomp1::parsers::{string, Error};
+//! use chomp1::prelude::{ParseResult, U8Input};
+//! use chomp1::types::budget::{run_with_budget, Budget, BudgetError};
+//!
+//! fn p<I: U8Input>(i: I) -> ParseResult<I, Vec<I::Buffer>, Error<u8>> {
+//!     many(i, |i| or(i, |i| string(i, b"aaab"), |i| string(i, b"a")))
+//! }
+//!
+//! let input = vec![b'a'; 10_000];
+//!
+//! assert_eq!(run_with_budget(&b"aaaba"[..], 100, p).1, Ok(vec![&b"aaab"[..], &b"a"[..]]));
+//! assert_eq!(run_with_budget(&input[..], 100, p).1, Err(BudgetError::Exhausted));
+//! ```
+
+use std::fmt;
+
+use crate::primitives::{Guard, IntoInner};
+use crate::types::{Input, ParseResult};
+
+/// Wrapper around an `Input` implementation which limits the number of
+/// primitive operations which can be performed on it.
+///
+/// Every token inspected or consumed costs one unit of fuel, as does every
+/// backtrack. Once the fuel runs out the input reports that no more tokens are
+/// available and `is_exhausted` returns true.
+#[derive(Debug)]
+pub struct Budget<I: Input> {
+    input: I,
+    fuel: usize,
+    exhausted: bool,
+}
+
+impl<I: Input> Budget<I> {
+    /// Wraps `i`, allowing at most `fuel` operations.
+    pub fn new(i: I, fuel: usize) -> Self {
+        Budget {
+            input: i,
+            fuel,
+            exhausted: false,
+        }
+    }
+
+    /// The remaining fuel.
+    pub fn remaining(&self) -> usize {
+        self.fuel
+    }
+
+    /// Returns true if the parser has tried to use more fuel than was
+    /// available.
+    ///
+    /// Any result obtained from a parser on an exhausted input is unreliable.
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// Attempts to use `n` units of fuel.
+    #[inline]
+    fn charge(&mut self, n: usize) -> bool {
+        if self.exhausted || n > self.fuel {
+            self.fuel = 0;
+            self.exhausted = true;
+
+            false
+        } else {
+            self.fuel -= n;
+
+            true
+        }
+    }
+
+    #[inline]
+    fn empty(&mut self, g: Guard) -> I::Buffer {
+        self.input
+            ._consume(g, 0)
+            .expect("consuming zero tokens always succeeds")
+    }
+}
+
+impl<I: Input> IntoInner for Budget<I> {
+    type Inner = (I, bool);
+
+    fn into_inner(self) -> Self::Inner {
+        (self.input, self.exhausted)
+    }
+}
+
+impl<I: Input> Input for Budget<I> {
+    type Buffer = I::Buffer;
+    type Marker = I::Marker;
+    type Token = I::Token;
+
+    #[inline]
+    fn _peek(&mut self, g: Guard) -> Option<Self::Token> {
+        if self.charge(1) {
+            self.input._peek(g)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn _pop(&mut self, g: Guard) -> Option<Self::Token> {
+        if self.charge(1) {
+            self.input._pop(g)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn _consume(&mut self, g: Guard, n: usize) -> Option<Self::Buffer> {
+        if self.charge(n) {
+            self.input._consume(g, n)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn _consume_while<F>(&mut self, g: Guard, mut f: F) -> Self::Buffer
+    where
+        F: FnMut(Self::Token) -> bool,
+    {
+        if self.exhausted {
+            return self.empty(g);
+        }
+
+        let mut fuel = self.fuel;
+        let mut exhausted = false;
+
+        let b = self.input._consume_while(g, |c| {
+            if fuel == 0 {
+                exhausted = true;
+
+                false
+            } else {
+                fuel -= 1;
+
+                f(c)
+            }
+        });
+
+        self.fuel = fuel;
+        self.exhausted = exhausted;
+
+        b
+    }
+
+    #[inline]
+    fn _consume_from(&mut self, g: Guard, m: Self::Marker) -> Self::Buffer {
+        self.input._consume_from(g, m)
+    }
+
+    #[inline]
+    fn _consume_remaining(&mut self, g: Guard) -> Self::Buffer {
+        self._consume_while(g, |_| true)
+    }
+
+    #[inline]
+    fn _mark(&self, g: Guard) -> Self::Marker {
+        self.input._mark(g)
+    }
+
+    #[inline]
+    fn _restore(mut self, g: Guard, m: Self::Marker) -> Self {
+        self.charge(1);
+
+        Budget {
+            input: self.input._restore(g, m),
+            fuel: self.fuel,
+            exhausted: self.exhausted,
+        }
+    }
+
+    #[cfg(feature = "trace")]
+    #[inline]
+    fn _trace(&self, g: Guard, name: &'static str, kind: crate::trace::EventKind) {
+        self.input._trace(g, name, kind)
+    }
+}
+
+/// Error returned by `run_with_budget`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BudgetError<E> {
+    /// The parser used up all of its fuel.
+    Exhausted,
+    /// The parser failed within its budget.
+    Parse(E),
+}
+
+impl<E: fmt::Display> fmt::Display for BudgetError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BudgetError::Exhausted => write!(f, "parse budget exhausted"),
+            BudgetError::Parse(ref e) => e.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error> std::error::Error for BudgetError<E> {}
+
+/// Runs the parser `f` on `i` with at most `fuel` units of fuel.
+///
+/// If the fuel runs out `BudgetError::Exhausted` is returned, even if the parser
+/// succeeded on the truncated input.
+pub fn run_with_budget<I, T, E, F>(i: I, fuel: usize, f: F) -> (I, Result<T, BudgetError<E>>)
+where
+    I: Input,
+    F: FnOnce(Budget<I>) -> ParseResult<Budget<I>, T, E>,
+{
+    let (b, r) = f(Budget::new(i, fuel)).into_inner();
+    let (i, exhausted) = b.into_inner();
+
+    if exhausted {
+        (i, Err(BudgetError::Exhausted))
+    } else {
+        (i, r.map_err(BudgetError::Parse))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{run_with_budget, Budget, BudgetError};
+    use crate::buffer::InputBuf;
+    use crate::combinators::{many, or};
+    use crate::parsers::{any, string, take_remainder, take_while, Error};
+    use crate::primitives::{IntoInner, Primitives};
+
+    #[test]
+    fn charges() {
+        let mut i = Budget::new(&b"abcdef"[..], 6);
+
+        assert_eq!(i.peek(), Some(b'a'));
+        assert_eq!(i.pop(), Some(b'a'));
+        assert_eq!(i.consume(2), Some(&b"bc"[..]));
+        assert_eq!(i.remaining(), 2);
+
+        let m = i.mark();
+        let mut i = i.restore(m);
+
+        assert_eq!(i.remaining(), 1);
+        assert!(!i.is_exhausted());
+        assert_eq!(i.consume(2), None);
+        assert!(i.is_exhausted());
+        assert_eq!(i.remaining(), 0);
+        assert_eq!(i.peek(), None);
+        assert_eq!(i.consume_while(|_| true), &b""[..]);
+        assert_eq!(i.into_inner(), (&b"def"[..], true));
+    }
+
+    #[test]
+    fn consume_while() {
+        let (i, r) = take_while(Budget::new(&b"aaaab"[..], 3), |c| c == b'a').into_inner();
+
+        assert_eq!(r, Ok(&b"aaa"[..]));
+        assert!(i.is_exhausted());
+
+        // Testing the token stopping the scan also costs fuel
+        let (i, r) = take_while(Budget::new(&b"aab"[..], 3), |c| c == b'a').into_inner();
+
+        assert_eq!(r, Ok(&b"aa"[..]));
+        assert_eq!(i.remaining(), 0);
+        assert!(!i.is_exhausted());
+
+        let (i, r) = take_remainder(Budget::new(&b"abc"[..], 3)).into_inner();
+
+        assert_eq!(r, Ok(&b"abc"[..]));
+        assert!(!i.is_exhausted());
+    }
+
+    #[test]
+    fn run() {
+        assert_eq!(
+            run_with_budget(&b"ab"[..], 10, |i| string(i, b"ab")).1,
+            Ok(&b"ab"[..])
+        );
+        assert_eq!(
+            run_with_budget(&b"ac"[..], 10, |i| string(i, b"ab")).1,
+            Err(BudgetError::Parse(Error::expected(b'b')))
+        );
+        assert_eq!(
+            run_with_budget(&b"ab"[..], 1, |i| string(i, b"ab")).1,
+            Err(BudgetError::Exhausted)
+        );
+
+        // Backtracking is charged, many stops early but the result is rejected
+        let r = run_with_budget(&[b'a'; 64][..], 64, |i| {
+            many::<_, Vec<_>, _, _, _>(i, |i| {
+                or(i, |i| string(i, b"ab"), |i| any(i).map(|_| &b""[..]))
+            })
+        });
+
+        assert_eq!(r.1, Err(BudgetError::Exhausted));
+        assert_eq!(
+            BudgetError::<Error<u8>>::Exhausted.to_string(),
+            "parse budget exhausted"
+        );
+    }
+
+    #[test]
+    fn input_buf() {
+        let (i, r) = any(Budget::new(InputBuf::new(b"a"), 0)).into_inner();
+        let (i, exhausted) = i.into_inner();
+
+        assert_eq!(r, Err(Error::unexpected()));
+        assert!(exhausted);
+        assert!(!i.is_incomplete());
+    }
+}
@@ -0,0 +1,182 @@
+//! Support for the `Bytes` type from the `bytes` crate.
+//!
+//! Buffers consumed from `Bytes` input are themselves `Bytes` sharing the same
+//! reference counted storage, which makes them cheap to clone and lets them
+//! outlive the buffer the data was read into.
+//!
+//! ```
+//! use bytes::Bytes;
+//! use chomp1::prelude::{take_while1, token, SimpleResult, U8Input};
+//! use chomp1::run_parser;
+//!
+//! fn word<I: U8Input>(i: I) -> SimpleResult<I, I::Buffer> {
+//!     take_while1(i, |c| c != b' ').bind(|i, w| token(i, b' ').map(|_| w))
+//! }
+//!
+//! let data = Bytes::from(b"hello world".to_vec());
+//! let (rest, r) = run_parser(data, word);
+//!
+//! assert_eq!(r, Ok(Bytes::from_static(b"hello")));
+//! assert_eq!(rest, Bytes::from_static(b"world"));
+//! ```
+
+use bytes::Bytes;
+
+use crate::primitives::Guard;
+use crate::search::Needle;
+use crate::types::{Buffer, Input};
+
+impl Input for Bytes {
+    type Buffer = Bytes;
+    type Marker = Bytes;
+    type Token = u8;
+
+    #[inline]
+    fn _peek(&mut self, _g: Guard) -> Option<Self::Token> {
+        self.first().cloned()
+    }
+
+    #[inline]
+    fn _pop(&mut self, _g: Guard) -> Option<Self::Token> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.split_to(1)[0])
+        }
+    }
+
+    #[inline]
+    fn _consume(&mut self, _g: Guard, n: usize) -> Option<Self::Buffer> {
+        if n > self.len() {
+            None
+        } else {
+            Some(self.split_to(n))
+        }
+    }
+
+    #[inline]
+    fn _consume_while<F>(&mut self, g: Guard, mut f: F) -> Self::Buffer
+    where
+        F: FnMut(Self::Token) -> bool,
+    {
+        match self.iter().position(|c| !f(*c)) {
+            Some(n) => self.split_to(n),
+            None => self._consume_remaining(g),
+        }
+    }
+
+    #[inline]
+    fn _consume_till<N>(&mut self, g: Guard, n: &N) -> Self::Buffer
+    where
+        N: Needle<Self::Token>,
+    {
+        match n.find(self) {
+            Some(n) => self.split_to(n),
+            None => self._consume_remaining(g),
+        }
+    }
+
+    #[inline]
+    fn _consume_from(&mut self, _g: Guard, m: Self::Marker) -> Self::Buffer {
+        m.slice(..m.len() - self.len())
+    }
+
+    #[inline]
+    fn _consume_remaining(&mut self, _g: Guard) -> Self::Buffer {
+        // Keep the position at the end instead of resetting to an empty Bytes
+        let n = self.len();
+
+        self.split_to(n)
+    }
+
+    #[inline]
+    fn _mark(&self, _g: Guard) -> Self::Marker {
+        self.clone()
+    }
+
+    #[inline]
+    fn _restore(self, _g: Guard, m: Self::Marker) -> Self {
+        m
+    }
+}
+
+impl Buffer for Bytes {
+    type Token = u8;
+
+    fn fold<B, F>(self, init: B, f: F) -> B
+    where
+        F: FnMut(B, Self::Token) -> B,
+    {
+        self.iter().cloned().fold(init, f)
+    }
+
+    fn iterate<F>(&self, mut f: F)
+    where
+        F: FnMut(Self::Token),
+    {
+        for c in self.iter().cloned() {
+            f(c)
+        }
+    }
+
+    fn len(&self) -> usize {
+        // Slice to reach inherent method to prevent infinite recursion
+        self[..].len()
+    }
+
+    #[cfg(feature = "std")]
+    fn to_vec(&self) -> Vec<Self::Token> {
+        self[..].to_vec()
+    }
+
+    #[cfg(feature = "std")]
+    fn into_vec(self) -> Vec<Self::Token> {
+        self.into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bytes::Bytes;
+
+    use crate::primitives::{IntoInner, Primitives};
+
+    #[test]
+    fn basic() {
+        use crate::ascii::decimal;
+
+        assert_eq!(
+            decimal(Bytes::from_static(b"123")).into_inner(),
+            (Bytes::new(), Ok(123i32))
+        );
+    }
+
+    #[test]
+    fn primitives() {
+        crate::types::test::run_primitives_test(Bytes::from_static(b"abc"), |x| x);
+    }
+
+    #[test]
+    fn shares_storage() {
+        use crate::search::take_till_bytes;
+
+        let data = Bytes::from(b"key=value;".to_vec());
+        let (mut i, r) = take_till_bytes(data.clone(), b'=').into_inner();
+        let key = r.unwrap();
+
+        assert_eq!(key, &b"key"[..]);
+        assert_eq!(key.as_ptr(), data.as_ptr());
+        assert_eq!(i.pop(), Some(b'='));
+
+        let m = i.mark();
+
+        assert_eq!(i.consume(5), Some(Bytes::from_static(b"value")));
+        assert_eq!(i.consume_from(m).as_ptr(), data[4..].as_ptr());
+
+        // Consumed buffers keep the storage alive
+        drop(data);
+        drop(i);
+
+        assert_eq!(key, &b"key"[..]);
+    }
+}
@@ -0,0 +1,444 @@
+//! Module containing tools for parsing data split into multiple chunks.
+//!
+//! Editors and network stacks often keep data as a list of chunks, like the
+//! leaves of a rope or a queue of received packets. `ChunkedInput` parses
+//! across the chunk boundaries without concatenating the chunks first, and the
+//! buffers it returns are `Chunks` views referring to the original slices.
+//!
+//! ```
+//! use chomp1::ascii::decimal;
+//! use chomp1::parsers::{string, token, Error};
+//! use chomp1::prelude::{ParseResult, U8Input};
+//! use chomp1::run_parser;
+//! use chomp1::types::chunked::ChunkedInput;
+//! use chomp1::types::Buffer;
+//!
+//! fn header<I: U8Input>(i: I) -> ParseResult<I, (I::Buffer, u32), Error<u8>> {
+//!     string(i, b"Content-Length")
+//!         .bind(|i, name| token(i, b':').then(decimal).map(|n| (name, n)))
+//! }
+//!
+//! let packets: Vec<&[u8]> = vec![b"Conte", b"nt-Len", b"gth:1", b"23\r\n"];
+//! let (i, r) = run_parser(ChunkedInput::new(&packets), header);
+//! let (name, n) = r.unwrap();
+//!
+//! assert_eq!(n, 123);
+//! assert_eq!(name.to_vec(), b"Content-Length");
+//! assert_eq!(name.slices().collect::<Vec<_>>(), [&b"Conte"[..], b"nt-Len", b"gth"]);
+//! assert_eq!(i.offset(), 18);
+//! ```
+
+use std::cmp;
+
+use crate::primitives::Guard;
+use crate::search::Needle;
+use crate::types::{Buffer, Input};
+
+/// A position in a `ChunkedInput`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Position {
+    /// Index of the current chunk, equal to the number of chunks at the end
+    chunk: usize,
+    /// Index within the current chunk, always less than the chunk length
+    /// unless at the end
+    index: usize,
+    /// Number of tokens before this position
+    offset: usize,
+}
+
+impl Position {
+    /// Index of the chunk containing the token at this position, equal to the
+    /// number of chunks at the end of the input.
+    pub fn chunk(&self) -> usize {
+        self.chunk
+    }
+
+    /// Number of tokens before this position in all the chunks.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// Moves `p` past any exhausted or empty chunks.
+#[inline]
+fn skip_empty<C: AsRef<[u8]>>(chunks: &[C], mut p: Position) -> Position {
+    while p.chunk < chunks.len() && p.index >= chunks[p.chunk].as_ref().len() {
+        p.chunk += 1;
+        p.index = 0;
+    }
+
+    p
+}
+
+/// Input over a sequence of chunks, parsed as if they were concatenated.
+///
+/// The chunks can be any type which can be borrowed as a byte slice, like
+/// `&[u8]`, `Vec<u8>` or `bytes::Bytes`.
+#[derive(Debug)]
+pub struct ChunkedInput<'a, C> {
+    chunks: &'a [C],
+    pos: Position,
+}
+
+// Derive would require C: Clone
+impl<'a, C> Clone for ChunkedInput<'a, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, C> Copy for ChunkedInput<'a, C> {}
+
+impl<'a, C: AsRef<[u8]>> ChunkedInput<'a, C> {
+    /// Creates an input over `chunks`.
+    pub fn new(chunks: &'a [C]) -> Self {
+        ChunkedInput {
+            chunks,
+            pos: skip_empty(chunks, Position::default()),
+        }
+    }
+
+    /// The current position.
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+
+    /// Number of tokens consumed.
+    pub fn offset(&self) -> usize {
+        self.pos.offset
+    }
+
+    /// The unconsumed part of the current chunk, empty at the end of the input.
+    #[inline]
+    fn current(&self) -> &'a [u8] {
+        match self.chunks.get(self.pos.chunk) {
+            Some(c) => &c.as_ref()[self.pos.index..],
+            None => &[],
+        }
+    }
+
+    /// Moves `n` tokens forward within the current chunk.
+    #[inline]
+    fn advance(&mut self, n: usize) {
+        self.pos.index += n;
+        self.pos.offset += n;
+        self.pos = skip_empty(self.chunks, self.pos);
+    }
+
+    #[inline]
+    fn buffer_from(&self, start: Position) -> Chunks<'a, C> {
+        Chunks {
+            chunks: self.chunks,
+            start,
+            end: self.pos,
+        }
+    }
+}
+
+impl<'a, C: AsRef<[u8]>> Input for ChunkedInput<'a, C> {
+    type Token = u8;
+    type Marker = Position;
+    type Buffer = Chunks<'a, C>;
+
+    #[inline]
+    fn _peek(&mut self, _g: Guard) -> Option<Self::Token> {
+        self.current().first().cloned()
+    }
+
+    #[inline]
+    fn _pop(&mut self, _g: Guard) -> Option<Self::Token> {
+        let c = self.current().first().cloned();
+
+        if c.is_some() {
+            self.advance(1);
+        }
+
+        c
+    }
+
+    #[inline]
+    fn _consume(&mut self, _g: Guard, mut n: usize) -> Option<Self::Buffer> {
+        let start = self.pos;
+
+        while n > 0 {
+            let len = self.current().len();
+
+            if len == 0 {
+                self.pos = start;
+
+                return None;
+            }
+
+            let k = cmp::min(len, n);
+
+            self.advance(k);
+
+            n -= k;
+        }
+
+        Some(self.buffer_from(start))
+    }
+
+    #[inline]
+    fn _consume_while<F>(&mut self, _g: Guard, mut f: F) -> Self::Buffer
+    where
+        F: FnMut(Self::Token) -> bool,
+    {
+        let start = self.pos;
+
+        loop {
+            let s = self.current();
+
+            if s.is_empty() {
+                break;
+            }
+
+            match s.iter().position(|c| !f(*c)) {
+                Some(n) => {
+                    self.advance(n);
+
+                    break;
+                }
+                None => self.advance(s.len()),
+            }
+        }
+
+        self.buffer_from(start)
+    }
+
+    #[inline]
+    fn _consume_till<N>(&mut self, _g: Guard, n: &N) -> Self::Buffer
+    where
+        N: Needle<Self::Token>,
+    {
+        let start = self.pos;
+
+        loop {
+            let s = self.current();
+
+            if s.is_empty() {
+                break;
+            }
+
+            match n.find(s) {
+                Some(k) => {
+                    self.advance(k);
+
+                    break;
+                }
+                None => self.advance(s.len()),
+            }
+        }
+
+        self.buffer_from(start)
+    }
+
+    #[inline]
+    fn _consume_from(&mut self, _g: Guard, m: Self::Marker) -> Self::Buffer {
+        self.buffer_from(m)
+    }
+
+    #[inline]
+    fn _consume_remaining(&mut self, _g: Guard) -> Self::Buffer {
+        let start = self.pos;
+
+        while !self.current().is_empty() {
+            let n = self.current().len();
+
+            self.advance(n);
+        }
+
+        self.buffer_from(start)
+    }
+
+    #[inline]
+    fn _mark(&self, _g: Guard) -> Self::Marker {
+        self.pos
+    }
+
+    #[inline]
+    fn _restore(mut self, _g: Guard, m: Self::Marker) -> Self {
+        self.pos = m;
+
+        self
+    }
+}
+
+/// A sequence of tokens consumed from `ChunkedInput`, referring to the slices
+/// of the chunks it spans.
+///
+/// Two buffers are equal if they contain the same tokens, no matter how they
+/// are split into chunks.
+#[derive(Debug)]
+pub struct Chunks<'a, C> {
+    chunks: &'a [C],
+    start: Position,
+    end: Position,
+}
+
+impl<'a, C> Clone for Chunks<'a, C> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, C> Copy for Chunks<'a, C> {}
+
+impl<'a, C: AsRef<[u8]>> Chunks<'a, C> {
+    /// The non-empty slices making up this buffer, in order.
+    pub fn slices(&self) -> impl Iterator<Item = &'a [u8]> + 'a {
+        let (chunks, start, end) = (self.chunks, self.start, self.end);
+        let last = cmp::min(end.chunk + 1, chunks.len());
+
+        (start.chunk..last)
+            .map(move |i| {
+                let s = chunks[i].as_ref();
+                let lo = if i == start.chunk { start.index } else { 0 };
+                let hi = if i == end.chunk { end.index } else { s.len() };
+
+                &s[lo..hi]
+            })
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Returns the buffer as a single slice if it does not span multiple
+    /// chunks.
+    pub fn as_slice(&self) -> Option<&'a [u8]> {
+        let mut s = self.slices();
+
+        match (s.next(), s.next()) {
+            (None, _) => Some(&[]),
+            (Some(s), None) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl<'a, C: AsRef<[u8]>> PartialEq for Chunks<'a, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.slices().flatten().eq(other.slices().flatten())
+    }
+}
+
+impl<'a, C: AsRef<[u8]>> Eq for Chunks<'a, C> {}
+
+impl<'a, C: AsRef<[u8]>> Buffer for Chunks<'a, C> {
+    type Token = u8;
+
+    fn fold<B, F>(self, init: B, f: F) -> B
+    where
+        F: FnMut(B, Self::Token) -> B,
+    {
+        self.slices().flatten().cloned().fold(init, f)
+    }
+
+    fn iterate<F>(&self, mut f: F)
+    where
+        F: FnMut(Self::Token),
+    {
+        for s in self.slices() {
+            for c in s {
+                f(*c)
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.end.offset - self.start.offset
+    }
+
+    #[cfg(feature = "std")]
+    fn to_vec(&self) -> Vec<Self::Token> {
+        let mut v = Vec::with_capacity(self.len());
+
+        for s in self.slices() {
+            v.extend_from_slice(s);
+        }
+
+        v
+    }
+
+    #[cfg(feature = "std")]
+    fn into_vec(self) -> Vec<Self::Token> {
+        self.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ascii::decimal;
+    use crate::combinators::sep_by;
+    use crate::parsers::{string, take_while1, token, Error};
+    use crate::primitives::{IntoInner, Primitives};
+    use crate::search::take_till_bytes;
+    use crate::types::test::run_primitives_test;
+
+    #[test]
+    fn primitives() {
+        let chunks: [&[u8]; 4] = [b"", b"a", b"", b"bc"];
+
+        run_primitives_test(ChunkedInput::new(&chunks), |c| c);
+        run_primitives_test(ChunkedInput::new(&[b"abc".to_vec()]), |c| c);
+    }
+
+    #[test]
+    fn across_chunks() {
+        let chunks = [b"12,3".to_vec(), b"4".to_vec(), vec![], b"5,67;".to_vec()];
+        let (mut i, r) =
+            sep_by::<_, Vec<u32>, _, _, _, _, _, _>(ChunkedInput::new(&chunks), decimal, |i| {
+                token(i, b',')
+            })
+            .into_inner();
+
+        assert_eq!(r, Ok(vec![12, 345, 67]));
+        assert_eq!(i.offset(), 9);
+        assert_eq!(i.position().chunk(), 3);
+        assert_eq!(i.pop(), Some(b';'));
+        assert_eq!(i.peek(), None);
+        assert_eq!(i.position().chunk(), 4);
+
+        let (i, r) = string(ChunkedInput::new(&chunks), b"12,345,x").into_inner();
+
+        assert_eq!(r, Err(Error::expected(b'x')));
+        assert_eq!(i.offset(), 7);
+
+        let mut i = ChunkedInput::new(&chunks);
+
+        assert_eq!(i.consume(11), None);
+        assert_eq!(i.offset(), 0);
+        assert_eq!(
+            i.consume(9).map(|b| b.to_vec()),
+            Some(b"12,345,67".to_vec())
+        );
+    }
+
+    #[test]
+    fn buffers() {
+        let a: [&[u8]; 3] = [b"hel", b"lo wo", b"rld"];
+        let b: [&[u8]; 1] = [b"hello world"];
+
+        let (_, x) = take_while1(ChunkedInput::new(&a), |c| c != b' ').into_inner();
+        let (_, y) = take_while1(ChunkedInput::new(&b), |c| c != b' ').into_inner();
+        let (x, y) = (x.unwrap(), y.unwrap());
+
+        assert_eq!(x, y);
+        assert_eq!(x.len(), 5);
+        assert_eq!(x.as_slice(), None);
+        assert_eq!(y.as_slice(), Some(&b"hello"[..]));
+        assert_eq!(x.slices().collect::<Vec<_>>(), [&b"hel"[..], b"lo"]);
+        assert_eq!(x.fold(0, |n, _| n + 1), 5);
+
+        let mut v = Vec::new();
+
+        y.iterate(|c| v.push(c));
+
+        assert_eq!(v, b"hello");
+
+        let (mut i, r) = take_till_bytes(ChunkedInput::new(&a), b'r').into_inner();
+
+        assert_eq!(r.map(|b| b.to_vec()), Ok(b"hello wo".to_vec()));
+        assert_eq!(i.consume_remaining().as_slice(), Some(&b"rld"[..]));
+        assert!(i.consume_remaining().is_empty());
+    }
+}
@@ -0,0 +1,515 @@
+//! Module containing tools for reparsing a document incrementally after edits.
+//!
+//! Parsers run on `Incremental` input, which keeps track of the offset of the
+//! current token and of how far ahead the parsers have looked. Rules wrapped
+//! in `memo` record the range they consumed and the range they examined in a
+//! `Memo` table. After the document is edited the table is told about the edit
+//! with `Memo::edit`, which discards the results that examined the edited
+//! range and moves the ones following it. Parsing the new document with the
+//! same table then reuses every result which is still valid.
+//!
+//! Offsets count tokens, which are bytes for `u8` input.
+//!
+//! Note: Requires the `std` feature.
+//!
+//! ```
+//! use chomp1::ascii::{decimal, skip_whitespace};
+//! use chomp1::combinators::sep_by;
+//! use chomp1::parsers::{token, Error};
+//! use chomp1::prelude::{ParseResult, U8Input};
+//! use chomp1::run_parser;
+//! use chomp1::types::incremental::{memo, Incremental, Memo};
+//!
+//! fn item<I: U8Input>(i: Incremental<I>, m: &Memo) -> ParseResult<Incremental<I>, u32, Error<u8>> {
+//!     memo(i, m, "item", |i| skip_whitespace(i).then(decimal))
+//! }
+//!
+//! fn list<I: U8Input>(i: Incremental<I>, m: &Memo) -> ParseResult<Incremental<I>, Vec<u32>, Error<u8>> {
+//!     sep_by(i, |i| item(i, m), |i| token(i, b','))
+//! }
+//!
+//! let mut m = Memo::new();
+//!
+//! let r = run_parser(Incremental::new(&b"1, 2, 3, 4"[..]), |i| list(i, &m)).1;
+//!
+//! assert_eq!(r, Ok(vec![1, 2, 3, 4]));
+//! assert_eq!(m.misses(), 4);
+//!
+//! // Replace "2" with "20"
+//! m.edit(3, 1, 2);
+//!
+//! let r = run_parser(Incremental::new(&b"1, 20, 3, 4"[..]), |i| list(i, &m)).1;
+//!
+//! assert_eq!(r, Ok(vec![1, 20, 3, 4]));
+//! // Only the edited item was parsed again
+//! assert_eq!(m.misses(), 5);
+//! assert_eq!(m.hits(), 3);
+//! ```
+
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::cmp;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::primitives::{Guard, IntoInner, Primitives};
+use crate::search::Needle;
+use crate::types::{Buffer, Input, ParseResult};
+
+/// Wrapper around an `Input` implementation tracking the offset of the current
+/// token and how far ahead parsers have examined the input.
+#[derive(Debug)]
+pub struct Incremental<I: Input> {
+    input: I,
+    /// Number of tokens consumed
+    offset: usize,
+    /// Offset one past the last token examined, is not reset by backtracking
+    examined: usize,
+}
+
+impl<I: Input> Incremental<I> {
+    /// Creates a new incremental input starting at offset zero.
+    pub fn new(i: I) -> Self {
+        Incremental {
+            input: i,
+            offset: 0,
+            examined: 0,
+        }
+    }
+
+    /// Number of tokens consumed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Offset one past the last token any parser has examined, including
+    /// tokens which were only peeked at or later backtracked over.
+    ///
+    /// Examining the end of the input counts as examining the token after it,
+    /// since appending to the input could change the result.
+    pub fn examined(&self) -> usize {
+        self.examined
+    }
+
+    #[inline]
+    fn examine(&mut self, end: usize) {
+        self.examined = cmp::max(self.examined, end);
+    }
+
+    /// Moves the offset past `b` and marks the token following it as examined.
+    #[inline]
+    fn advance_past(&mut self, b: &I::Buffer) {
+        self.offset += b.len();
+
+        let end = self.offset + 1;
+
+        self.examine(end);
+    }
+}
+
+impl<I: Input> IntoInner for Incremental<I> {
+    type Inner = I;
+
+    fn into_inner(self) -> Self::Inner {
+        self.input
+    }
+}
+
+impl<I: Input> Input for Incremental<I> {
+    type Buffer = I::Buffer;
+    type Marker = (usize, I::Marker);
+    type Token = I::Token;
+
+    #[inline]
+    fn _peek(&mut self, g: Guard) -> Option<Self::Token> {
+        let end = self.offset + 1;
+
+        self.examine(end);
+
+        self.input._peek(g)
+    }
+
+    #[inline]
+    fn _pop(&mut self, g: Guard) -> Option<Self::Token> {
+        let end = self.offset + 1;
+
+        self.examine(end);
+
+        self.input._pop(g).inspect(|_| self.offset += 1)
+    }
+
+    #[inline]
+    fn _consume(&mut self, g: Guard, n: usize) -> Option<Self::Buffer> {
+        // Failing depends on the input ending before offset + n
+        let end = self.offset + n;
+
+        self.examine(end);
+
+        self.input._consume(g, n).inspect(|_| self.offset += n)
+    }
+
+    #[inline]
+    fn _consume_while<F>(&mut self, g: Guard, f: F) -> Self::Buffer
+    where
+        F: FnMut(Self::Token) -> bool,
+    {
+        let b = self.input._consume_while(g, f);
+
+        self.advance_past(&b);
+
+        b
+    }
+
+    #[inline]
+    fn _consume_till<N>(&mut self, g: Guard, n: &N) -> Self::Buffer
+    where
+        N: Needle<Self::Token>,
+    {
+        let b = self.input._consume_till(g, n);
+
+        self.advance_past(&b);
+
+        b
+    }
+
+    #[inline]
+    fn _consume_prefix(&mut self, g: Guard, s: &[Self::Token]) -> Result<Self::Buffer, usize> {
+        match self.input._consume_prefix(g, s) {
+            Ok(b) => {
+                self.offset += s.len();
+
+                let end = self.offset;
+
+                self.examine(end);
+
+                Ok(b)
+            }
+            Err(n) => {
+                self.offset += n;
+
+                let end = self.offset + 1;
+
+                self.examine(end);
+
+                Err(n)
+            }
+        }
+    }
+
+    #[inline]
+    fn _consume_from(&mut self, g: Guard, m: Self::Marker) -> Self::Buffer {
+        self.input._consume_from(g, m.1)
+    }
+
+    #[inline]
+    fn _consume_remaining(&mut self, g: Guard) -> Self::Buffer {
+        let b = self.input._consume_remaining(g);
+
+        self.advance_past(&b);
+
+        b
+    }
+
+    #[inline]
+    fn _mark(&self, g: Guard) -> Self::Marker {
+        (self.offset, self.input._mark(g))
+    }
+
+    #[inline]
+    fn _restore(self, g: Guard, m: Self::Marker) -> Self {
+        Incremental {
+            input: self.input._restore(g, m.1),
+            offset: m.0,
+            examined: self.examined,
+        }
+    }
+
+    #[cfg(feature = "trace")]
+    #[inline]
+    fn _trace(&self, g: Guard, name: &'static str, kind: crate::trace::EventKind) {
+        self.input._trace(g, name, kind)
+    }
+}
+
+/// Successful result of a memoized rule.
+struct Entry {
+    /// Number of tokens consumed by the rule
+    consumed: usize,
+    /// Number of tokens examined by the rule, at least `consumed`
+    examined: usize,
+    value: Box<dyn Any>,
+}
+
+/// Table of the results of memoized rules, see `memo`.
+///
+/// Results are keyed by the name of the rule and the offset it started at, a
+/// single table can be shared by all the rules of a grammar as long as rules
+/// with the same name produce the same type.
+#[derive(Default)]
+pub struct Memo {
+    entries: RefCell<HashMap<(&'static str, usize), Entry>>,
+    hits: Cell<usize>,
+    misses: Cell<usize>,
+}
+
+impl Memo {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of results stored in the table.
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    /// Returns true if no results are stored in the table.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of times a stored result was reused.
+    pub fn hits(&self) -> usize {
+        self.hits.get()
+    }
+
+    /// Number of times a rule had to be run.
+    pub fn misses(&self) -> usize {
+        self.misses.get()
+    }
+
+    /// Removes all stored results.
+    pub fn clear(&mut self) {
+        self.entries.get_mut().clear()
+    }
+
+    /// Updates the table after `removed` tokens starting at offset `start` were
+    /// replaced by `inserted` tokens.
+    ///
+    /// Results which examined any of the removed tokens, or the position the
+    /// tokens were inserted at, are discarded. Results starting after the
+    /// edited range are moved to their new offsets.
+    pub fn edit(&mut self, start: usize, removed: usize, inserted: usize) {
+        let end = start + removed;
+        let entries = self.entries.get_mut();
+
+        *entries = entries
+            .drain()
+            .filter_map(|((rule, pos), e)| {
+                if pos >= end {
+                    Some(((rule, pos - removed + inserted), e))
+                } else if start < pos + e.examined {
+                    None
+                } else {
+                    Some(((rule, pos), e))
+                }
+            })
+            .collect();
+    }
+}
+
+impl fmt::Debug for Memo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Memo")
+            .field("len", &self.len())
+            .field("hits", &self.hits())
+            .field("misses", &self.misses())
+            .finish()
+    }
+}
+
+/// Runs the rule `f` under the name `name`, reusing the result stored in `m`
+/// for the current offset if there is one.
+///
+/// Successful results are stored together with the number of tokens the rule
+/// consumed and examined, failures are not stored. The rule must only depend on
+/// the input, reused results skip any side effects of `f`.
+///
+/// Note: Requires the `std` feature, `T` is cloned when a result is reused.
+pub fn memo<I: Input, T, E, F>(
+    mut i: Incremental<I>,
+    m: &Memo,
+    name: &'static str,
+    f: F,
+) -> ParseResult<Incremental<I>, T, E>
+where
+    T: Clone + 'static,
+    F: FnOnce(Incremental<I>) -> ParseResult<Incremental<I>, T, E>,
+{
+    let start = i.offset;
+    let stored = m.entries.borrow().get(&(name, start)).and_then(|e| {
+        e.value
+            .downcast_ref::<T>()
+            .map(|t| (e.consumed, e.examined, t.clone()))
+    });
+
+    if let Some((consumed, examined, t)) = stored {
+        // Fails if the table was not told about an edit shortening the input
+        if i.consume(consumed).is_some() {
+            m.hits.set(m.hits.get() + 1);
+
+            i.examine(start + examined);
+
+            return i.ret(t);
+        }
+    }
+
+    m.misses.set(m.misses.get() + 1);
+
+    // Measure what the rule examines on its own
+    let outer = i.examined;
+
+    i.examined = start;
+
+    let (mut b, r) = f(i).into_inner();
+    let examined = b.examined;
+
+    b.examined = cmp::max(outer, examined);
+
+    if let Ok(ref t) = r {
+        m.entries.borrow_mut().insert(
+            (name, start),
+            Entry {
+                consumed: b.offset - start,
+                examined: cmp::max(examined, b.offset) - start,
+                value: Box::new(t.clone()),
+            },
+        );
+    }
+
+    b.from_result(r)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ascii::skip_whitespace;
+    use crate::combinators::{many, or};
+    use crate::parsers::{string, take_while1, token, Error};
+    use crate::primitives::IntoInner;
+    use crate::types::U8Input;
+
+    #[test]
+    fn tracking() {
+        let mut i = Incremental::new(&b"abc def"[..]);
+
+        assert_eq!(i.peek(), Some(b'a'));
+        assert_eq!((i.offset(), i.examined()), (0, 1));
+        assert_eq!(i.consume_while(|c| c != b' '), &b"abc"[..]);
+        assert_eq!((i.offset(), i.examined()), (3, 4));
+
+        let m = i.mark();
+
+        assert_eq!(i.consume_prefix(b" dx"), Err(2));
+        assert_eq!((i.offset(), i.examined()), (5, 6));
+
+        // Backtracking does not forget what was examined
+        let mut i = i.restore(m);
+
+        assert_eq!((i.offset(), i.examined()), (3, 6));
+        assert_eq!(i.consume(5), None);
+        assert_eq!((i.offset(), i.examined()), (3, 8));
+        assert_eq!(i.consume_remaining(), &b" def"[..]);
+        assert_eq!((i.offset(), i.examined()), (7, 8));
+        assert_eq!(i.into_inner(), &b""[..]);
+    }
+
+    #[test]
+    fn edit() {
+        fn word<I: U8Input>(
+            i: Incremental<I>,
+            m: &Memo,
+        ) -> ParseResult<Incremental<I>, Vec<u8>, Error<u8>> {
+            memo(i, m, "word", |i| {
+                skip_whitespace(i)
+                    .then(|i| take_while1(i, |c| c != b' '))
+                    .map(|b| b.to_vec())
+            })
+        }
+
+        fn words(input: &[u8], m: &Memo) -> Result<Vec<Vec<u8>>, Error<u8>> {
+            many(Incremental::new(input), |i| word(i, m)).into_inner().1
+        }
+
+        let mut m = Memo::new();
+
+        assert_eq!(words(b"ab cd ef", &m).unwrap().len(), 3);
+        assert_eq!((m.hits(), m.misses(), m.len()), (0, 4, 3));
+
+        // Unchanged input reuses everything
+        assert_eq!(words(b"ab cd ef", &m).unwrap().len(), 3);
+        assert_eq!((m.hits(), m.misses(), m.len()), (3, 5, 3));
+
+        // Insertion right after "cd", which examined the space following it
+        m.edit(5, 0, 1);
+
+        assert_eq!(m.len(), 2);
+        assert_eq!(
+            words(b"ab cdx ef", &m),
+            Ok(vec![b"ab".to_vec(), b"cdx".to_vec(), b"ef".to_vec()])
+        );
+        assert_eq!((m.hits(), m.misses(), m.len()), (5, 7, 3));
+
+        // Removal of "ab " and the space the next word started with, moves the
+        // last word
+        m.edit(0, 3, 0);
+
+        assert_eq!(m.len(), 1);
+        assert_eq!(
+            words(b"cdx ef", &m),
+            Ok(vec![b"cdx".to_vec(), b"ef".to_vec()])
+        );
+        assert_eq!((m.hits(), m.misses(), m.len()), (6, 9, 2));
+
+        // Appending invalidates the word which ran into the end of the input
+        m.edit(6, 0, 2);
+
+        assert_eq!(m.len(), 1);
+        assert_eq!(
+            words(b"cdx efgh", &m),
+            Ok(vec![b"cdx".to_vec(), b"efgh".to_vec()])
+        );
+    }
+
+    #[test]
+    fn lookahead() {
+        // "a" followed by "b" is a different rule than "a" followed by "c", the
+        // rule consumes one token but examines two
+        fn rule<I: U8Input>(
+            i: Incremental<I>,
+            m: &Memo,
+        ) -> ParseResult<Incremental<I>, u8, Error<u8>> {
+            memo(i, m, "rule", |i| {
+                or(
+                    i,
+                    |i| {
+                        let p = i.mark();
+
+                        string(i, b"ab").map(|_| 1).bind(|i, n| {
+                            let i = i.restore(p);
+
+                            token(i, b'a').map(|_| n)
+                        })
+                    },
+                    |i| token(i, b'a').map(|_| 2),
+                )
+            })
+        }
+
+        let mut m = Memo::new();
+        let (i, r) = rule(Incremental::new(&b"ab"[..]), &m).into_inner();
+
+        assert_eq!(r, Ok(1));
+        assert_eq!((i.offset(), i.examined()), (1, 2));
+
+        m.edit(1, 1, 1);
+
+        assert!(m.is_empty());
+
+        let (i, r) = rule(Incremental::new(&b"ac"[..]), &m).into_inner();
+
+        assert_eq!(r, Ok(2));
+        assert_eq!((i.offset(), i.examined()), (1, 2));
+        assert_eq!((m.hits(), m.misses()), (0, 2));
+    }
+}
@@ -1,10 +1,20 @@
 //! Types which facillitates the chaining of parsers and their results.
 
+pub mod bits;
+pub mod budget;
+#[cfg(feature = "bytes")]
+pub mod bytes;
+pub mod chunked;
+#[cfg(feature = "std")]
+pub mod incremental;
 pub mod numbering;
+pub mod tokens;
+pub mod utf8;
 #[cfg(feature = "tendril")]
 pub mod tendril;
 
 use crate::primitives::{Guard, IntoInner};
+use crate::search::Needle;
 
 /// The buffers yielded parsers consuming a sequence of the input.
 ///
@@ -270,6 +280,40 @@ pub trait Input: Sized {
         self._consume_while(g, f);
     }
 
+    /// **Primitive:** See `Primitives::consume_till` for documentation.
+    #[inline]
+    #[doc(hidden)]
+    fn _consume_till<N>(&mut self, g: Guard, n: &N) -> Self::Buffer
+    where
+        N: Needle<Self::Token>,
+    {
+        self._consume_while(g, |c| !n.matches(c))
+    }
+
+    /// **Primitive:** See `Primitives::consume_prefix` for documentation.
+    #[inline]
+    #[doc(hidden)]
+    fn _consume_prefix(&mut self, g: Guard, s: &[Self::Token]) -> Result<Self::Buffer, usize> {
+        let mut n = 0;
+        let len = s.len();
+
+        let b = self._consume_while(g, |c| {
+            if n >= len || c != s[n] {
+                false
+            } else {
+                n += 1;
+
+                true
+            }
+        });
+
+        if n >= len {
+            Ok(b)
+        } else {
+            Err(n)
+        }
+    }
+
     /// **Primitive:** See `Primitives::mark` for documentation.
     #[doc(hidden)]
     fn _mark(&self, _: Guard) -> Self::Marker;
@@ -277,6 +321,12 @@ pub trait Input: Sized {
     /// **Primitive:** See `Primitives::restore` for documentation.
     #[doc(hidden)]
     fn _restore(self, _: Guard, _: Self::Marker) -> Self;
+
+    /// **Primitive:** See `Primitives::trace` for documentation.
+    #[cfg(feature = "trace")]
+    #[inline]
+    #[doc(hidden)]
+    fn _trace(&self, _: Guard, _: &'static str, _: crate::trace::EventKind) {}
 }
 
 impl<'a, I: Copy + PartialEq> Input for &'a [I] {
@@ -327,6 +377,39 @@ impl<'a, I: Copy + PartialEq> Input for &'a [I] {
         }
     }
 
+    #[inline]
+    fn _consume_till<N>(&mut self, g: Guard, n: &N) -> Self::Buffer
+    where
+        N: Needle<Self::Token>,
+    {
+        if let Some(n) = n.find(self) {
+            let b = &self[..n];
+
+            *self = &self[n..];
+
+            b
+        } else {
+            self._consume_remaining(g)
+        }
+    }
+
+    #[inline]
+    fn _consume_prefix(&mut self, _g: Guard, s: &[Self::Token]) -> Result<Self::Buffer, usize> {
+        if self.starts_with(s) {
+            let b = &self[..s.len()];
+
+            *self = &self[s.len()..];
+
+            Ok(b)
+        } else {
+            let n = self.iter().zip(s).take_while(|&(a, b)| a == b).count();
+
+            *self = &self[n..];
+
+            Err(n)
+        }
+    }
+
     #[inline]
     fn _consume_from(&mut self, _g: Guard, m: Self::Marker) -> Self::Buffer {
         &m[..m.len() - self.len()]
@@ -203,6 +203,12 @@ impl<I: Input, N: Numbering<Token = I::Token>> Input for InputPosition<I, N> {
             num: m.0,
         }
     }
+
+    #[cfg(feature = "trace")]
+    #[inline]
+    fn _trace(&self, g: Guard, name: &'static str, kind: crate::trace::EventKind) {
+        self.input._trace(g, name, kind)
+    }
 }
 
 #[cfg(test)]
@@ -1,15 +1,18 @@
 //! Support for the tendril type, this is probably a bad idea since it is
 //! actually not a rope and will probably cause excessive reallocations while
 //! parsing.
+//!
+//! Both `ByteTendril` and `StrTendril` are supported, yielding `u8` and `char`
+//! tokens respectively. Consumed buffers are subtendrils sharing storage with
+//! the input.
 
 use std::mem;
 
-use tendril::ByteTendril;
+use tendril::{ByteTendril, StrTendril};
 
 use crate::primitives::Guard;
 use crate::types::{Buffer, Input};
 
-// TODO: Impl for more than byte tendril
 impl Input for ByteTendril {
     type Buffer = ByteTendril;
     type Marker = ByteTendril;
@@ -121,9 +124,123 @@ impl Buffer for ByteTendril {
     }
 }
 
+impl Input for StrTendril {
+    type Buffer = StrTendril;
+    type Marker = StrTendril;
+    type Token = char;
+
+    #[inline]
+    fn _peek(&mut self, _g: Guard) -> Option<Self::Token> {
+        self.chars().next()
+    }
+
+    #[inline]
+    fn _pop(&mut self, _g: Guard) -> Option<Self::Token> {
+        self.pop_front_char()
+    }
+
+    #[inline]
+    fn _consume(&mut self, g: Guard, n: usize) -> Option<Self::Buffer> {
+        match self.char_indices().enumerate().take(n + 1).last() {
+            // num always equal to n if self contains more than n characters
+            Some((num, (pos, _))) if n == num => {
+                let b = self.subtendril(0, pos as u32);
+
+                self.pop_front(pos as u32);
+
+                Some(b)
+            }
+            // num always equal to n - 1 if self contains exactly n characters
+            Some((num, _)) if n == num + 1 => Some(self._consume_remaining(g)),
+            // Only consuming zero characters succeeds on an empty tendril
+            None if n == 0 => Some(self.subtendril(0, 0)),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn _consume_while<F>(&mut self, g: Guard, mut f: F) -> Self::Buffer
+    where
+        F: FnMut(Self::Token) -> bool,
+    {
+        match self.char_indices().find(|&(_, c)| !f(c)) {
+            Some((pos, _)) => {
+                let b = self.subtendril(0, pos as u32);
+
+                self.pop_front(pos as u32);
+
+                b
+            }
+            None => self._consume_remaining(g),
+        }
+    }
+
+    #[inline]
+    fn _consume_from(&mut self, _g: Guard, m: Self::Marker) -> Self::Buffer {
+        m.subtendril(0, m.len32() - self.len32())
+    }
+
+    #[inline]
+    fn _consume_remaining(&mut self, _g: Guard) -> Self::Buffer {
+        let b = self.subtendril(0, 0);
+
+        mem::replace(self, b)
+    }
+
+    #[inline]
+    fn _mark(&self, _g: Guard) -> Self::Marker {
+        self.clone()
+    }
+
+    #[inline]
+    fn _restore(self, _g: Guard, m: Self::Marker) -> Self {
+        m
+    }
+}
+
+impl Buffer for StrTendril {
+    type Token = char;
+
+    fn fold<B, F>(self, init: B, f: F) -> B
+    where
+        F: FnMut(B, Self::Token) -> B,
+    {
+        self.chars().fold(init, f)
+    }
+
+    fn iterate<F>(&self, mut f: F)
+    where
+        F: FnMut(Self::Token),
+    {
+        for c in self.chars() {
+            f(c)
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.chars().count()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len32() == 0
+    }
+
+    #[cfg(feature = "std")]
+    fn to_vec(&self) -> Vec<Self::Token> {
+        self.chars().collect()
+    }
+
+    #[cfg(feature = "std")]
+    fn into_vec(self) -> Vec<Self::Token> {
+        self.chars().collect()
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use tendril::Tendril;
+    use tendril::{ByteTendril, StrTendril};
+
+    use crate::types::Buffer;
 
     #[test]
     fn basic() {
@@ -131,13 +248,45 @@ mod test {
         use crate::primitives::IntoInner;
 
         assert_eq!(
-            decimal(Tendril::from_slice(&b"123"[..])).into_inner(),
-            (Tendril::from_slice(&b""[..]), Ok(123i32))
+            decimal(ByteTendril::from_slice(&b"123"[..])).into_inner(),
+            (ByteTendril::from_slice(&b""[..]), Ok(123i32))
         );
     }
 
     #[test]
     fn primitives() {
-        crate::types::test::run_primitives_test(Tendril::from_slice(&b"abc"[..]), |x| x);
+        crate::types::test::run_primitives_test(ByteTendril::from_slice(&b"abc"[..]), |x| x);
+        crate::types::test::run_primitives_test(StrTendril::from_slice("abc"), |c| c as char);
+    }
+
+    #[test]
+    fn str_tendril() {
+        use crate::parsers::{string, take_while};
+        use crate::primitives::{IntoInner, Primitives};
+
+        let t = StrTendril::from_slice("ünïcödé text, longer than the inline limit");
+        let (mut i, r) = take_while(t.clone(), |c| c != ' ').into_inner();
+        let word = r.unwrap();
+
+        assert_eq!(&*word, "ünïcödé");
+        assert_eq!(Buffer::len(&word), 7);
+        // Buffers are subtendrils of the input
+        assert!(word.is_shared_with(&t));
+
+        assert_eq!(i.pop(), Some(' '));
+        assert_eq!(i.consume(4).as_deref(), Some("text"));
+
+        let m = i.mark();
+
+        let (i, r) = string(i, &[',', ' ', 'l']).into_inner();
+
+        assert_eq!(r.map(|b| b.to_vec()), Ok(vec![',', ' ', 'l']));
+
+        let mut i = i.restore(m);
+
+        assert_eq!(i.consume(100), None);
+        assert_eq!(&*i.consume_remaining(), ", longer than the inline limit");
+        assert_eq!(i.consume(0).as_deref(), Some(""));
+        assert_eq!(i.pop(), None);
     }
 }
@@ -0,0 +1,469 @@
+//! Module containing tools for parsing the output of a separate lexer.
+//!
+//! `Input` for slices requires `Copy` tokens, which rules out lexer tokens
+//! carrying spans, owned text or other rich data. `Tokens` wraps a slice of
+//! such tokens and yields references to them instead, so the generic parsers
+//! like `any` and `satisfy` work on it as well. Tokens implementing `Lexeme`
+//! can in addition be matched by kind through `token_kind` and `satisfy_map`,
+//! which fail with a `TokenError` carrying the index and span of the offending
+//! token.
+//!
+//! ```
+//! use std::ops::Range;
+//!
+//! use chomp1::combinators::sep_by1;
+//! use chomp1::types::tokens::{satisfy_map, token_kind, Lexeme, TokenError, TokenResult, Tokens};
+//! use chomp1::run_parser;
+//!
+//! #[derive(Clone, Copy, Debug, PartialEq)]
+//! enum Kind {
+//!     Ident,
+//!     Comma,
+//! }
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct Token {
+//!     kind: Kind,
+//!     text: String,
+//!     span: Range<usize>,
+//! }
+//!
+//! impl Lexeme for Token {
+//!     type Kind = Kind;
+//!     type Span = Range<usize>;
+//!
+//!     fn kind(&self) -> Kind {
+//!         self.kind
+//!     }
+//!
+//!     fn span(&self) -> Range<usize> {
+//!         self.span.clone()
+//!     }
+//! }
+//!
+//! fn ident<'a>(i: Tokens<'a, Token>) -> TokenResult<'a, Token, &'a str> {
+//!     satisfy_map(i, |t| if t.kind == Kind::Ident { Some(&t.text[..]) } else { None })
+//! }
+//!
+//! let t = |kind, text: &str, start| Token { kind, text: text.to_owned(), span: start..start + text.len() };
+//! let input = [t(Kind::Ident, "a", 0), t(Kind::Comma, ",", 1), t(Kind::Ident, "b", 2), t(Kind::Comma, ",", 3)];
+//!
+//! let (i, r) = run_parser(Tokens::new(&input), |i| {
+//!     sep_by1::<_, Vec<_>, _, _, _, _, _, _>(i, ident, |i| token_kind(i, Kind::Comma))
+//! });
+//!
+//! assert_eq!(r, Ok(vec!["a", "b"]));
+//! assert_eq!(i.index(), 3);
+//!
+//! let (_, r) = run_parser(i, |i| token_kind(i, Kind::Comma).then(ident));
+//!
+//! assert_eq!(r, Err(TokenError::new(4, None, None)));
+//! assert_eq!(r.unwrap_err().to_string(), "unexpected token at end of input");
+//! ```
+
+use std::fmt;
+
+use crate::primitives::{Guard, IntoInner, Primitives};
+use crate::types::{Buffer, Input, ParseResult};
+
+/// A token produced by a lexer, which has a kind and a location in the source.
+pub trait Lexeme: PartialEq {
+    /// The type of the kind of the token, usually a fieldless enum.
+    type Kind: Copy + PartialEq + fmt::Debug;
+    /// The location of the token in the source, like a `Range<usize>`.
+    type Span: Clone + fmt::Debug;
+
+    /// The kind of this token.
+    fn kind(&self) -> Self::Kind;
+
+    /// The location of this token in the source.
+    fn span(&self) -> Self::Span;
+}
+
+/// Input over a slice of tokens which yields references to the tokens.
+#[derive(Debug)]
+pub struct Tokens<'a, T> {
+    tokens: &'a [T],
+    index: usize,
+}
+
+// Derive would require T: Clone
+impl<'a, T> Clone for Tokens<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for Tokens<'a, T> {}
+
+impl<'a, T> Tokens<'a, T> {
+    /// Creates an input over `tokens`.
+    pub fn new(tokens: &'a [T]) -> Self {
+        Tokens { tokens, index: 0 }
+    }
+
+    /// Index of the next token in the original slice.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The tokens which have not been consumed yet.
+    pub fn remaining(&self) -> &'a [T] {
+        &self.tokens[self.index..]
+    }
+}
+
+impl<'a, T> IntoInner for Tokens<'a, T> {
+    /// The remaining tokens and the index of the first of them.
+    type Inner = (&'a [T], usize);
+
+    fn into_inner(self) -> Self::Inner {
+        (self.remaining(), self.index)
+    }
+}
+
+impl<'a, T: PartialEq> Input for Tokens<'a, T> {
+    type Token = &'a T;
+    type Marker = usize;
+    type Buffer = TokenSlice<'a, T>;
+
+    #[inline]
+    fn _peek(&mut self, _g: Guard) -> Option<Self::Token> {
+        self.tokens.get(self.index)
+    }
+
+    #[inline]
+    fn _pop(&mut self, _g: Guard) -> Option<Self::Token> {
+        self.tokens.get(self.index).inspect(|_| self.index += 1)
+    }
+
+    #[inline]
+    fn _consume(&mut self, _g: Guard, n: usize) -> Option<Self::Buffer> {
+        if n > self.tokens.len() - self.index {
+            None
+        } else {
+            self.index += n;
+
+            Some(TokenSlice(&self.tokens[self.index - n..self.index]))
+        }
+    }
+
+    #[inline]
+    fn _consume_while<F>(&mut self, g: Guard, mut f: F) -> Self::Buffer
+    where
+        F: FnMut(Self::Token) -> bool,
+    {
+        match self.remaining().iter().position(|t| !f(t)) {
+            Some(n) => self
+                ._consume(g, n)
+                .expect("position is within the remaining tokens"),
+            None => self._consume_remaining(g),
+        }
+    }
+
+    #[inline]
+    fn _consume_from(&mut self, _g: Guard, m: Self::Marker) -> Self::Buffer {
+        TokenSlice(&self.tokens[m..self.index])
+    }
+
+    #[inline]
+    fn _consume_remaining(&mut self, _g: Guard) -> Self::Buffer {
+        let b = TokenSlice(self.remaining());
+
+        self.index = self.tokens.len();
+
+        b
+    }
+
+    #[inline]
+    fn _mark(&self, _g: Guard) -> Self::Marker {
+        self.index
+    }
+
+    #[inline]
+    fn _restore(mut self, _g: Guard, m: Self::Marker) -> Self {
+        self.index = m;
+
+        self
+    }
+}
+
+/// A sequence of tokens consumed from `Tokens`.
+#[derive(Debug, Eq, PartialEq)]
+pub struct TokenSlice<'a, T>(pub &'a [T]);
+
+impl<'a, T> Clone for TokenSlice<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for TokenSlice<'a, T> {}
+
+impl<'a, T: PartialEq> Buffer for TokenSlice<'a, T> {
+    type Token = &'a T;
+
+    fn fold<B, F>(self, init: B, f: F) -> B
+    where
+        F: FnMut(B, Self::Token) -> B,
+    {
+        self.0.iter().fold(init, f)
+    }
+
+    fn iterate<F>(&self, mut f: F)
+    where
+        F: FnMut(Self::Token),
+    {
+        for t in self.0 {
+            f(t)
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[cfg(feature = "std")]
+    fn to_vec(&self) -> Vec<Self::Token> {
+        self.0.iter().collect()
+    }
+
+    #[cfg(feature = "std")]
+    fn into_vec(self) -> Vec<Self::Token> {
+        self.to_vec()
+    }
+}
+
+/// Error produced by `token_kind` and `satisfy_map`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenError<K, S> {
+    index: usize,
+    expected: Option<K>,
+    found: Option<S>,
+}
+
+impl<K, S> TokenError<K, S> {
+    /// Creates a new error at the token `index`.
+    pub fn new(index: usize, expected: Option<K>, found: Option<S>) -> Self {
+        TokenError {
+            index,
+            expected,
+            found,
+        }
+    }
+
+    /// Index of the offending token.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The kind of token which was expected, `None` if the token did not
+    /// satisfy a predicate.
+    pub fn expected(&self) -> Option<&K> {
+        self.expected.as_ref()
+    }
+
+    /// Span of the offending token, `None` at the end of the input.
+    pub fn found(&self) -> Option<&S> {
+        self.found.as_ref()
+    }
+}
+
+impl<K: fmt::Debug, S: fmt::Debug> fmt::Display for TokenError<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.expected {
+            Some(ref k) => write!(f, "expected {:?}", k)?,
+            None => write!(f, "unexpected token")?,
+        }
+
+        match self.found {
+            Some(ref s) => write!(f, " at {:?}", s),
+            None => write!(f, " at end of input"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: fmt::Debug, S: fmt::Debug> std::error::Error for TokenError<K, S> {}
+
+/// Result returned by the parsers in this module.
+pub type TokenResult<'a, T, U> =
+    ParseResult<Tokens<'a, T>, U, TokenError<<T as Lexeme>::Kind, <T as Lexeme>::Span>>;
+
+/// Matches a single token of the given kind, returning a reference to it.
+///
+/// If the token is of another kind the error contains `kind` and the span of
+/// the token found.
+#[inline]
+pub fn token_kind<T: Lexeme>(mut i: Tokens<'_, T>, kind: T::Kind) -> TokenResult<'_, T, &T> {
+    let index = i.index;
+
+    match i.peek() {
+        Some(t) if t.kind() == kind => {
+            i.pop();
+
+            i.ret(t)
+        }
+        t => i.err(TokenError::new(index, Some(kind), t.map(Lexeme::span))),
+    }
+}
+
+/// Matches a single token for which `f` returns `Some`, returning the contained
+/// value.
+///
+/// Useful to match a kind of token and extract its data at the same time.
+#[inline]
+pub fn satisfy_map<'a, T: Lexeme, U, F>(mut i: Tokens<'a, T>, f: F) -> TokenResult<'a, T, U>
+where
+    F: FnOnce(&'a T) -> Option<U>,
+{
+    let index = i.index;
+
+    match i.peek() {
+        Some(t) => match f(t) {
+            Some(u) => {
+                i.pop();
+
+                i.ret(u)
+            }
+            None => i.err(TokenError::new(index, None, Some(t.span()))),
+        },
+        None => i.err(TokenError::new(index, None, None)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Range;
+
+    use super::*;
+    use crate::combinators::{many, or};
+    use crate::parsers::{any, satisfy, Error};
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Kind {
+        Let,
+        Ident,
+        Eq,
+        Number,
+    }
+
+    // Not Copy
+    #[derive(Debug, PartialEq)]
+    struct Tok {
+        kind: Kind,
+        text: String,
+        span: Range<usize>,
+    }
+
+    impl Lexeme for Tok {
+        type Kind = Kind;
+        type Span = Range<usize>;
+
+        fn kind(&self) -> Kind {
+            self.kind
+        }
+
+        fn span(&self) -> Range<usize> {
+            self.span.clone()
+        }
+    }
+
+    fn lex(s: &str) -> Vec<Tok> {
+        let mut start = 0;
+
+        s.split(' ')
+            .map(|w| {
+                let kind = match w {
+                    "let" => Kind::Let,
+                    "=" => Kind::Eq,
+                    _ if w.bytes().all(|c| c.is_ascii_digit()) => Kind::Number,
+                    _ => Kind::Ident,
+                };
+                let t = Tok {
+                    kind,
+                    text: w.to_owned(),
+                    span: start..start + w.len(),
+                };
+
+                start += w.len() + 1;
+
+                t
+            })
+            .collect()
+    }
+
+    fn number<'a>(i: Tokens<'a, Tok>) -> TokenResult<'a, Tok, u64> {
+        satisfy_map(i, |t| match t.kind {
+            Kind::Number => t.text.parse().ok(),
+            _ => None,
+        })
+    }
+
+    fn binding<'a>(i: Tokens<'a, Tok>) -> TokenResult<'a, Tok, (&'a str, u64)> {
+        token_kind(i, Kind::Let)
+            .then(|i| token_kind(i, Kind::Ident))
+            .bind(|i, name| {
+                token_kind(i, Kind::Eq)
+                    .then(number)
+                    .map(|n| (&name.text[..], n))
+            })
+    }
+
+    #[test]
+    fn bindings() {
+        let tokens = lex("let a = 1 let b = 22");
+        let (i, r) = many::<_, Vec<_>, _, _, _>(Tokens::new(&tokens), binding).into_inner();
+
+        assert_eq!(r, Ok(vec![("a", 1), ("b", 22)]));
+        assert_eq!(i.remaining(), &[][..]);
+
+        let tokens = lex("let a = b");
+        let (i, r) = binding(Tokens::new(&tokens)).into_inner();
+
+        assert_eq!(r, Err(TokenError::new(3, None, Some(8..9))));
+        assert_eq!(i.index(), 3);
+        assert_eq!(r.unwrap_err().to_string(), "unexpected token at 8..9");
+
+        let tokens = lex("let =");
+        let r = binding(Tokens::new(&tokens)).into_inner().1.unwrap_err();
+
+        assert_eq!(r.index(), 1);
+        assert_eq!(r.expected(), Some(&Kind::Ident));
+        assert_eq!(r.found(), Some(&(4..5)));
+        assert_eq!(r.to_string(), "expected Ident at 4..5");
+
+        let tokens = lex("let a");
+        let r = binding(Tokens::new(&tokens)).into_inner().1.unwrap_err();
+
+        assert_eq!(r, TokenError::new(2, Some(Kind::Eq), None));
+        assert_eq!(r.to_string(), "expected Eq at end of input");
+    }
+
+    #[test]
+    fn generic_parsers() {
+        let tokens = lex("a b = 3");
+        let i = Tokens::new(&tokens);
+
+        let (i, r) = any(i).into_inner();
+        assert_eq!(r.map(|t| &t.text[..]), Ok("a"));
+
+        let r: ParseResult<_, _, Error<&Tok>> = or(
+            i,
+            |i| satisfy(i, |t: &Tok| t.kind == Kind::Eq),
+            |i| satisfy(i, |t: &Tok| t.kind == Kind::Ident),
+        );
+        let (mut i, r) = r.into_inner();
+        assert_eq!(r.map(|t| t.span.clone()), Ok(2..3));
+
+        let m = i.mark();
+        let b = i.consume_while(|t| t.kind != Kind::Number);
+
+        assert_eq!(b.len(), 1);
+        assert_eq!(b.0[0].text, "=");
+        assert_eq!(i.consume_from(m), b);
+        assert_eq!(i.consume(2), None);
+        assert_eq!(i.consume_remaining().to_vec(), vec![&tokens[3]]);
+        assert_eq!(i.into_inner(), (&[][..], 4));
+    }
+}
@@ -0,0 +1,626 @@
+//! Module containing tools for parsing UTF-8 encoded byte input as characters.
+//!
+//! `&str` input needs the whole buffer to be validated up front, which is not
+//! possible when the data arrives in pieces through `buffer::Source`. The
+//! `Utf8Input` wrapper instead decodes `char` tokens lazily from any `U8Input`.
+//! Invalid byte sequences make the input behave as if it had ended, and are
+//! reported as a `Utf8Error` by `utf8` if the parser fails.
+//!
+//! A code point split across the end of a streaming buffer is not invalid, the
+//! underlying `InputBuf` is then marked as incomplete as usual so that the
+//! stream requests more data.
+//!
+//! ```
+//! use chomp1::parsers::{self, take_while1};
+//! use chomp1::prelude::{parse_only, ParseResult, U8Input};
+//! use chomp1::types::Buffer;
+//! use chomp1::types::utf8::{utf8, Utf8Error};
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Error {
+//!     Syntax,
+//!     Utf8(Utf8Error),
+//! }
+//!
+//! impl From<parsers::Error<char>> for Error {
+//!     fn from(_: parsers::Error<char>) -> Self {
+//!         Error::Syntax
+//!     }
+//! }
+//!
+//! impl From<Utf8Error> for Error {
+//!     fn from(e: Utf8Error) -> Self {
+//!         Error::Utf8(e)
+//!     }
+//! }
+//!
+//! fn word<I: U8Input>(i: I) -> ParseResult<I, String, Error> {
+//!     utf8(i, |i| {
+//!         take_while1(i, char::is_alphabetic)
+//!             .map(|b| b.fold(String::new(), |mut s, c| { s.push(c); s }))
+//!             .map_err(Error::from)
+//!     })
+//! }
+//!
+//! assert_eq!(parse_only(word, "grüße!".as_bytes()), Ok("grüße".to_owned()));
+//! assert_eq!(parse_only(word, b"!"), Err((&b"!"[..], Error::Syntax)));
+//!
+//! match parse_only(word, b"\xc3\x28") {
+//!     Err((_, Error::Utf8(e))) => assert_eq!(e.error_len(), Some(1)),
+//!     r => panic!("{:?}", r),
+//! }
+//! ```
+
+use std::fmt;
+
+use crate::primitives::{Guard, IntoInner, Primitives};
+use crate::types::{Buffer, Input, ParseResult, U8Input};
+
+/// Error produced when the input does not contain valid UTF-8.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Utf8Error {
+    error_len: Option<u8>,
+}
+
+impl Utf8Error {
+    /// The length of the invalid byte sequence, `None` if the input ended in
+    /// the middle of a code point.
+    pub fn error_len(&self) -> Option<usize> {
+        self.error_len.map(usize::from)
+    }
+
+    /// Returns true if the input ended in the middle of a code point.
+    ///
+    /// When parsing a stream more data might complete the code point.
+    pub fn is_incomplete(&self) -> bool {
+        self.error_len.is_none()
+    }
+}
+
+impl fmt::Display for Utf8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.error_len {
+            Some(n) => write!(f, "invalid utf-8 sequence of {} bytes", n),
+            None => write!(f, "incomplete utf-8 byte sequence"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Utf8Error {}
+
+/// Decodes a code point from the start of `i`, consuming its bytes.
+///
+/// `Ok(None)` if `i` is empty. On error an unspecified number of bytes has
+/// been consumed.
+#[inline]
+fn decode<I: U8Input>(i: &mut I) -> Result<Option<char>, Utf8Error> {
+    let b = match i.pop() {
+        Some(b) => b,
+        None => return Ok(None),
+    };
+
+    // Ranges from the well-formed byte sequences table in the Unicode standard,
+    // the first continuation byte excludes overlong forms and surrogates
+    let (n, lo, hi, c) = match b {
+        0x00..=0x7f => return Ok(Some(char::from(b))),
+        0xc2..=0xdf => (1, 0x80, 0xbf, b & 0x1f),
+        0xe0 => (2, 0xa0, 0xbf, b & 0x0f),
+        0xe1..=0xec | 0xee..=0xef => (2, 0x80, 0xbf, b & 0x0f),
+        0xed => (2, 0x80, 0x9f, b & 0x0f),
+        0xf0 => (3, 0x90, 0xbf, b & 0x07),
+        0xf1..=0xf3 => (3, 0x80, 0xbf, b & 0x07),
+        0xf4 => (3, 0x80, 0x8f, b & 0x07),
+        _ => return Err(Utf8Error { error_len: Some(1) }),
+    };
+    let mut c = u32::from(c);
+
+    for k in 0..n {
+        let range = if k == 0 { lo..=hi } else { 0x80..=0xbf };
+
+        match i.pop() {
+            Some(b) if range.contains(&b) => c = (c << 6) | u32::from(b & 0x3f),
+            Some(_) => {
+                return Err(Utf8Error {
+                    error_len: Some(k + 1),
+                })
+            }
+            None => return Err(Utf8Error { error_len: None }),
+        }
+    }
+
+    Ok(char::from_u32(c))
+}
+
+/// Wrapper around a `U8Input` which decodes UTF-8 `char` tokens from it.
+///
+/// Once an invalid or truncated byte sequence has been encountered the input
+/// reports that no more tokens are available at that position and `error`
+/// returns the error. Like the incomplete state of `InputBuf` the error is
+/// kept even if the parser backtracks. Note that this means parsers like `eof`
+/// succeed in front of an invalid sequence, it is left in the remainder.
+#[derive(Debug)]
+pub struct Utf8Input<I: U8Input> {
+    // Only None while restoring, see `rewind`
+    input: Option<I>,
+    error: Option<Utf8Error>,
+}
+
+impl<I: U8Input> Utf8Input<I> {
+    /// Wraps `i`, decoding characters from its bytes.
+    pub fn new(i: I) -> Self {
+        Utf8Input {
+            input: Some(i),
+            error: None,
+        }
+    }
+
+    /// The last decoding error encountered, if any.
+    pub fn error(&self) -> Option<Utf8Error> {
+        self.error
+    }
+
+    #[inline]
+    fn input(&mut self) -> &mut I {
+        self.input.as_mut().expect("Utf8Input used after a panic")
+    }
+
+    /// Restores the wrapped input to `m`.
+    #[inline]
+    fn rewind(&mut self, m: I::Marker) {
+        let i = self.input.take().expect("Utf8Input used after a panic");
+
+        self.input = Some(i.restore(m));
+    }
+
+    /// Decodes the next character, consuming it only if `consume` is true.
+    #[inline]
+    fn next(&mut self, consume: bool) -> Option<char> {
+        // ASCII does not require backtracking
+        match self.input().peek() {
+            Some(b) if b < 0x80 => {
+                if consume {
+                    self.input().pop();
+                }
+
+                return Some(char::from(b));
+            }
+            None => return None,
+            Some(_) => {}
+        }
+
+        let m = self.input().mark();
+
+        match decode(self.input()) {
+            Ok(c) => {
+                if !consume {
+                    self.rewind(m);
+                }
+
+                c
+            }
+            Err(e) => {
+                self.rewind(m);
+                self.error = Some(e);
+
+                None
+            }
+        }
+    }
+}
+
+impl<I: U8Input> IntoInner for Utf8Input<I> {
+    /// The wrapped input and the last decoding error.
+    type Inner = (I, Option<Utf8Error>);
+
+    fn into_inner(self) -> Self::Inner {
+        (
+            self.input.expect("Utf8Input used after a panic"),
+            self.error,
+        )
+    }
+}
+
+impl<I: U8Input> Input for Utf8Input<I> {
+    type Token = char;
+    type Marker = I::Marker;
+    type Buffer = Utf8Buffer<I::Buffer>;
+
+    #[inline]
+    fn _peek(&mut self, _g: Guard) -> Option<Self::Token> {
+        self.next(false)
+    }
+
+    #[inline]
+    fn _pop(&mut self, _g: Guard) -> Option<Self::Token> {
+        self.next(true)
+    }
+
+    #[inline]
+    fn _consume(&mut self, _g: Guard, n: usize) -> Option<Self::Buffer> {
+        let m = self.input().mark();
+
+        for _ in 0..n {
+            if self.next(true).is_none() {
+                self.rewind(m);
+
+                return None;
+            }
+        }
+
+        Some(Utf8Buffer(self.input().consume_from(m)))
+    }
+
+    #[inline]
+    fn _consume_while<F>(&mut self, _g: Guard, mut f: F) -> Self::Buffer
+    where
+        F: FnMut(Self::Token) -> bool,
+    {
+        let m = self.input().mark();
+
+        while let Some(c) = self.next(false) {
+            if !f(c) {
+                break;
+            }
+
+            self.next(true);
+        }
+
+        Utf8Buffer(self.input().consume_from(m))
+    }
+
+    #[inline]
+    fn _consume_from(&mut self, _g: Guard, m: Self::Marker) -> Self::Buffer {
+        Utf8Buffer(self.input().consume_from(m))
+    }
+
+    /// Consumes all characters up to the end of the input or the first invalid
+    /// byte sequence.
+    #[inline]
+    fn _consume_remaining(&mut self, g: Guard) -> Self::Buffer {
+        self._consume_while(g, |_| true)
+    }
+
+    #[inline]
+    fn _mark(&self, _g: Guard) -> Self::Marker {
+        self.input
+            .as_ref()
+            .expect("Utf8Input used after a panic")
+            .mark()
+    }
+
+    #[inline]
+    fn _restore(mut self, _g: Guard, m: Self::Marker) -> Self {
+        self.rewind(m);
+
+        self
+    }
+
+    #[cfg(feature = "trace")]
+    #[inline]
+    fn _trace(&self, _g: Guard, name: &'static str, kind: crate::trace::EventKind) {
+        if let Some(ref i) = self.input {
+            i.trace(name, kind)
+        }
+    }
+}
+
+/// A sequence of characters consumed from `Utf8Input`, wrapping the buffer of
+/// their UTF-8 encoded bytes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Utf8Buffer<B>(B);
+
+impl<B> IntoInner for Utf8Buffer<B> {
+    /// The buffer of UTF-8 encoded bytes.
+    type Inner = B;
+
+    fn into_inner(self) -> Self::Inner {
+        self.0
+    }
+}
+
+/// Incremental decoder for the already validated contents of a `Utf8Buffer`.
+#[derive(Default)]
+struct Decoder {
+    c: u32,
+    left: u8,
+}
+
+impl Decoder {
+    #[inline]
+    fn push(&mut self, b: u8) -> Option<char> {
+        if b & 0xc0 == 0x80 {
+            self.c = (self.c << 6) | u32::from(b & 0x3f);
+            self.left -= 1;
+        } else {
+            let (left, c) = match b {
+                0x00..=0x7f => (0, b),
+                0xc0..=0xdf => (1, b & 0x1f),
+                0xe0..=0xef => (2, b & 0x0f),
+                _ => (3, b & 0x07),
+            };
+
+            self.c = u32::from(c);
+            self.left = left;
+        }
+
+        if self.left == 0 {
+            char::from_u32(self.c)
+        } else {
+            None
+        }
+    }
+}
+
+impl<B: Buffer<Token = u8>> Buffer for Utf8Buffer<B> {
+    type Token = char;
+
+    fn fold<A, F>(self, init: A, mut f: F) -> A
+    where
+        F: FnMut(A, Self::Token) -> A,
+    {
+        let mut d = Decoder::default();
+
+        self.0.fold(init, |acc, b| match d.push(b) {
+            Some(c) => f(acc, c),
+            None => acc,
+        })
+    }
+
+    fn iterate<F>(&self, mut f: F)
+    where
+        F: FnMut(Self::Token),
+    {
+        let mut d = Decoder::default();
+
+        self.0.iterate(|b| {
+            if let Some(c) = d.push(b) {
+                f(c)
+            }
+        })
+    }
+
+    fn len(&self) -> usize {
+        let mut n = 0;
+
+        self.0.iterate(|b| {
+            if b & 0xc0 != 0x80 {
+                n += 1;
+            }
+        });
+
+        n
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[cfg(feature = "std")]
+    fn to_vec(&self) -> Vec<Self::Token> {
+        let mut v = Vec::new();
+
+        self.iterate(|c| v.push(c));
+
+        v
+    }
+
+    #[cfg(feature = "std")]
+    fn into_vec(self) -> Vec<Self::Token> {
+        self.to_vec()
+    }
+}
+
+/// Runs the character parser `f` on the UTF-8 decoded bytes of `i`.
+///
+/// If `f` fails after an invalid byte sequence was encountered the error is
+/// replaced with the `Utf8Error`. If the sequence was truncated by the end of
+/// an `InputBuf` it will also be marked as incomplete, making a stream retry
+/// once more data is available.
+#[inline]
+pub fn utf8<I: U8Input, T, E, F>(i: I, f: F) -> ParseResult<I, T, E>
+where
+    F: FnOnce(Utf8Input<I>) -> ParseResult<Utf8Input<I>, T, E>,
+    E: From<Utf8Error>,
+{
+    let (i, r) = f(Utf8Input::new(i)).into_inner();
+    let (i, error) = i.into_inner();
+
+    match (r, error) {
+        (Ok(t), _) => i.ret(t),
+        (Err(_), Some(e)) => i.err(e.into()),
+        (Err(e), None) => i.err(e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use super::*;
+    use crate::buffer::{FixedSizeBuffer, InputBuf, Needed, Source, Stream, StreamError};
+    use crate::combinators::count;
+    use crate::parsers::{any, string, take_while, Error};
+
+    #[derive(Debug, PartialEq)]
+    enum E {
+        Parse(Error<char>),
+        Utf8(Utf8Error),
+    }
+
+    impl From<Error<char>> for E {
+        fn from(e: Error<char>) -> Self {
+            E::Parse(e)
+        }
+    }
+
+    impl From<Utf8Error> for E {
+        fn from(e: Utf8Error) -> Self {
+            E::Utf8(e)
+        }
+    }
+
+    fn chars<I: U8Input>(i: I) -> ParseResult<I, String, E> {
+        utf8(i, |i| count(i, 3, any).map_err(E::from))
+    }
+
+    #[test]
+    fn primitives() {
+        let mut i = Utf8Input::new("aé€😀".as_bytes());
+
+        assert_eq!(i.peek(), Some('a'));
+        assert_eq!(i.pop(), Some('a'));
+        assert_eq!(i.peek(), Some('é'));
+
+        let m = i.mark();
+
+        assert_eq!(i.consume(2).map(|b| b.to_vec()), Some(vec!['é', '€']));
+        assert_eq!(i.consume(2), None);
+        assert_eq!(i.pop(), Some('😀'));
+        assert_eq!(i.pop(), None);
+
+        let mut i = i.restore(m);
+        let b = i.consume_while(|c| c != '😀');
+
+        assert_eq!(b.len(), 2);
+        assert_eq!(b.into_inner(), "é€".as_bytes());
+        assert_eq!(i.consume_remaining().to_vec(), vec!['😀']);
+        assert_eq!(i.into_inner(), (&b""[..], None));
+    }
+
+    #[test]
+    fn invalid() {
+        let cases: &[(&[u8], Option<usize>)] = &[
+            (b"\x80", Some(1)),
+            (b"\xc0\xaf", Some(1)),
+            (b"\xc3\x28", Some(1)),
+            (b"\xe0\x80\xaf", Some(1)),
+            (b"\xed\xa0\x80", Some(1)),
+            (b"\xe2\x82\x28", Some(2)),
+            (b"\xf0\x9f\x98\x28", Some(3)),
+            (b"\xf4\x90\x80\x80", Some(1)),
+            (b"\xf8", Some(1)),
+            (b"\xe2\x82", None),
+            (b"\xf0\x9f\x98", None),
+        ];
+
+        for &(s, len) in cases {
+            let mut input = b"ab".to_vec();
+
+            input.extend_from_slice(s);
+
+            let mut i = Utf8Input::new(&input[..]);
+
+            assert_eq!(i.consume_remaining().to_vec(), vec!['a', 'b'], "{:?}", s);
+            assert_eq!(i.peek(), None);
+            assert_eq!(i.error().map(|e| e.error_len()), Some(len), "{:?}", s);
+            assert_eq!(i.into_inner().0, s);
+        }
+
+        // Failing to decode does not consume anything
+        let mut i = Utf8Input::new(&b"\xc3\x28"[..]);
+
+        assert_eq!(i.pop(), None);
+        assert_eq!(
+            i.into_inner(),
+            (&b"\xc3\x28"[..], Some(Utf8Error { error_len: Some(1) }))
+        );
+
+        assert_eq!(
+            Utf8Error { error_len: Some(2) }.to_string(),
+            "invalid utf-8 sequence of 2 bytes"
+        );
+        assert_eq!(
+            Utf8Error { error_len: None }.to_string(),
+            "incomplete utf-8 byte sequence"
+        );
+    }
+
+    #[test]
+    fn run() {
+        assert_eq!(
+            chars("hé😀".as_bytes()).into_inner(),
+            (&b""[..], Ok("hé😀".to_owned()))
+        );
+        assert_eq!(
+            chars(&b"h\xffi"[..]).into_inner(),
+            (
+                &b"\xffi"[..],
+                Err(E::Utf8(Utf8Error { error_len: Some(1) }))
+            )
+        );
+
+        // The error is only reported if the parser fails
+        let r = utf8(&b"ab\xff"[..], |i| {
+            take_while(i, |c| c != 'b').map_err(E::from)
+        });
+
+        assert_eq!(r.into_inner(), (&b"b\xff"[..], Ok(Utf8Buffer(&b"a"[..]))));
+
+        let r = utf8(&b"\xc3\xa9t\xc3\xa9"[..], |i| {
+            string(i, &['é', 't', 'é']).map_err(E::from)
+        });
+
+        assert_eq!(
+            r.map(|b| b.to_vec()).into_inner().1,
+            Ok(vec!['é', 't', 'é'])
+        );
+    }
+
+    #[test]
+    fn incomplete() {
+        let (i, r) = chars(InputBuf::new("a€".as_bytes()[..2].as_ref())).into_inner();
+
+        assert!(i.is_incomplete());
+        assert_eq!(r, Err(E::Utf8(Utf8Error { error_len: None })));
+    }
+
+    /// Reader returning one byte at a time.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> io::Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.0 = rest;
+
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn stream() {
+        let s = "añ€😀z";
+        let mut src = Source::from_read(Trickle(s.as_bytes()), FixedSizeBuffer::new());
+        let mut v = Vec::new();
+
+        loop {
+            match src.parse(|i| utf8(i, |i| any(i).map_err(E::from))) {
+                Ok(c) => v.push(c),
+                Err(StreamError::Retry(_)) => {}
+                Err(StreamError::EndOfInput) => break,
+                Err(e) => panic!("{:?}", e),
+            }
+        }
+
+        assert_eq!(v.into_iter().collect::<String>(), s);
+
+        let mut src = Source::from_read(Trickle(b"a\xe2\x82"), FixedSizeBuffer::new());
+
+        loop {
+            match src.parse(|i| utf8(i, |i| any(i).map_err(E::from))) {
+                Ok(c) => assert_eq!(c, 'a'),
+                Err(StreamError::Retry(_)) => {}
+                Err(e) => {
+                    assert_eq!(e, StreamError::Incomplete(Some(Needed(1))));
+
+                    break;
+                }
+            }
+        }
+    }
+}
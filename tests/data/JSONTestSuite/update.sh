@@ -0,0 +1,21 @@
+#!/bin/sh
+# Replaces the vendored files with the full `test_parsing` directory and the
+# license of JSONTestSuite at the given revision (default `master`).
+set -eu
+
+rev="${1:-master}"
+dir="$(cd "$(dirname "$0")" && pwd)"
+tmp="$(mktemp -d)"
+trap 'rm -rf "$tmp"' EXIT
+
+# Downloaded to a file first, so that a failed download stops the script
+curl -sSfL -o "$tmp/suite.tar.gz" "https://github.com/nst/JSONTestSuite/archive/$rev.tar.gz"
+tar -xzf "$tmp/suite.tar.gz" -C "$tmp"
+
+src="$(echo "$tmp"/JSONTestSuite-*)"
+
+rm -rf "$dir/test_parsing"
+cp -R "$src/test_parsing" "$dir/test_parsing"
+cp "$src/LICENSE" "$dir/LICENSE"
+
+echo "Vendored $(ls "$dir/test_parsing" | wc -l) files from JSONTestSuite $rev"
@@ -0,0 +1,95 @@
+#![cfg(feature = "json")]
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+use chomp1::buffer::{GrowingBuffer, Source, StreamError};
+use chomp1::formats::json::{document, EventParser};
+use chomp1::parse_only;
+
+/// Reader yielding a single byte per call, forcing the event parser to retry
+/// at every possible position.
+struct Trickle<'a>(&'a [u8]);
+
+impl<'a> Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0.is_empty() || buf.is_empty() {
+            return Ok(0);
+        }
+
+        buf[0] = self.0[0];
+        self.0 = &self.0[1..];
+
+        Ok(1)
+    }
+}
+
+fn suite() -> Vec<(String, Vec<u8>)> {
+    let dir =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/data/JSONTestSuite/test_parsing");
+
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .expect("test_parsing directory")
+        .map(|e| {
+            let path = e.expect("directory entry").path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+
+            (name, fs::read(&path).expect("test file"))
+        })
+        .collect();
+
+    files.sort();
+
+    files
+}
+
+/// Parses a complete document through the event parser, requiring exactly one
+/// top-level value.
+fn accepted_by_events(data: &[u8]) -> bool {
+    let mut src = Source::from_read(Trickle(data), GrowingBuffer::new());
+    let mut p = EventParser::new();
+    let mut values = 0;
+
+    loop {
+        let top = p.depth() == 0;
+
+        match p.next_event(&mut src) {
+            Ok(Some(_)) => {
+                if top {
+                    values += 1;
+                }
+            }
+            Ok(None) => return values == 1,
+            Err(StreamError::Retry(_)) => {}
+            Err(_) => return false,
+        }
+    }
+}
+
+#[test]
+fn json_test_suite() {
+    let files = suite();
+    let mut failures = Vec::new();
+
+    assert!(!files.is_empty());
+
+    for (name, data) in &files {
+        let dom = parse_only(document, data).is_ok();
+        let events = accepted_by_events(data);
+
+        if dom != events {
+            failures.push(format!("{}: document {} but events {}", name, dom, events));
+        }
+
+        if name.starts_with("y_") && !dom {
+            failures.push(format!("{}: rejected", name));
+        }
+
+        if name.starts_with("n_") && dom {
+            failures.push(format!("{}: accepted", name));
+        }
+    }
+
+    assert!(failures.is_empty(), "{:#?}", failures);
+}